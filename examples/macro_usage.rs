@@ -93,6 +93,7 @@ mod tests {
             background_compaction: false,
             background_compaction_interval: std::time::Duration::from_secs(1),
             enable_wal: false,
+            ..rust_solo_all_db::engine::LSMConfig::default()
         };
         
         let mut db = rust_solo_all_db::engine::LSMTree::with_config(config).unwrap();
@@ -122,6 +123,158 @@ mod tests {
         assert!(not_found.is_none());
     }
 
+    #[test]
+    fn test_find_by_indexed_field() {
+        let temp_dir = tempdir().unwrap();
+        let config = rust_solo_all_db::engine::LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..rust_solo_all_db::engine::LSMConfig::default()
+        };
+        let mut db = rust_solo_all_db::engine::LSMTree::with_config(config).unwrap();
+
+        User::new("u1".to_string(), "Alice".to_string(), "a1@example.com".to_string(), 1).save(&mut db).unwrap();
+        User::new("u2".to_string(), "Alice".to_string(), "a2@example.com".to_string(), 2).save(&mut db).unwrap();
+        User::new("u3".to_string(), "Bob".to_string(), "bob@example.com".to_string(), 3).save(&mut db).unwrap();
+
+        let mut ids: Vec<String> = User::find_by_name(&db, "Alice").unwrap().into_iter().map(|u| u.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["u1", "u2"]);
+        assert_eq!(User::find_by_email(&db, "bob@example.com").unwrap()[0].id, "u3");
+        // Without #[table_name] the table is the lowercased struct name
+        assert!(db.get("user:id:u3").unwrap().is_some());
+        assert!(db.get("u3").unwrap().is_none());
+
+        // Renaming a user moves it to the new index entry and out of the old one
+        User::new("u2".to_string(), "Carol".to_string(), "a2@example.com".to_string(), 2).save(&mut db).unwrap();
+        let alices: Vec<String> = User::find_by_name(&db, "Alice").unwrap().into_iter().map(|u| u.id).collect();
+        assert_eq!(alices, vec!["u1"]);
+        assert_eq!(User::find_by_name(&db, "Carol").unwrap()[0].id, "u2");
+
+        // Saving again without changes doesn't duplicate the entry
+        User::new("u1".to_string(), "Alice".to_string(), "a1@example.com".to_string(), 1).save(&mut db).unwrap();
+        assert_eq!(User::find_by_name(&db, "Alice").unwrap().len(), 1);
+
+        // Deleting drops the record from its index entries
+        User::delete_by_id(&mut db, "u1").unwrap();
+        assert!(User::find_by_name(&db, "Alice").unwrap().is_empty());
+        assert!(db.get("user:name:Alice").unwrap().is_none());
+        assert!(!User::delete_by_id(&mut db, "u1").unwrap());
+    }
+
+    #[test]
+    fn test_record_keys_dont_collide_with_index_entries() {
+        let temp_dir = tempdir().unwrap();
+        let config = rust_solo_all_db::engine::LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..rust_solo_all_db::engine::LSMConfig::default()
+        };
+        let mut db = rust_solo_all_db::engine::LSMTree::with_config(config).unwrap();
+
+        User::new("u1".to_string(), "Alice".to_string(), "a1@example.com".to_string(), 1).save(&mut db).unwrap();
+        // An id shaped like an index key must not land on the index entry
+        User::new("user:name:Alice".to_string(), "Bob".to_string(), "b@example.com".to_string(), 2).save(&mut db).unwrap();
+        User::new("u2".to_string(), "Alice".to_string(), "a2@example.com".to_string(), 3).save(&mut db).unwrap();
+
+        let mut ids: Vec<String> = User::find_by_name(&db, "Alice").unwrap().into_iter().map(|u| u.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["u1", "u2"]);
+        assert_eq!(User::find_by_id(&db, "user:name:Alice").unwrap().unwrap().name, "Bob");
+        assert!(User::delete_by_id(&mut db, "user:name:Alice").unwrap());
+        assert_eq!(User::find_by_name(&db, "Alice").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_migrate_legacy_record() {
+        let temp_dir = tempdir().unwrap();
+        let config = rust_solo_all_db::engine::LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..rust_solo_all_db::engine::LSMConfig::default()
+        };
+        let mut db = rust_solo_all_db::engine::LSMTree::with_config(config).unwrap();
+
+        // Earlier versions stored records under the bare primary key
+        let legacy = User::new("u1".to_string(), "Alice".to_string(), "a1@example.com".to_string(), 1);
+        db.insert("u1".to_string(), serde_json::to_string(&legacy).unwrap()).unwrap();
+        assert!(User::find_by_id(&db, "u1").unwrap().is_none());
+
+        assert!(User::migrate_legacy_record(&mut db, "u1").unwrap());
+        assert!(db.get("u1").unwrap().is_none());
+        assert_eq!(User::find_by_id(&db, "u1").unwrap().unwrap().name, "Alice");
+        assert_eq!(User::find_by_name(&db, "Alice").unwrap().len(), 1);
+        assert!(!User::migrate_legacy_record(&mut db, "u1").unwrap());
+    }
+
+    #[test]
+    fn test_update_and_upsert() {
+        let temp_dir = tempdir().unwrap();
+        let config = rust_solo_all_db::engine::LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..rust_solo_all_db::engine::LSMConfig::default()
+        };
+        let mut db = rust_solo_all_db::engine::LSMTree::with_config(config).unwrap();
+
+        let user = User::new("u1".to_string(), "Alice".to_string(), "alice@example.com".to_string(), 1);
+        assert!(!User::exists(&db, "u1").unwrap());
+        assert!(matches!(user.update(&mut db), Err(rust_solo_all_db::DbError::KeyNotFound(_))));
+        assert!(User::find_by_id(&db, "u1").unwrap().is_none());
+
+        // upsert inserts when missing, then replaces
+        user.upsert(&mut db).unwrap();
+        assert!(User::exists(&db, "u1").unwrap());
+        let renamed = User::new("u1".to_string(), "Alicia".to_string(), "alice@example.com".to_string(), 1);
+        renamed.upsert(&mut db).unwrap();
+        assert_eq!(User::find_by_id(&db, "u1").unwrap().unwrap().name, "Alicia");
+
+        // update succeeds once the record exists and keeps the indexes current
+        let updated = User::new("u1".to_string(), "Alice".to_string(), "alice@example.com".to_string(), 2);
+        updated.update(&mut db).unwrap();
+        assert_eq!(User::find_by_id(&db, "u1").unwrap().unwrap().created_at, 2);
+        assert!(User::find_by_name(&db, "Alicia").unwrap().is_empty());
+        assert_eq!(User::find_by_name(&db, "Alice").unwrap().len(), 1);
+
+        User::delete_by_id(&mut db, "u1").unwrap();
+        assert!(!User::exists(&db, "u1").unwrap());
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, QueryBuilder)]
+    #[table_name = "users_v2"]
+    struct RenamedUser {
+        #[primary_key]
+        id: String,
+        #[indexed]
+        name: String,
+    }
+
+    #[test]
+    fn test_table_name_override() {
+        let temp_dir = tempdir().unwrap();
+        let config = rust_solo_all_db::engine::LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..rust_solo_all_db::engine::LSMConfig::default()
+        };
+        let mut db = rust_solo_all_db::engine::LSMTree::with_config(config).unwrap();
+
+        let user = RenamedUser { id: "u1".to_string(), name: "Alice".to_string() };
+        user.save(&mut db).unwrap();
+
+        assert!(db.get("users_v2:id:u1").unwrap().is_some());
+        assert_eq!(db.get("users_v2:name:Alice").unwrap(), Some(r#"["u1"]"#.to_string()));
+        assert!(db.get("renameduser:id:u1").unwrap().is_none());
+        assert_eq!(RenamedUser::find_by_id(&db, "u1").unwrap().unwrap().name, "Alice");
+        assert_eq!(RenamedUser::find_by_name(&db, "Alice").unwrap().len(), 1);
+    }
+
     #[test]
     fn test_database_macro() {
         let db_result = database! {
@@ -1,9 +1,15 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Data, Fields};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Data, DeriveInput, Expr, ExprLit, Fields, Ident, Lit, LitStr, Meta, MetaNameValue, Token};
 
 /// Derive macro that generates query builder methods for structs
-#[proc_macro_derive(QueryBuilder, attributes(primary_key, indexed))]
+///
+/// Records are stored under `{table}:id:{pk}` and each `#[indexed]` field under
+/// `{table}:{field}:{value}`, built with `Key` so separators inside values are escaped and
+/// the two can't collide. Records saved by earlier versions of the derive sit under their
+/// bare primary key; `migrate_legacy_record(db, id)` moves one to the namespaced key.
+#[proc_macro_derive(QueryBuilder, attributes(primary_key, indexed, table_name))]
 pub fn derive_query_builder(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
@@ -25,16 +31,166 @@ pub fn derive_query_builder(input: TokenStream) -> TokenStream {
         panic!("QueryBuilder requires a field marked with #[primary_key]")
     };
 
+    // Records live under `{table}:id:{pk}`. Secondary indexes: each `#[indexed]` value maps
+    // `{table}:{field}:{value}` to a JSON list of the primary keys holding it, kept in step
+    // with the record on save and delete
+    let table_name = match table_name(&input) {
+        Ok(table) => table.unwrap_or_else(|| name.to_string().to_lowercase()),
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let indexed_fields: Vec<_> = fields
+        .iter()
+        .filter(|field| field.attrs.iter().any(|attr| attr.path().is_ident("indexed")))
+        .map(|field| field.ident.as_ref().unwrap())
+        .collect();
+    if let Some(id_field) = indexed_fields.iter().find(|ident| **ident == "id") {
+        return syn::Error::new_spanned(id_field, "QueryBuilder can't index a field named `id`: its entries would share keys with the records")
+            .to_compile_error()
+            .into();
+    }
+    let indexed_names: Vec<String> = indexed_fields.iter().map(|ident| ident.to_string()).collect();
+    let find_by_methods: Vec<_> = indexed_fields.iter().map(|ident| format_ident!("find_by_{}", ident)).collect();
+
+    let index_helpers = if indexed_fields.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            // Primary keys stored under one index entry
+            fn query_builder_index_ids(db: &rust_solo_all_db::engine::LSMTree, key: &str) -> rust_solo_all_db::DbResult<Vec<String>> {
+                match db.get(key)? {
+                    Some(value) => serde_json::from_str(&value)
+                        .map_err(|e| rust_solo_all_db::DbError::InvalidOperation(e.to_string())),
+                    None => Ok(Vec::new()),
+                }
+            }
+
+            // Index writes that move `pk` from the entries of `old` to those of `new`
+            fn query_builder_index_ops(
+                db: &rust_solo_all_db::engine::LSMTree,
+                pk: &str,
+                old: Option<&#name>,
+                new: Option<&#name>,
+            ) -> rust_solo_all_db::DbResult<Vec<rust_solo_all_db::WriteOp>> {
+                let mut ops = Vec::new();
+                #(
+                    let old_value = old.map(|item| item.#indexed_fields.to_string());
+                    let new_value = new.map(|item| item.#indexed_fields.to_string());
+                    if old_value != new_value {
+                        if let Some(value) = old_value {
+                            let key = rust_solo_all_db::Key::new(#table_name).field(#indexed_names).value(&value).into_string();
+                            let mut ids = Self::query_builder_index_ids(db, &key)?;
+                            ids.retain(|id| id != pk);
+                            if ids.is_empty() {
+                                ops.push(rust_solo_all_db::WriteOp::Delete(key));
+                            } else {
+                                let ids = serde_json::to_string(&ids)
+                                    .map_err(|e| rust_solo_all_db::DbError::InvalidOperation(e.to_string()))?;
+                                ops.push(rust_solo_all_db::WriteOp::Put(key, ids));
+                            }
+                        }
+                        if let Some(value) = new_value {
+                            let key = rust_solo_all_db::Key::new(#table_name).field(#indexed_names).value(&value).into_string();
+                            let mut ids = Self::query_builder_index_ids(db, &key)?;
+                            if !ids.iter().any(|id| id == pk) {
+                                ids.push(pk.to_string());
+                            }
+                            let ids = serde_json::to_string(&ids)
+                                .map_err(|e| rust_solo_all_db::DbError::InvalidOperation(e.to_string()))?;
+                            ops.push(rust_solo_all_db::WriteOp::Put(key, ids));
+                        }
+                    }
+                )*
+                Ok(ops)
+            }
+
+            #(
+                pub fn #find_by_methods(db: &rust_solo_all_db::engine::LSMTree, value: &str) -> rust_solo_all_db::DbResult<Vec<#name>> {
+                    let key = rust_solo_all_db::Key::new(#table_name).field(#indexed_names).value(&value).into_string();
+                    let mut items = Vec::new();
+                    for id in Self::query_builder_index_ids(db, &key)? {
+                        if let Some(item) = Self::find_by_id(db, &id)? {
+                            items.push(item);
+                        }
+                    }
+                    Ok(items)
+                }
+            )*
+        }
+    };
+
+    // The record and its index entries are written in one transaction so they can't diverge
+    let (save_index_ops, delete_index_ops) = if indexed_fields.is_empty() {
+        (quote! { Vec::new() }, quote! { Vec::new() })
+    } else {
+        (
+            quote! {
+                {
+                    let previous = Self::find_by_id(db, &pk)?;
+                    Self::query_builder_index_ops(db, &pk, previous.as_ref(), Some(self))?
+                }
+            },
+            quote! { Self::query_builder_index_ops(db, id, previous.as_ref(), None)? },
+        )
+    };
+
     let expanded = quote! {
         impl #name {
-            pub fn save(&self, db: &mut rust_solo_all_db::engine::LSMTree) -> rust_solo_all_db::DbResult<()> {
+            fn query_builder_record_key(id: &str) -> String {
+                rust_solo_all_db::Key::new(#table_name).field("id").value(id).into_string()
+            }
+
+            // Writes that store the record and move its index entries
+            fn query_builder_save_ops(&self, db: &rust_solo_all_db::engine::LSMTree) -> rust_solo_all_db::DbResult<Vec<rust_solo_all_db::WriteOp>> {
                 let value = serde_json::to_string(self)
                     .map_err(|e| rust_solo_all_db::DbError::InvalidOperation(e.to_string()))?;
-                db.insert(self.#primary_key_name.clone(), value)
+                let pk = self.#primary_key_name.clone();
+                let mut ops: Vec<rust_solo_all_db::WriteOp> = #save_index_ops;
+                ops.push(rust_solo_all_db::WriteOp::Put(Self::query_builder_record_key(&pk), value));
+                Ok(ops)
+            }
+
+            pub fn save(&self, db: &mut rust_solo_all_db::engine::LSMTree) -> rust_solo_all_db::DbResult<()> {
+                let ops = self.query_builder_save_ops(db)?;
+                db.transaction(ops)
+            }
+
+            // Move a record saved under its bare primary key by an earlier version of the
+            // derive to its namespaced key, indexing it on the way; false if there's none
+            pub fn migrate_legacy_record(db: &mut rust_solo_all_db::engine::LSMTree, id: &str) -> rust_solo_all_db::DbResult<bool> {
+                let Some(value) = db.get(id)? else {
+                    return Ok(false);
+                };
+                let item: #name = serde_json::from_str(&value)
+                    .map_err(|e| rust_solo_all_db::DbError::InvalidOperation(e.to_string()))?;
+                // A bare key that happens to hold another record isn't a legacy copy of `id`
+                if item.#primary_key_name != id {
+                    return Ok(false);
+                }
+                let mut ops = item.query_builder_save_ops(db)?;
+                ops.push(rust_solo_all_db::WriteOp::Delete(id.to_string()));
+                db.transaction(ops)?;
+                Ok(true)
+            }
+
+            // Replace an existing record; fails with KeyNotFound if there's nothing to update
+            pub fn update(&self, db: &mut rust_solo_all_db::engine::LSMTree) -> rust_solo_all_db::DbResult<()> {
+                if !Self::exists(db, &self.#primary_key_name)? {
+                    return Err(rust_solo_all_db::DbError::KeyNotFound(self.#primary_key_name.to_string()));
+                }
+                self.save(db)
+            }
+
+            // Insert the record, or replace it if the primary key is already taken
+            pub fn upsert(&self, db: &mut rust_solo_all_db::engine::LSMTree) -> rust_solo_all_db::DbResult<()> {
+                self.save(db)
+            }
+
+            pub fn exists(db: &rust_solo_all_db::engine::LSMTree, id: &str) -> rust_solo_all_db::DbResult<bool> {
+                db.contains_key(&Self::query_builder_record_key(id))
             }
 
             pub fn find_by_id(db: &rust_solo_all_db::engine::LSMTree, id: &str) -> rust_solo_all_db::DbResult<Option<#name>> {
-                match db.get(id)? {
+                match db.get(&Self::query_builder_record_key(id))? {
                     Some(value) => {
                         let item: #name = serde_json::from_str(&value)
                             .map_err(|e| rust_solo_all_db::DbError::InvalidOperation(e.to_string()))?;
@@ -45,18 +201,39 @@ pub fn derive_query_builder(input: TokenStream) -> TokenStream {
             }
 
             pub fn delete_by_id(db: &mut rust_solo_all_db::engine::LSMTree, id: &str) -> rust_solo_all_db::DbResult<bool> {
-                match db.delete(id) {
-                    Ok(_) => Ok(true),
-                    Err(rust_solo_all_db::DbError::KeyNotFound(_)) => Ok(false),
-                    Err(e) => Err(e),
+                let previous = Self::find_by_id(db, id)?;
+                if previous.is_none() {
+                    return Ok(false);
                 }
+                let mut ops: Vec<rust_solo_all_db::WriteOp> = #delete_index_ops;
+                ops.push(rust_solo_all_db::WriteOp::Delete(Self::query_builder_record_key(id)));
+                db.transaction(ops)?;
+                Ok(true)
             }
+
+            #index_helpers
         }
     };
 
     TokenStream::from(expanded)
 }
 
+// The `#[table_name = "..."]` override, if the struct sets one
+fn table_name(input: &DeriveInput) -> syn::Result<Option<String>> {
+    for attr in &input.attrs {
+        if !attr.path().is_ident("table_name") {
+            continue;
+        }
+        match &attr.meta {
+            Meta::NameValue(MetaNameValue { value: Expr::Lit(ExprLit { lit: Lit::Str(name), .. }), .. }) => {
+                return Ok(Some(name.value()));
+            }
+            _ => return Err(syn::Error::new_spanned(attr, "QueryBuilder expects #[table_name = \"...\"]")),
+        }
+    }
+    Ok(None)
+}
+
 /// Simple table definition macro
 #[proc_macro]
 pub fn table(_input: TokenStream) -> TokenStream {
@@ -102,19 +279,110 @@ pub fn database(_input: TokenStream) -> TokenStream {
                 background_compaction: false,
                 background_compaction_interval: std::time::Duration::from_secs(1),
                 enable_wal: true,
+                ..rust_solo_all_db::engine::LSMConfig::default()
             };
             rust_solo_all_db::engine::LSMTree::with_config(config)
         }
     }.into()
 }
 
-/// Simple query macro
+/// Run a SQL statement against a database, binding `{name}` placeholders to local variables
+///
+/// Each placeholder is replaced at runtime by the variable's value rendered with
+/// `SqlLiteral`, so strings are quoted and escaped rather than spliced in raw. `{{` and
+/// `}}` stand for literal braces. Evaluates to `DbResult<QueryResult>`.
+///
+/// ```ignore
+/// let user_id = "user123";
+/// let result = query!(db, "SELECT * FROM users WHERE key = {user_id}");
+/// ```
 #[proc_macro]
-pub fn query(_input: TokenStream) -> TokenStream {
+pub fn query(input: TokenStream) -> TokenStream {
+    let QueryInput { db, sql } = parse_macro_input!(input as QueryInput);
+
+    let pieces = match split_placeholders(&sql.value()) {
+        Ok(pieces) => pieces,
+        Err(message) => return syn::Error::new(sql.span(), message).to_compile_error().into(),
+    };
+    let pushes = pieces.iter().map(|piece| match piece {
+        SqlPiece::Text(text) => quote! { sql.push_str(#text); },
+        SqlPiece::Param(name) => {
+            let ident = Ident::new(name, sql.span());
+            quote! { sql.push_str(&rust_solo_all_db::query::SqlLiteral::to_sql_literal(&#ident)); }
+        }
+    });
+
     quote! {
         {
-            // Simple query placeholder - in real implementation would parse SQL
-            Ok("Query result".to_string())
+            let mut sql = String::new();
+            #(#pushes)*
+            rust_solo_all_db::query::SQLParser::new(&sql)
+                .parse()
+                .and_then(|statement| rust_solo_all_db::query::QueryExecutor::new(&mut #db).execute(statement))
         }
     }.into()
 }
+
+struct QueryInput {
+    db: Expr,
+    sql: LitStr,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let db = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let sql = input.parse()?;
+        Ok(QueryInput { db, sql })
+    }
+}
+
+enum SqlPiece {
+    Text(String),
+    Param(String),
+}
+
+// Split a statement into literal text and `{name}` placeholders
+fn split_placeholders(sql: &str) -> Result<Vec<SqlPiece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = sql.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("Unclosed placeholder {{{}", name)),
+                    }
+                }
+                let name = name.trim().to_string();
+                if syn::parse_str::<Ident>(&name).is_err() {
+                    return Err(format!("Placeholder {{{}}} must name a variable", name));
+                }
+                if !text.is_empty() {
+                    pieces.push(SqlPiece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(SqlPiece::Param(name));
+            }
+            '}' => return Err("Unmatched } in query; write }} for a literal brace".to_string()),
+            _ => text.push(ch),
+        }
+    }
+
+    if !text.is_empty() {
+        pieces.push(SqlPiece::Text(text));
+    }
+    Ok(pieces)
+}
@@ -25,7 +25,7 @@ pub enum Commands {
 
     // Load CSV data into the database
     Load {
-        // Path to CSV file
+        // Path to CSV file, or - to read from stdin
         file: PathBuf,
 
         // Key column name or index
@@ -46,6 +46,12 @@ pub enum Commands {
         batch_size: Option<usize>,
     },
 
+    // Replay a dump written by `export --with-deletes` (CSV or .jsonl), re-applying deletes
+    Import {
+        // Path to the dump file
+        file: PathBuf,
+    },
+
     Query {
         // SQL query to execute
         sql: String,
@@ -81,6 +87,10 @@ pub enum Commands {
         // Refresh interval in seconds for live mode
         #[arg(short, long, default_value = "1")]
         interval: usize,
+
+        // Output format (table, json, prometheus)
+        #[arg(short, long, default_value = "table")]
+        format: String,
     },
 
     // Database maintenance operations
@@ -101,6 +111,17 @@ pub enum MaintenanceOps {
     // Force compaction of all levels
     CompactAll,
 
+    // Compact a single level into the one below it
+    CompactLevel {
+        level: usize,
+    },
+
+    // Show what compaction would do without running it
+    Plan,
+
+    // Show each level's SSTables with their key ranges and sizes
+    Distribution,
+
     // Vacuum deleted entries
     Vacuum,
 
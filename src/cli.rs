@@ -1,28 +1,39 @@
 // Command-line interface for the database
 use crate::engine::lsm::{LSMTree, LSMConfig};
-use crate::query::{QueryExecutor, SQLParser};
+use crate::config::DatabaseConfig;
+use crate::query::{QueryCache, QueryExecutor, SQLParser};
 use crate::DbResult;
 use crate::engine::ETLLoader;
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 pub struct DatabaseCLI {
     db: LSMTree,
+    config: DatabaseConfig, // Query limits and `load` defaults for the session
+    query_cache: Option<Arc<QueryCache>>, // Shared by every `query` command in the session
 }
 
 impl DatabaseCLI {
     pub fn new() -> DbResult<Self> {
-        let mut config = LSMConfig::default();
-        config.memtable_size_limit = 100; // Smaller limit for CLI demo
-        config.data_dir = PathBuf::from("data/runtime");
+        Self::with_config(DatabaseConfig::default())
+    }
 
-        let db = LSMTree::with_config(config)?;
-        Ok(Self { db })
+    // Queries and loads follow `config`'s query and ETL settings. The session's own
+    // database still lives in data/runtime
+    pub fn with_config(config: DatabaseConfig) -> DbResult<Self> {
+        let mut lsm_config = LSMConfig::default();
+        lsm_config.memtable_size_limit = 100; // Smaller limit for CLI demo
+        lsm_config.data_dir = PathBuf::from("data/runtime");
+
+        let db = LSMTree::with_config(lsm_config)?;
+        let query_cache = QueryCache::for_config(&config.query).map(Arc::new);
+        Ok(Self { db, config, query_cache })
     }
 
-    pub fn run(&mut self) -> DbResult<()> {
+    pub fn run(mut self) -> DbResult<()> {
         println!("Welcome to the RustDB CLI!");
-        println!("Commands: insert <key> <value>, get <key>, delete <key>, load <csv_file> [key_col] [value_col], compact, autocompact, stats, flush, quit");
+        println!("Commands: insert <key> <value>, get <key>, delete <key>, exists <key>, range <start> <end>, scan [prefix], keys [limit], count, load <csv_file> [key_col] [value_col], export <csv_file>, import <dump_file>, compact, autocompact, stats, flush, quit");
         println!();
 
         loop {
@@ -55,8 +66,8 @@ impl DatabaseCLI {
             }
         }
 
-        // Flush any remaining data before exit
-        self.db.flush()?;
+        // Flush and shut down cleanly before exit
+        self.db.close()?;
         println!("Database flush. Sayonara!");
         Ok(())
     }
@@ -94,16 +105,25 @@ impl DatabaseCLI {
                     println!("Usage: delete <key");
                     return Ok(false);
                 }
-                if self.db.delete(parts[1])? {
+                if self.db.contains_key(parts[1])? && self.db.delete(parts[1])? {
                     println!("Deleted: {}", parts[1]);
                 } else {
                     println!("Key not found: {}", parts[1]);
                 }
             }
 
+            "exists" => {
+                if parts.len() != 2 {
+                    println!("Usage: exists <key>");
+                    return Ok(false);
+                }
+                println!("{}: {}", parts[1], self.db.contains_key(parts[1])?);
+            }
+
             "stats" => {
                 let stats = self.db.stats();
                 println!("{}", stats);
+                print!("{}", stats.levels);
             }
 
             "flush" => {
@@ -129,15 +149,22 @@ impl DatabaseCLI {
                     println!("       load <csv_file> --no-headers [key_column] [value_column]");
                     println!("       load <csv_file> --delimiter <char> [key_column] [value_column]");
                     println!("       load <csv_file> --recovery-mode [options]");
+                    println!("       load <csv_file> [options] --batch-size <n> --threads <n>");
                     return Ok(false);
                 }
 
+                // Flags override the configured ETL settings
+                let etl = &self.config.etl;
+
                 let file_path = parts[1];
                 let mut key_column = 0;
                 let mut value_column = 1;
-                let mut has_headers = true;
-                let mut delimiter = ',';
+                let mut has_headers = etl.has_headers;
+                let mut delimiter = etl.delimiter;
                 let mut recovery_mode = false;
+                let mut batch_size = etl.batch_size;
+                let mut threads = etl.parallel_threads;
+                let mut positional = 0;
                 let mut i = 2;
 
                 // Parse arguments
@@ -160,63 +187,178 @@ impl DatabaseCLI {
                             recovery_mode = true;
                             i += 1;
                         }
+                        flag @ ("--batch-size" | "--threads") => {
+                            match parts.get(i + 1).and_then(|arg| arg.parse::<usize>().ok()).filter(|&n| n > 0) {
+                                Some(n) if flag == "--batch-size" => batch_size = n,
+                                Some(n) => threads = n,
+                                None => {
+                                    println!("Error: {} requires a positive number", flag);
+                                    return Ok(false);
+                                }
+                            }
+                            i += 2;
+                        }
                         arg => {
-                            if key_column == 0 {
-                                key_column = arg.parse().unwrap_or(0);
-                            } else if value_column == 1 {
-                                value_column = arg.parse().unwrap_or(1);
+                            // Positional columns fill in order: key, then value
+                            match positional {
+                                0 => key_column = arg.parse().unwrap_or(0),
+                                1 => value_column = arg.parse().unwrap_or(1),
+                                _ => {}
                             }
+                            positional += 1;
                             i += 1;
                         }
                     }
                 }
                 
-                println!("Loading CSV: {} (key_col={}, value_col={}, headers={}, delimiter='{}', recovery={})", 
-                    file_path, key_column, value_column, has_headers, delimiter, recovery_mode);
+                println!("Loading CSV: {} (key_col={}, value_col={}, headers={}, delimiter='{}', recovery={}, batch_size={}, threads={})", 
+                    file_path, key_column, value_column, has_headers, delimiter, recovery_mode, batch_size, threads);
                 
-                let loader = ETLLoader::new().with_recovery_mode(recovery_mode);
+                let loader = ETLLoader::with_config(batch_size, threads)
+                    .with_delimiter(delimiter as u8)
+                    .with_recovery_mode(recovery_mode);
                 
-                if recovery_mode {
-                    match loader.load_csv_with_recovery(file_path, &mut self.db, key_column, value_column, has_headers) {
-                        Ok(result) => {
-                            println!("Successfully loaded {} out of {} records ({:.1}% success rate)", 
-                                result.successful_inserts, result.total_rows, result.success_rate() * 100.0);
-                            
-                            if !result.errors.is_empty() {
-                                println!("Errors encountered:");
-                                for error in result.errors.iter().take(5) { // Show first 5 errors
-                                    println!("  Row {}: {}", error.row_number, error.error);
-                                }
-                                if result.errors.len() > 5 {
-                                    println!("  ... and {} more errors", result.errors.len() - 5);
-                                }
+                match loader.load_csv_detailed(file_path, &mut self.db, key_column, value_column, has_headers) {
+                    Ok(result) => {
+                        println!("Successfully loaded {} out of {} records ({:.1}% success rate, {} errors)", 
+                            result.successful_inserts, result.total_rows, result.success_rate() * 100.0, result.errors.len());
+                        
+                        if !result.errors.is_empty() {
+                            println!("Errors encountered:");
+                            for error in result.errors.iter().take(5) { // Show first 5 errors
+                                println!("  Row {}: {}", error.row_number, error.error);
+                            }
+                            if result.errors.len() > 5 {
+                                println!("  ... and {} more errors", result.errors.len() - 5);
                             }
                         }
-                        Err(e) => println!("Error loading CSV: {}", e),
                     }
-                } else {
-                    match loader.load_csv(file_path, &mut self.db, key_column, value_column) {
-                        Ok(count) => println!("Successfully loaded {} records from {}", count, file_path),
-                        Err(e) => println!("Error loading CSV: {}", e),
+                    Err(e) => println!("Error loading CSV: {}", e),
+                }
+            }
+
+            "range" => {
+                if parts.len() < 3 {
+                    println!("Usage: range <start> <end> [limit]");
+                    return Ok(false);
+                }
+
+                let limit = match parts.get(3) {
+                    Some(arg) => match arg.parse::<usize>() {
+                        Ok(limit) => Some(limit),
+                        Err(_) => {
+                            println!("Error: limit must be a number");
+                            return Ok(false);
+                        }
+                    },
+                    None => None,
+                };
+
+                let entries = self.db.scan_range(parts[1], parts[2])?;
+                let shown = limit.unwrap_or(entries.len()).min(entries.len());
+                for (key, value) in &entries[..shown] {
+                    println!("{}: {}", key, value);
+                }
+                println!("{} of {} entries in [{}, {})", shown, entries.len(), parts[1], parts[2]);
+            }
+
+            "scan" => {
+                let prefix = parts.get(1).copied().unwrap_or("");
+                let entries = self.db.get_prefix(prefix)?;
+                for (key, value) in &entries {
+                    println!("{}: {}", key, value);
+                }
+                println!("{} entries with prefix '{}'", entries.len(), prefix);
+            }
+
+            "count" => {
+                println!("{} live keys", self.db.key_count()?);
+            }
+
+            "keys" => {
+                let limit = match parts.get(1) {
+                    Some(arg) => match arg.parse::<usize>() {
+                        Ok(limit) => Some(limit),
+                        Err(_) => {
+                            println!("Usage: keys [limit]");
+                            return Ok(false);
+                        }
+                    },
+                    None => None,
+                };
+
+                let mut shown = 0;
+                for (key, _) in self.db.iter()?.take(limit.unwrap_or(usize::MAX)) {
+                    println!("{}", key);
+                    shown += 1;
+                }
+                println!("{} keys listed", shown);
+            }
+
+            "export" => {
+                if parts.len() < 2 {
+                    println!("Usage: export <csv_file> [--no-headers | --with-deletes]");
+                    return Ok(false);
+                }
+
+                let file_path = parts[1];
+                let loader = ETLLoader::new();
+                // --with-deletes writes a dump that `import` can replay, deletes included
+                if parts[2..].contains(&"--with-deletes") {
+                    match loader.export_dump(&self.db, file_path) {
+                        Ok(count) => println!("Exported {} entries to {}", count, file_path),
+                        Err(e) => println!("Error exporting dump: {}", e),
                     }
+                    return Ok(false);
+                }
+
+                let has_headers = !parts[2..].contains(&"--no-headers");
+                match loader.export_csv(&self.db, file_path, has_headers) {
+                    Ok(count) => println!("Exported {} records to {}", count, file_path),
+                    Err(e) => println!("Error exporting CSV: {}", e),
+                }
+            }
+
+            "import" => {
+                if parts.len() < 2 {
+                    println!("Usage: import <dump_file>");
+                    return Ok(false);
+                }
+
+                let file_path = parts[1];
+                match ETLLoader::new().import_dump(file_path, &mut self.db) {
+                    Ok(result) => println!("Imported {} upserts and {} deletes from {}", result.upserts, result.deletes, file_path),
+                    Err(e) => println!("Error importing dump: {}", e),
                 }
             }
 
             "query" => {
                 if parts.len() < 2 {
-                    println!("Usage: query <SQL>");
+                    println!("Usage: query <SQL> [--format table|json|csv]");
                     return Ok(false);
                 }
                 
-                let sql = parts[1..].join(" ");
+                // A trailing --format renders SELECT rows as a table, JSON or escaped CSV
+                let (sql_parts, format) = match parts.len() {
+                    len if len >= 4 && parts[len - 2] == "--format" => (&parts[1..len - 2], Some(parts[len - 1])),
+                    _ => (&parts[1..], None),
+                };
+                let sql = sql_parts.join(" ");
                 
                 let mut parser = SQLParser::new(&sql);
                 match parser.parse() {
                     Ok(statement) => {
-                        let mut executor = QueryExecutor::new(&mut self.db);
-                        match executor.execute(statement) {
-                            Ok(result) => println!("{}", result.format()),
-                            Err(e) => println!("Query execution error: {}", e),
+                        let mut executor = QueryExecutor::new(&mut self.db).with_query_config(&self.config.query);
+                        if let Some(ref cache) = self.query_cache {
+                            executor = executor.with_cache(cache.clone());
+                        }
+                        match (executor.execute(statement), format) {
+                            (Ok(result), Some(format)) => match result.render(format, None) {
+                                Ok(rendered) => println!("{}", rendered),
+                                Err(e) => println!("Query output error: {}", e),
+                            },
+                            (Ok(result), None) => println!("{}", result.format()),
+                            (Err(e), _) => println!("Query execution error: {}", e),
                         }
                     }
                     Err(e) => println!("SQL parsing error: {}", e),
@@ -244,7 +386,17 @@ impl DatabaseCLI {
         println!("  insert <key> <value>                    - Insert a key-value pair");
         println!("  get <key>                               - Get value by key");
         println!("  delete <key>                            - Delete a key");
+        println!("  exists <key>                            - Check whether a key has a live value");
         println!("  load <csv_file> [key_col] [value_col]   - Load data from CSV file with specified columns (default: 0,1)");
+        println!("       [--batch-size <n>] [--threads <n>] - Override the loader's batch size and thread count");
+        println!("  range <start> <end> [limit]             - List keys in [start, end)");
+        println!("  scan [prefix]                           - List keys starting with prefix");
+        println!("  count                                   - Count live keys");
+        println!("  keys [limit]                            - List keys without values");
+        println!("  export <csv_file> [--no-headers]        - Export all key-value pairs to a CSV file");
+        println!("       [--with-deletes]                   - Also write deleted keys (.jsonl path for JSON lines)");
+        println!("  import <dump_file>                      - Replay an export --with-deletes dump, deletes included");
+        println!("  query <SQL> [--format table|json|csv]   - Run a SQL statement");
         println!("  compact                                 - Force compaction of all levels");
         println!("  autocompact                             - Check and compact levels if needed");
         println!("  stats                                   - Show database statistics");
@@ -268,7 +420,7 @@ mod tests {
         config.background_compaction = false; // Disable background compaction
 
         let db = LSMTree::with_config(config).unwrap();
-        let cli = DatabaseCLI { db };
+        let cli = DatabaseCLI { db, config: DatabaseConfig::default(), query_cache: None };
         (cli, temp_dir)
     }
 
@@ -355,6 +507,46 @@ mod tests {
         assert_eq!(value, Some("value0".to_string()));
     }
 
+    #[test]
+    fn test_handle_load_command_with_threads() {
+        let (mut cli, temp_dir) = create_test_cli();
+
+        let csv_path = temp_dir.path().join("input.csv");
+        std::fs::write(&csv_path, "key,value\nkey1,value1\nkey2,value2\nkey3,value3\n").unwrap();
+        let csv_path = csv_path.to_str().unwrap();
+
+        assert!(!cli.handle_command(&format!("load {} 0 1 --threads 2", csv_path)).unwrap());
+        assert_eq!(cli.db.get("key3").unwrap(), Some("value3".to_string()));
+
+        cli.handle_command(&format!("load {} --batch-size 1 --threads 1", csv_path)).unwrap();
+        assert_eq!(cli.db.get("key1").unwrap(), Some("value1".to_string()));
+
+        // A bad count is reported without loading anything
+        cli.db.delete("key2").unwrap();
+        cli.handle_command(&format!("load {} --threads none", csv_path)).unwrap();
+        assert_eq!(cli.db.get("key2").unwrap(), None);
+    }
+
+    #[test]
+    fn test_load_follows_the_session_etl_config() {
+        let (mut cli, temp_dir) = create_test_cli();
+        cli.config.etl.has_headers = false;
+        cli.config.etl.delimiter = ';';
+
+        // No header row to skip and ';' between fields, without any flags
+        let csv_path = temp_dir.path().join("input.csv");
+        std::fs::write(&csv_path, "key1;value1\nkey2;value2\n").unwrap();
+        cli.handle_command(&format!("load {}", csv_path.to_str().unwrap())).unwrap();
+        assert_eq!(cli.db.get("key1").unwrap(), Some("value1".to_string()));
+        assert_eq!(cli.db.get("key2").unwrap(), Some("value2".to_string()));
+
+        // Flags still override it
+        let csv_path = temp_dir.path().join("commas.csv");
+        std::fs::write(&csv_path, "key3,value3\n").unwrap();
+        cli.handle_command(&format!("load {} --delimiter ,", csv_path.to_str().unwrap())).unwrap();
+        assert_eq!(cli.db.get("key3").unwrap(), Some("value3".to_string()));
+    }
+
     #[test]
     fn test_handle_stats_command() {
         let (mut cli, _temp_dir) = create_test_cli();
@@ -380,6 +572,106 @@ mod tests {
         assert_eq!(value, Some("value1".to_string()));
     }
 
+    #[test]
+    fn test_handle_range_command() {
+        let (mut cli, _temp_dir) = create_test_cli();
+
+        for key in ["a1", "a2", "b1", "b2", "c1"] {
+            cli.db.insert(key.to_string(), format!("v_{}", key)).unwrap();
+        }
+
+        let result = cli.handle_command("range a2 c1");
+        assert!(result.is_ok());
+        assert!(!result.unwrap());
+
+        let result = cli.handle_command("range a1 c1 2");
+        assert!(!result.unwrap());
+
+        let entries = cli.db.scan_range("a2", "c1").unwrap();
+        let keys: Vec<&str> = entries.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["a2", "b1", "b2"]);
+    }
+
+    #[test]
+    fn test_handle_scan_command() {
+        let (mut cli, _temp_dir) = create_test_cli();
+
+        for key in ["user:1", "user:2", "order:1"] {
+            cli.db.insert(key.to_string(), "x".to_string()).unwrap();
+        }
+
+        let result = cli.handle_command("scan user:");
+        assert!(result.is_ok());
+        assert!(!result.unwrap());
+        assert!(!cli.handle_command("scan").unwrap());
+
+        let entries = cli.db.get_prefix("user:").unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[test]
+    fn test_handle_count_and_keys_commands() {
+        let (mut cli, _temp_dir) = create_test_cli();
+
+        for i in 0..5 {
+            cli.db.insert(format!("key{}", i), "value".to_string()).unwrap();
+        }
+        cli.db.delete("key2").unwrap();
+
+        assert!(!cli.handle_command("count").unwrap());
+        assert!(!cli.handle_command("keys").unwrap());
+        assert!(!cli.handle_command("keys 2").unwrap());
+        assert_eq!(cli.db.key_count().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_handle_exists_command() {
+        let (mut cli, _temp_dir) = create_test_cli();
+
+        cli.db.insert("key1".to_string(), "value1".to_string()).unwrap();
+        assert!(!cli.handle_command("exists key1").unwrap());
+        assert!(!cli.handle_command("exists missing").unwrap());
+        assert!(!cli.handle_command("exists").unwrap());
+
+        // Deleting a missing key doesn't write a tombstone
+        assert!(!cli.handle_command("delete missing").unwrap());
+        assert_eq!(cli.db.stats().memtable_entries, 1);
+    }
+
+    #[test]
+    fn test_handle_export_command() {
+        let (mut cli, temp_dir) = create_test_cli();
+
+        cli.db.insert("key1".to_string(), "value1".to_string()).unwrap();
+        cli.db.insert("key2".to_string(), "value2".to_string()).unwrap();
+
+        let export_path = temp_dir.path().join("export.csv");
+        let result = cli.handle_command(&format!("export {}", export_path.display()));
+        assert!(result.is_ok());
+
+        let contents = std::fs::read_to_string(&export_path).unwrap();
+        assert_eq!(contents, "key,value\nkey1,value1\nkey2,value2\n");
+    }
+
+    #[test]
+    fn test_handle_import_command() {
+        let (mut cli, temp_dir) = create_test_cli();
+
+        cli.db.insert("key1".to_string(), "value1".to_string()).unwrap();
+        cli.db.insert("key2".to_string(), "value2".to_string()).unwrap();
+        cli.db.delete("key2").unwrap();
+
+        let dump_path = temp_dir.path().join("dump.jsonl");
+        cli.handle_command(&format!("export {} --with-deletes", dump_path.display())).unwrap();
+
+        cli.db.insert("key2".to_string(), "stale".to_string()).unwrap();
+        cli.db.insert("key1".to_string(), "stale".to_string()).unwrap();
+        cli.handle_command(&format!("import {}", dump_path.display())).unwrap();
+
+        assert_eq!(cli.db.get("key1").unwrap(), Some("value1".to_string()));
+        assert_eq!(cli.db.get("key2").unwrap(), None);
+    }
+
     #[test]
     fn test_handle_help_command() {
         let (mut cli, _temp_dir) = create_test_cli();
@@ -1,3 +1,4 @@
+use crate::{DbError, DbResult};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::Duration;
@@ -17,6 +18,58 @@ pub struct StorageConfig {
     pub enable_wal: bool,
     pub background_compaction: bool,
     pub compaction_interval_secs: u64,
+    pub memtable_bytes_limit: Option<usize>,
+    #[serde(default)]
+    pub use_mmap: bool,
+    #[serde(default = "default_block_cache_bytes")]
+    pub block_cache_bytes: usize,
+    #[serde(default = "default_bloom_false_positive_rate")]
+    pub bloom_false_positive_rate: f64,
+    pub wal_segment_bytes: Option<usize>,
+    #[serde(default = "default_level_fanout")]
+    pub level_fanout: usize,
+    #[serde(default = "default_l0_file_limit")]
+    pub l0_file_limit: usize,
+    #[serde(default = "default_l1_base_bytes")]
+    pub l1_base_bytes: usize,
+    #[serde(default = "default_auto_flush")]
+    pub auto_flush: bool,
+    #[serde(default = "default_max_sstable_bytes")]
+    pub max_sstable_bytes: usize,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub compaction_rate_limit_bytes_per_sec: Option<u64>,
+    #[serde(default)]
+    pub l0_stall_threshold: Option<usize>,
+}
+
+fn default_max_sstable_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_auto_flush() -> bool {
+    true
+}
+
+fn default_level_fanout() -> usize {
+    10
+}
+
+fn default_l0_file_limit() -> usize {
+    4
+}
+
+fn default_l1_base_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_block_cache_bytes() -> usize {
+    8 * 1024 * 1024
+}
+
+fn default_bloom_false_positive_rate() -> f64 {
+    0.01
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,13 +77,17 @@ pub struct EtlConfig {
     pub batch_size: usize,
     pub parallel_threads: usize,
     pub delimiter: char,
-    pub hash_headers: bool,
+    #[serde(alias = "hash_headers")]
+    pub has_headers: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryConfig {
     pub enable_query_cache: bool,
     pub max_result_size: usize,
+    // Cut SELECT results at max_result_size instead of failing the query
+    #[serde(default)]
+    pub truncate_results: bool,
     pub query_timeout_secs: u64,
 }
 
@@ -50,16 +107,30 @@ impl Default for DatabaseConfig {
                 enable_wal: true,
                 background_compaction: false,
                 compaction_interval_secs: 60,
+                memtable_bytes_limit: None,
+                use_mmap: false,
+                block_cache_bytes: default_block_cache_bytes(),
+                bloom_false_positive_rate: default_bloom_false_positive_rate(),
+                wal_segment_bytes: None,
+                level_fanout: default_level_fanout(),
+                l0_file_limit: default_l0_file_limit(),
+                l1_base_bytes: default_l1_base_bytes(),
+                auto_flush: default_auto_flush(),
+                max_sstable_bytes: default_max_sstable_bytes(),
+                read_only: false,
+                compaction_rate_limit_bytes_per_sec: None,
+                l0_stall_threshold: None,
             },
             etl: EtlConfig {
                 batch_size: 1000,
                 parallel_threads: 4,
                 delimiter: ',',
-                hash_headers: true,
+                has_headers: true,
             },
             query: QueryConfig {
                 enable_query_cache: true,
                 max_result_size: 10000,
+                truncate_results: false,
                 query_timeout_secs: 30,
             },
             logging: LoggingConfig {
@@ -75,9 +146,37 @@ impl DatabaseConfig {
     pub fn load_from_file(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         let content = std::fs::read_to_string(path)?;
         let config: DatabaseConfig = serde_yaml::from_str(&content)?;
+        config.validate()?;
         Ok(config)
     }
 
+    // Check every section, reporting the first invalid field
+    pub fn validate(&self) -> DbResult<()> {
+        self.to_lsm_config().validate()?;
+
+        let invalid = |msg: &str| Err(DbError::InvalidOperation(format!("Invalid configuration: {}", msg)));
+
+        if self.etl.batch_size == 0 {
+            return invalid("etl.batch_size must be greater than 0");
+        }
+        if self.etl.parallel_threads == 0 {
+            return invalid("etl.parallel_threads must be greater than 0");
+        }
+        if !self.etl.delimiter.is_ascii() {
+            return invalid("etl.delimiter must be a single ASCII character");
+        }
+        if self.query.max_result_size == 0 {
+            return invalid("query.max_result_size must be greater than 0");
+        }
+        if self.query.query_timeout_secs == 0 {
+            return invalid("query.query_timeout_secs must be greater than 0");
+        }
+        if !matches!(self.logging.level.as_str(), "trace" | "debug" | "info" | "warn" | "error") {
+            return invalid("logging.level must be one of trace, debug, info, warn, error");
+        }
+        Ok(())
+    }
+
     pub fn save_to_file(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
         let content = serde_yaml::to_string(self)?;
         std::fs::write(path, content)?;
@@ -91,6 +190,97 @@ impl DatabaseConfig {
             background_compaction: self.storage.background_compaction,
             background_compaction_interval: Duration::from_secs(self.storage.compaction_interval_secs),
             enable_wal: self.storage.enable_wal,
+            memtable_bytes_limit: self.storage.memtable_bytes_limit,
+            use_mmap: self.storage.use_mmap,
+            block_cache_bytes: self.storage.block_cache_bytes,
+            bloom_false_positive_rate: self.storage.bloom_false_positive_rate,
+            wal_segment_bytes: self.storage.wal_segment_bytes,
+            level_fanout: self.storage.level_fanout,
+            l0_file_limit: self.storage.l0_file_limit,
+            l1_base_bytes: self.storage.l1_base_bytes,
+            auto_flush: self.storage.auto_flush,
+            max_sstable_bytes: self.storage.max_sstable_bytes,
+            read_only: self.storage.read_only,
+            key_ordering: crate::engine::KeyOrdering::default(),
+            compaction_rate_limit_bytes_per_sec: self.storage.compaction_rate_limit_bytes_per_sec,
+            l0_stall_threshold: self.storage.l0_stall_threshold,
+            write_stall_policy: crate::engine::WriteStallPolicy::default(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rejected(config: DatabaseConfig, field: &str) {
+        match config.validate() {
+            Err(DbError::InvalidOperation(msg)) => assert!(msg.contains(field), "{} not in '{}'", field, msg),
+            other => panic!("expected {} to be rejected, got {:?}", field, other),
+        }
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(DatabaseConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_fields_are_rejected() {
+        let mut config = DatabaseConfig::default();
+        config.storage.memtable_size_limit = 0;
+        assert_rejected(config, "memtable_size_limit");
+
+        let mut config = DatabaseConfig::default();
+        config.storage.data_dir = PathBuf::new();
+        assert_rejected(config, "data_dir");
+
+        let mut config = DatabaseConfig::default();
+        config.etl.batch_size = 0;
+        assert_rejected(config, "etl.batch_size");
+
+        let mut config = DatabaseConfig::default();
+        config.etl.parallel_threads = 0;
+        assert_rejected(config, "etl.parallel_threads");
+
+        let mut config = DatabaseConfig::default();
+        config.etl.delimiter = '§';
+        assert_rejected(config, "etl.delimiter");
+
+        let mut config = DatabaseConfig::default();
+        config.query.max_result_size = 0;
+        assert_rejected(config, "query.max_result_size");
+
+        let mut config = DatabaseConfig::default();
+        config.query.query_timeout_secs = 0;
+        assert_rejected(config, "query.query_timeout_secs");
+
+        let mut config = DatabaseConfig::default();
+        config.logging.level = "verbose".into();
+        assert_rejected(config, "logging.level");
+    }
+
+    #[test]
+    fn test_legacy_hash_headers_key() {
+        let mut yaml = serde_yaml::to_string(&DatabaseConfig::default()).unwrap();
+        yaml = yaml.replace("has_headers: true", "hash_headers: false");
+
+        let config: DatabaseConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert!(!config.etl.has_headers);
+    }
+
+    #[test]
+    fn test_load_from_file_validates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("db.yaml");
+
+        let mut config = DatabaseConfig::default();
+        config.save_to_file(&path).unwrap();
+        assert!(DatabaseConfig::load_from_file(&path).is_ok());
+
+        config.etl.parallel_threads = 0;
+        config.save_to_file(&path).unwrap();
+        let err = DatabaseConfig::load_from_file(&path).unwrap_err();
+        assert!(err.to_string().contains("etl.parallel_threads"));
+    }
+}
@@ -0,0 +1,94 @@
+// Async wrappers - run blocking LSMTree calls on tokio's blocking thread pool
+//
+// Threading model: every LSMTree operation does synchronous file I/O (WAL appends,
+// SSTable reads, flushes), so calling it directly from an async task stalls that
+// executor thread. These wrappers take a SharedLSMTree and move the call onto
+// `tokio::task::spawn_blocking`. Reads take the shared lock, so many `get_async`
+// calls can run in parallel; writes take the exclusive lock and are serialized.
+// Background compaction keeps running on its own OS thread either way.
+
+use crate::engine::LSMTree;
+use crate::{DbError, DbResult};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+// A tree that can be handed to many tasks at once
+pub type SharedLSMTree = Arc<RwLock<LSMTree>>;
+
+impl LSMTree {
+    // Wrap a tree for use with the async API
+    pub fn into_shared(self) -> SharedLSMTree {
+        Arc::new(RwLock::new(self))
+    }
+
+    pub async fn get_async(tree: &SharedLSMTree, key: String) -> DbResult<Option<String>> {
+        let tree = tree.clone();
+        run_blocking(move || tree.read().get(&key)).await
+    }
+
+    pub async fn insert_async(tree: &SharedLSMTree, key: String, value: String) -> DbResult<()> {
+        let tree = tree.clone();
+        run_blocking(move || tree.write().insert(key, value)).await
+    }
+
+    pub async fn delete_async(tree: &SharedLSMTree, key: String) -> DbResult<bool> {
+        let tree = tree.clone();
+        run_blocking(move || tree.write().delete(&key)).await
+    }
+
+    pub async fn flush_async(tree: &SharedLSMTree) -> DbResult<()> {
+        let tree = tree.clone();
+        run_blocking(move || tree.write().flush()).await
+    }
+}
+
+async fn run_blocking<T, F>(work: F) -> DbResult<T>
+where
+    F: FnOnce() -> DbResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work).await.map_err(|e| {
+        DbError::InvalidOperation(format!("Blocking task failed: {}", e))
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::LSMConfig;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_get_async() {
+        let temp_dir = tempdir().unwrap();
+        let config = LSMConfig {
+            memtable_size_limit: 50,
+            data_dir: temp_dir.path().to_path_buf(),
+            background_compaction: false,
+            background_compaction_interval: Duration::from_secs(1),
+            enable_wal: true,
+            ..LSMConfig::default()
+        };
+        let tree = LSMTree::with_config(config).unwrap().into_shared();
+
+        for i in 0..200 {
+            LSMTree::insert_async(&tree, format!("key{:03}", i), format!("value{}", i)).await.unwrap();
+        }
+        LSMTree::delete_async(&tree, "key007".to_string()).await.unwrap();
+        LSMTree::flush_async(&tree).await.unwrap();
+
+        let handles: Vec<_> = (0..200)
+            .map(|i| {
+                let tree = tree.clone();
+                tokio::spawn(async move { (i, LSMTree::get_async(&tree, format!("key{:03}", i)).await) })
+            })
+            .collect();
+
+        for handle in handles {
+            let (i, result) = handle.await.unwrap();
+            let expected = if i == 7 { None } else { Some(format!("value{}", i)) };
+            assert_eq!(result.unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// Block cache - keeps recently read SSTable records in memory so hot files aren't re-read from disk
+
+use crate::engine::sstable::Record;
+use crate::DbResult;
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug)]
+struct CacheState {
+    entries: LruCache<PathBuf, (Arc<Vec<Record>>, usize)>, // Records plus their estimated size
+    used_bytes: usize,
+}
+
+#[derive(Debug)]
+pub struct BlockCache {
+    state: Mutex<CacheState>,
+    capacity_bytes: usize,
+    hits: AtomicU64,
+    misses: AtomicU64, // Every miss is a read of the SSTable file
+}
+
+impl BlockCache {
+    pub fn new(capacity_bytes: usize) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                entries: LruCache::unbounded(),
+                used_bytes: 0,
+            }),
+            capacity_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // Return the cached records for a file, or load them with `load` and cache the result
+    pub fn get_or_load<F>(&self, path: &Path, load: F) -> DbResult<Arc<Vec<Record>>>
+    where
+        F: FnOnce() -> DbResult<Vec<Record>>,
+    {
+        if let Some((records, _)) = self.state.lock().entries.get(path) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(records.clone());
+        }
+
+        // Load outside the lock so a slow read doesn't block lookups of other files
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let records = Arc::new(load()?);
+        let size = Self::estimate_size(&records);
+
+        // A file bigger than the whole cache is served but not kept
+        if size <= self.capacity_bytes {
+            let mut state = self.state.lock();
+            if let Some((_, old_size)) = state.entries.put(path.to_path_buf(), (records.clone(), size)) {
+                state.used_bytes -= old_size;
+            }
+            state.used_bytes += size;
+
+            while state.used_bytes > self.capacity_bytes {
+                match state.entries.pop_lru() {
+                    Some((_, (_, evicted_size))) => state.used_bytes -= evicted_size,
+                    None => break,
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    // Drop a file's records, e.g. once compaction has deleted it
+    pub fn invalidate(&self, path: &Path) {
+        let mut state = self.state.lock();
+        if let Some((_, size)) = state.entries.pop(path) {
+            state.used_bytes -= size;
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.state.lock().used_bytes
+    }
+
+    pub fn capacity_bytes(&self) -> usize {
+        self.capacity_bytes
+    }
+
+    fn estimate_size(records: &[Record]) -> usize {
+        records
+            .iter()
+            .map(|record| {
+                std::mem::size_of::<Record>() + record.key.len() + record.value.data_len()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    fn records(count: usize) -> Vec<Record> {
+        (0..count)
+            .map(|i| Record {
+                key: format!("key{:03}", i),
+                value: Value::Data("v".repeat(10)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_block_cache_hits_and_eviction() {
+        let one_file = BlockCache::estimate_size(&records(10));
+        let cache = BlockCache::new(one_file * 2);
+
+        cache.get_or_load(Path::new("a.sst"), || Ok(records(10))).unwrap();
+        cache.get_or_load(Path::new("a.sst"), || panic!("should be cached")).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // Loading two more files evicts the least recently used one
+        cache.get_or_load(Path::new("b.sst"), || Ok(records(10))).unwrap();
+        cache.get_or_load(Path::new("c.sst"), || Ok(records(10))).unwrap();
+        assert_eq!(cache.used_bytes(), one_file * 2);
+        cache.get_or_load(Path::new("a.sst"), || Ok(records(10))).unwrap();
+        assert_eq!(cache.misses(), 4);
+
+        cache.invalidate(Path::new("a.sst"));
+        assert_eq!(cache.used_bytes(), one_file);
+    }
+}
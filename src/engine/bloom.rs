@@ -4,6 +4,8 @@ use std::hash::{Hasher};
 use std::collections::hash_map::DefaultHasher;
 use serde::{Deserialize, Serialize};
 
+const MIN_BIT_SIZE: usize = 64;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BloomFilter {
     // Store bits as Vec<u8> for serialization 
@@ -33,11 +35,16 @@ where
 
 impl BloomFilter {
     pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        // Size for at least one item so an empty SSTable still gets a usable filter
+        let sizing_items = expected_items.max(1);
+
         // Calculate optimal size of the bit vector
-        let bit_size = (-(expected_items as f64 * false_positive_rate.ln()) / (2.0_f64.ln().powi(2))).ceil() as usize;
+        let bit_size = (-(sizing_items as f64 * false_positive_rate.ln()) / (2.0_f64.ln().powi(2))).ceil() as usize;
+        let bit_size = bit_size.max(MIN_BIT_SIZE);
 
         // Calculate optimal number of hash functions
-        let hash_functions = ((bit_size as f64 / expected_items as f64) * 2.0_f64.ln()).ceil() as usize;
+        let hash_functions = ((bit_size as f64 / sizing_items as f64) * 2.0_f64.ln()).ceil() as usize;
+        let hash_functions = hash_functions.max(1);
 
         Self {
             bits: BitVec::from_elem(bit_size, false),
@@ -71,9 +78,10 @@ impl BloomFilter {
         true
     }
 
+    // Reset every bit while keeping the bit length the hash positions are computed against
     pub fn clear(&mut self) {
-        self.bits.clear();
-        self.bits.grow(self.bits.capacity(), false);
+        let bit_len = self.bits.len();
+        self.bits = BitVec::from_elem(bit_len, false);
     }
 
     pub fn len(&self) -> usize {
@@ -106,6 +114,12 @@ impl BloomFilter {
     }
 
     fn get_hash_positions(&self, item: &str) -> Vec<usize> {
+        // A zero-length filter (only reachable through with_size) has nothing to set or test,
+        // so every lookup falls through to "might contain"
+        if self.bits.is_empty() {
+            return Vec::new();
+        }
+
         let mut positions = Vec::with_capacity(self.hash_functions);
 
         let hash1 = self.hash_item(item, 0);
@@ -170,6 +184,44 @@ mod tests {
         assert!(!bloom.contains("test"));
     }
 
+    #[test]
+    fn test_bloom_filter_clear_preserves_length() {
+        let mut bloom = BloomFilter::new(100, 0.01);
+        let original_len = bloom.len();
+
+        for i in 0..50 {
+            bloom.insert(&format!("key{}", i));
+        }
+
+        bloom.clear();
+        assert_eq!(bloom.len(), original_len);
+        assert_eq!(bloom.estimated_false_positive_rate(), 0.0);
+        for i in 0..50 {
+            assert!(!bloom.contains(&format!("key{}", i)), "key{} survived clear", i);
+        }
+
+        // Still usable afterwards
+        bloom.insert("again");
+        assert!(bloom.contains("again"));
+        assert_eq!(bloom.len(), original_len);
+    }
+
+    #[test]
+    fn test_bloom_filter_zero_expected_items() {
+        let mut bloom = BloomFilter::new(0, 0.01);
+        assert!(bloom.len() >= MIN_BIT_SIZE);
+        assert!(!bloom.contains("anything"));
+
+        bloom.insert("key");
+        assert!(bloom.contains("key"));
+
+        // A zero-length filter can't rule anything out
+        let mut empty = BloomFilter::with_size(0, 3);
+        empty.insert("key");
+        assert!(empty.contains("key"));
+        assert!(empty.contains("other"));
+    }
+
     #[test]
     fn test_bloom_filter_custom_size() {
         let mut bloom = BloomFilter::with_size(1000, 3);
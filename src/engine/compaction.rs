@@ -1,8 +1,8 @@
 // Compaction module for merging SSTables in LSM tree
 
-use crate::{DbResult, Value};
-use super::{SSTable};
-use std::collections::BTreeMap;
+use crate::DbResult;
+use super::{MergeIterator, SSTable};
+use super::sstable::{RangeTombstone, SSTableWriter, DEFAULT_BLOOM_FALSE_POSITIVE_RATE};
 use std::path::{PathBuf};
 
 pub struct Compactor {
@@ -16,55 +16,61 @@ impl Compactor {
 
     // Returns the path to the new merged SSTable
     pub fn compact_sstables(&self, sstables: &[SSTable], output_id: u64) -> DbResult<SSTable> {
-        println!("Starting compaction of {} SSTables...", sstables.len());
-
-        let mut all_records = BTreeMap::new();
-        let mut total_input_records = 0;
-
-        // Process SSTables in reverse order so newer values (including tombstones) are not overwritten by older values
-        for sstable in sstables.iter().rev() {
-            let records = sstable.scan()?;
-            total_input_records += records.len();
-            
-            // Add records to map (BTreeMap automatically handles duplicates by keeping latest)
-            for record in records {
-                all_records.insert(record.key, record.value);
-            }
-        }
-
-        // Filter out tombstones for the final output
-        let final_records: BTreeMap<String, Value> = all_records
-            .into_iter()
-            .filter(|(_, value)| !value.is_tombstone())
-            .collect();
+        log::info!("Starting compaction of {} SSTables...", sstables.len());
 
-        println!("Compaction stats:");
-        println!("Input: {} SSTables with {} total records", sstables.len(), total_input_records);
-        println!("Output: {} unique records after merging", final_records.len());
+        // Stream in reverse order: the merge lets later inputs win, and here the first SSTable is the newest,
+        // so newer values (including tombstones) are not overwritten by older values
+        let streams = sstables.iter().rev().map(|sstable| sstable.records_iter()).collect::<DbResult<Vec<_>>>()?;
+        let mut merged = MergeIterator::new(streams)?;
 
         let output_filename = format!("sstable_{:06}_compacted.sst", output_id);
         let output_path = self.data_dir.join(output_filename);
+        let expected_records = sstables.iter().map(|sstable| sstable.len()).sum();
+        let mut writer = SSTableWriter::create(&output_path, 0, expected_records, DEFAULT_BLOOM_FALSE_POSITIVE_RATE)?;
+
+        // Range tombstones by merge input, for dropping the older records they cover
+        let range_tombstones: Vec<(usize, &RangeTombstone)> = sstables
+            .iter()
+            .rev()
+            .enumerate()
+            .flat_map(|(input, sstable)| sstable.range_tombstones().iter().map(move |t| (input, t)))
+            .collect();
+
+        // Filter out tombstones for the final output
+        while let Some(record) = merged.next() {
+            let record = record?;
+            let source = merged.last_source();
+            let range_deleted = range_tombstones.iter().any(|&(input, t)| input > source && t.covers(&record.key));
+            if !record.value.is_tombstone() && !range_deleted {
+                writer.append(&record)?;
+            }
+        }
+
+        log::debug!("Compaction stats:");
+        log::debug!("Input: {} SSTables with {} total records", sstables.len(), merged.records_read());
+        log::debug!("Output: {} unique records after merging", writer.len());
 
-        let compacted_sstable = SSTable::create_with_level(&output_path, &final_records, 0)?;
+        let compacted_sstable = writer.finish()?;
 
-        println!("Compaction complete. Merged SSTable created at: {}", output_path.display());
+        log::info!("Compaction complete. Merged SSTable created at: {}", output_path.display());
 
         Ok(compacted_sstable)
     }
 
     pub fn cleanup_old_sstables(&self, old_sstables: &[SSTable]) -> DbResult<()> {
-        println!("Cleaning up {} old SSTables...", old_sstables.len());
+        log::debug!("Cleaning up {} old SSTables...", old_sstables.len());
 
         for sstable in old_sstables {
+            sstable.evict_from_cache();
             match std::fs::remove_file(sstable.file_path()) {
-                Ok(_) => println!("Deleted: {}", sstable.file_path().display()),
+                Ok(_) => log::debug!("Deleted: {}", sstable.file_path().display()),
                 Err(e) => {
-                    println!("Failed to delete {}: {}", sstable.file_path().display(), e);
+                    log::warn!("Failed to delete {}: {}", sstable.file_path().display(), e);
                 }
             }
         }
 
-        println!("Cleanup complete.");
+        log::debug!("Cleanup complete.");
         Ok(())
     }
 
@@ -1,30 +1,32 @@
 use crate::engine::SSTable;
+use serde::Serialize;
 use std::collections::BTreeMap;
 
+pub const DEFAULT_LEVEL_0_FILE_LIMIT: usize = 4;
+pub const DEFAULT_LEVEL_SIZE_MULTIPLIER: usize = 10;
+pub const DEFAULT_LEVEL_1_BASE_BYTES: usize = 10 * 1024 * 1024; // 10MB for Level 1
+
 #[derive(Debug)]
 pub struct LevelManager {
     levels: BTreeMap<usize, Vec<SSTable>>, // level -> SSTables
     max_level: usize,
     level_size_multiplier: usize, // Usually 10
     level_0_file_limit: usize, // Trigger compaction
+    level_1_base_bytes: usize, // Byte budget of Level 1; deeper levels scale by the multiplier
 }
 
 impl LevelManager {
     pub fn new() -> Self {
-        Self {
-            levels: BTreeMap::new(),
-            max_level: 0,
-            level_size_multiplier: 10,
-            level_0_file_limit: 4,
-        }
+        Self::with_config(DEFAULT_LEVEL_0_FILE_LIMIT, DEFAULT_LEVEL_SIZE_MULTIPLIER, DEFAULT_LEVEL_1_BASE_BYTES)
     }
 
-    pub fn with_config(level_0_file_limit: usize, level_size_multiplier: usize) -> Self {
+    pub fn with_config(level_0_file_limit: usize, level_size_multiplier: usize, level_1_base_bytes: usize) -> Self {
         Self {
             levels: BTreeMap::new(),
             max_level: 0,
             level_size_multiplier,
             level_0_file_limit,
+            level_1_base_bytes,
         }
     }
 
@@ -69,7 +71,7 @@ impl LevelManager {
                 self.levels.get(&0).map_or(false, |files| files.len() >= self.level_0_file_limit)
             }
             _ => {
-                // Level 1+: Check total size
+                // Level 1+: Check bytes on disk against the level's byte budget
                 let level_size = self.get_level_size(level);
                 let max_size = self.get_max_level_size(level);
                 level_size >= max_size
@@ -100,6 +102,15 @@ impl LevelManager {
         }
     }
 
+    // Level 1+ files are sorted by min_key and don't overlap, so at most one can hold the key:
+    // binary-search for the last file starting at or before it, then check its upper bound
+    pub fn find_sstable_for_key(&self, level: usize, key: &str) -> Option<&SSTable> {
+        let level_sstables = self.levels.get(&level)?;
+        let index = level_sstables.partition_point(|sstable| sstable.min_key() <= key);
+        let sstable = level_sstables.get(index.checked_sub(1)?)?;
+        sstable.key_in_range(key).then_some(sstable)
+    }
+
     pub fn get_overlapping_sstables(&self, level: usize, min_key: &str, max_key: &str) -> Vec<SSTable> {
         let level_sstables = self.get_sstables_at_level(level);
         let mut overlapping = Vec::new();
@@ -131,20 +142,29 @@ impl LevelManager {
         self.max_level = self.levels.keys().max().copied().unwrap_or(0);
     }
 
+    // Bytes on disk across the level's SSTables
     pub fn get_level_size(&self, level: usize) -> usize {
+        self.levels.get(&level)
+            .map(|sstables| sstables.iter().map(|s| s.size_bytes() as usize).sum())
+            .unwrap_or(0)
+    }
+
+    // Records across the level's SSTables
+    pub fn get_level_entries(&self, level: usize) -> usize {
         self.levels.get(&level)
             .map(|sstables| sstables.iter().map(|s| s.len()).sum())
             .unwrap_or(0)
     }
 
+    // Byte budget for a level. Level 0 compacts on file count instead, but since it drains
+    // into Level 1 it reports Level 1's budget
     pub fn get_max_level_size(&self, level: usize) -> usize {
         match level {
-            0 => self.level_0_file_limit, // Level 0 is measured by file count
-            1 => 10 * 1024 * 1024, // 10MB for Level 1
+            0 | 1 => self.level_1_base_bytes,
             _ => {
                 // Each level is level_size_multiplier times larger than the previous
-                let level_1_size = 10 * 1024 * 1024;
-                level_1_size * self.level_size_multiplier.pow((level - 1) as u32)
+                let multiplier = self.level_size_multiplier.saturating_pow((level - 1) as u32);
+                self.level_1_base_bytes.saturating_mul(multiplier)
             }
         }
     }
@@ -167,6 +187,7 @@ impl LevelManager {
 
             level_stats.insert(level, LevelStats {
                 file_count: count,
+                entry_count: self.get_level_entries(level),
                 total_size: size,
                 max_size,
                 should_compact: self.should_compact(level),
@@ -179,20 +200,90 @@ impl LevelManager {
         }
     }
 
+    // Every file's key span and size, level by level
+    pub fn key_distribution(&self) -> KeyDistribution {
+        let levels = self
+            .levels
+            .iter()
+            .filter(|(_, sstables)| !sstables.is_empty())
+            .map(|(&level, sstables)| {
+                let files = sstables
+                    .iter()
+                    .map(|sstable| FileSpan {
+                        file_name: sstable
+                            .file_path()
+                            .file_name()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .unwrap_or_default(),
+                        min_key: sstable.min_key().to_string(),
+                        max_key: sstable.max_key().to_string(),
+                        entries: sstable.len(),
+                        size_bytes: sstable.size_bytes(),
+                    })
+                    .collect();
+                (level, files)
+            })
+            .collect();
+
+        KeyDistribution { levels }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.levels.is_empty()
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LevelStats {
     pub file_count: usize,
-    pub total_size: usize,
-    pub max_size: usize,
+    pub entry_count: usize,
+    pub total_size: usize, // Bytes
+    pub max_size: usize,   // Bytes
     pub should_compact: bool,
 }
 
-#[derive(Debug)]
+// One SSTable's share of the key space
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileSpan {
+    pub file_name: String,
+    pub min_key: String,
+    pub max_key: String,
+    pub entries: usize,
+    pub size_bytes: u64,
+}
+
+// Where keys sit across levels and files, for spotting hot or lopsided key ranges. Files are
+// listed in each level's own order: Level 0 oldest first, deeper levels by min_key
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct KeyDistribution {
+    pub levels: BTreeMap<usize, Vec<FileSpan>>, // Levels without files are left out
+}
+
+impl std::fmt::Display for KeyDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.levels.is_empty() {
+            return writeln!(f, "No SSTables");
+        }
+
+        for (level, files) in &self.levels {
+            let entries: usize = files.iter().map(|file| file.entries).sum();
+            writeln!(f, "Level {}: {} files, {} entries", level, files.len(), entries)?;
+            for file in files {
+                writeln!(f, "  {} [{}, {}] {} entries, {} bytes",
+                    file.file_name,
+                    file.min_key,
+                    file.max_key,
+                    file.entries,
+                    file.size_bytes
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct LevelManagerStats {
     pub max_level: usize,
     pub level_stats: BTreeMap<usize, LevelStats>,
@@ -204,9 +295,10 @@ impl std::fmt::Display for LevelManagerStats {
         writeln!(f, " Max level: {}", self.max_level)?;
 
         for (level, stats) in &self.level_stats {
-            writeln!(f, "  Level {}: {} files, {} entries, max: {} (compact: {})", 
+            writeln!(f, "  Level {}: {} files, {} entries, {} bytes, max: {} bytes (compact: {})", 
                 level, 
                 stats.file_count, 
+                stats.entry_count, 
                 stats.total_size, 
                 stats.max_size,
                 stats.should_compact
@@ -254,7 +346,7 @@ mod tests {
 
     #[test]
     fn test_compaction_trigger() {
-        let mut manager = LevelManager::with_config(2, 10); // Small limit for testing
+        let mut manager = LevelManager::with_config(2, 10, DEFAULT_LEVEL_1_BASE_BYTES); // Small limit for testing
         
         // Add files to Level 0
         for i in 0..3 {
@@ -303,4 +395,70 @@ mod tests {
         assert_eq!(stats.max_level, 1);
         assert_eq!(stats.level_stats.len(), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_fanout_changes_compaction_threshold() {
+        let table_bytes = create_test_sstable(2, "key1", "key2").size_bytes() as usize;
+
+        // Three equally sized Level 2 files against a Level 2 budget of base * fanout
+        let level_2_with_fanout = |fanout: usize| {
+            let mut manager = LevelManager::with_config(DEFAULT_LEVEL_0_FILE_LIMIT, fanout, table_bytes);
+            for i in 0..3 {
+                manager.add_sstable(create_test_sstable(2, &format!("key{}", i), &format!("key{}", i + 1)), 2);
+            }
+            assert_eq!(manager.get_level_size(2), 3 * table_bytes);
+            manager
+        };
+
+        let narrow = level_2_with_fanout(2);
+        assert_eq!(narrow.get_max_level_size(2), 2 * table_bytes);
+        assert!(narrow.should_compact(2));
+
+        let wide = level_2_with_fanout(10);
+        assert_eq!(wide.get_max_level_size(2), 10 * table_bytes);
+        assert!(!wide.should_compact(2));
+    }
+
+    #[test]
+    fn test_level_1_compacts_past_byte_threshold() {
+        let temp_dir = tempdir().unwrap();
+        let data: BTreeMap<String, Value> = (0..100)
+            .map(|i| (format!("key{:03}", i), Value::Data("v".repeat(100))))
+            .collect();
+        let sstable = SSTable::create_with_level(temp_dir.path().join("l1.sst"), &data, 1).unwrap();
+
+        // Sizes are real bytes on disk, not record counts
+        let file_bytes = std::fs::metadata(sstable.file_path()).unwrap().len() as usize;
+        assert!(file_bytes > 100 * 100);
+
+        let mut under = LevelManager::with_config(DEFAULT_LEVEL_0_FILE_LIMIT, DEFAULT_LEVEL_SIZE_MULTIPLIER, file_bytes + 1);
+        under.add_sstable(sstable.clone(), 1);
+        assert_eq!(under.get_level_size(1), file_bytes);
+        assert!(!under.should_compact(1));
+
+        let mut over = LevelManager::with_config(DEFAULT_LEVEL_0_FILE_LIMIT, DEFAULT_LEVEL_SIZE_MULTIPLIER, file_bytes / 2);
+        over.add_sstable(sstable, 1);
+        assert!(over.should_compact(1));
+        assert_eq!(over.get_compaction_candidates(1).len(), 1);
+    }
+
+    #[test]
+    fn test_find_sstable_for_key() {
+        let mut manager = LevelManager::new();
+        for (min_key, max_key) in [("k20", "k29"), ("k00", "k09"), ("k40", "k49"), ("k10", "k19")] {
+            manager.add_sstable(create_test_sstable(1, min_key, max_key), 1);
+        }
+
+        let found = |key: &str| manager.find_sstable_for_key(1, key).map(|s| s.min_key().to_string());
+        assert_eq!(found("k00").as_deref(), Some("k00"));
+        assert_eq!(found("k15").as_deref(), Some("k10"));
+        assert_eq!(found("k29").as_deref(), Some("k20"));
+        assert_eq!(found("k49").as_deref(), Some("k40"));
+
+        // Gaps between files, and keys outside every file
+        assert_eq!(found("k35"), None);
+        assert_eq!(found("a"), None);
+        assert_eq!(found("z"), None);
+        assert!(manager.find_sstable_for_key(2, "k15").is_none());
+    }
+}
@@ -1,202 +1,426 @@
-use crate::engine::{SSTable, LevelManager};
-use crate::{DbResult, Value};
-use std::collections::BTreeMap;
+use crate::engine::{BlockCache, SSTable, LevelManager};
+use crate::engine::merge_iterator::MergeIterator;
+use crate::engine::sstable::{RangeTombstone, SSTableWriter, DEFAULT_BLOOM_FALSE_POSITIVE_RATE};
+use crate::DbResult;
+use crate::metrics::PerformanceMetrics;
 use std::path::PathBuf;
+use std::fmt;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// What a compaction of one level would do, worked out from in-memory SSTable metadata only
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionPlan {
+    pub source_level: usize,
+    pub target_level: usize,
+    pub source_files: Vec<PathBuf>,
+    pub target_files: Vec<PathBuf>, // Files in the target level whose key range overlaps the sources
+    pub input_records: usize,
+    pub estimated_output_records: usize, // Upper bound: duplicates and tombstones only drop out during the merge
+    pub estimated_bytes: u64,            // Bytes read from the input files
+}
+
+impl CompactionPlan {
+    pub fn is_empty(&self) -> bool {
+        self.source_files.is_empty()
+    }
+}
+
+impl fmt::Display for CompactionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Level {} -> Level {}", self.source_level, self.target_level)?;
+        writeln!(f, "  Source files ({}):", self.source_files.len())?;
+        for path in &self.source_files {
+            writeln!(f, "    {}", path.display())?;
+        }
+        writeln!(f, "  Overlapping target files ({}):", self.target_files.len())?;
+        for path in &self.target_files {
+            writeln!(f, "    {}", path.display())?;
+        }
+        writeln!(f, "  Input records: {}", self.input_records)?;
+        writeln!(f, "  Estimated output records: <= {}", self.estimated_output_records)?;
+        write!(f, "  Estimated bytes read: {}", self.estimated_bytes)
+    }
+}
+
+pub const DEFAULT_MAX_SSTABLE_BYTES: usize = 64 * 1024 * 1024; // 64MB per SSTable
+
+// What one compaction actually did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionResult {
+    pub input_files: usize,
+    pub output_files: usize,
+    pub records_in: usize,         // Every record read, duplicates included
+    pub records_out: usize,        // Live records written to the output files
+    pub tombstones_dropped: usize, // Deletions that won the merge and were discarded
+}
+
+impl fmt::Display for CompactionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {} files, {} -> {} records, {} tombstones dropped",
+            self.input_files, self.output_files, self.records_in, self.records_out, self.tombstones_dropped
+        )
+    }
+}
+
+// Input files captured for one compaction, so the merge can run without the level manager lock
+#[derive(Debug, Clone)]
+pub struct CompactionJob {
+    pub source_level: usize,
+    pub target_level: usize,
+    pub source_files: Vec<SSTable>,
+    pub target_files: Vec<SSTable>,
+    pub deeper_overlap: bool, // Levels below the target hold keys in the inputs' range
+}
+
+impl CompactionJob {
+    pub fn inputs(&self) -> Vec<SSTable> {
+        self.source_files.iter().chain(&self.target_files).cloned().collect()
+    }
+}
 
 #[derive(Debug)]
 pub struct LeveledCompactor {
     data_dir: PathBuf,
-    next_sstable_id: AtomicU64,
+    next_sstable_id: Arc<AtomicU64>, // Shared with the LSMTree flush path so file ids never collide
+    metrics: Option<Arc<PerformanceMetrics>>,
+    use_mmap: bool, // Map newly written SSTables for reads
+    block_cache: Option<Arc<BlockCache>>,
+    bloom_false_positive_rate: f64,
+    max_sstable_bytes: usize, // Output is split into files of about this size
+    rate_limit_bytes_per_sec: Option<u64>, // Cap on output bytes per second, so compaction leaves I/O for foreground work
+}
+
+// Paces a compaction's output to a byte rate by sleeping whenever writing gets ahead of it
+struct Throttle {
+    bytes_per_sec: u64,
+    start: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    // Sleeps shorter than this are skipped and made up on a later write
+    const MIN_SLEEP: Duration = Duration::from_millis(1);
+
+    fn new(bytes_per_sec: u64) -> Self {
+        Self { bytes_per_sec, start: Instant::now(), bytes: 0 }
+    }
+
+    fn record(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+        let due = Duration::from_secs_f64(self.bytes as f64 / self.bytes_per_sec as f64);
+        let ahead = due.saturating_sub(self.start.elapsed());
+        if ahead >= Self::MIN_SLEEP {
+            thread::sleep(ahead);
+        }
+    }
 }
 
 impl LeveledCompactor {
-    pub fn new(data_dir: PathBuf, next_sstable_id: u64) -> Self {
+    pub fn new(data_dir: PathBuf, next_sstable_id: Arc<AtomicU64>) -> Self {
         Self 
         { 
             data_dir, 
-            next_sstable_id: AtomicU64::new(next_sstable_id), 
+            next_sstable_id, 
+            metrics: None,
+            use_mmap: false,
+            block_cache: None,
+            bloom_false_positive_rate: DEFAULT_BLOOM_FALSE_POSITIVE_RATE,
+            max_sstable_bytes: DEFAULT_MAX_SSTABLE_BYTES,
+            rate_limit_bytes_per_sec: None,
         }
     }
 
-    // Main compaction entry point
-    pub fn compact_level(&mut self, level_manager: &mut LevelManager, level: usize) -> DbResult<()> {
-        match level {
-            0 => self.compact_level_0_to_1(level_manager),
-            _ => self.compact_level_n_to_n_plus_1(level_manager, level),
-        }
+    pub fn with_mmap(mut self, use_mmap: bool) -> Self {
+        self.use_mmap = use_mmap;
+        self
     }
 
-    // Level 0 to 1: Handle overlapping SSTables
-    pub fn compact_level_0_to_1(&mut self, level_manager: &mut LevelManager) -> DbResult<()> {
-        println!("Starting Level 0 to Level 1 compaction...");
+    pub fn with_block_cache(mut self, block_cache: Option<Arc<BlockCache>>) -> Self {
+        self.block_cache = block_cache;
+        self
+    }
 
-        // Collect all Level 0 SSTables (they can overlap)
-        let level_0_sstables = level_manager.get_sstables_at_level(0);
-        if level_0_sstables.is_empty() {
-            return Ok(());
-        }
+    pub fn with_bloom_false_positive_rate(mut self, bloom_false_positive_rate: f64) -> Self {
+        self.bloom_false_positive_rate = bloom_false_positive_rate;
+        self
+    }
 
-        // Find the key range covered by Level 0 SSTables
-        let min_key = level_0_sstables.iter()
-            .map(|sstable| sstable.min_key())
-            .min()
-            .unwrap_or("")
-            .to_string();
-        let max_key = level_0_sstables.iter()
-            .map(|sstable| sstable.max_key())
-            .max()
-            .unwrap_or("")
-            .to_string();
-
-        // Find overlapping SSTables in Level 1
-        let level_1_overlapping = level_manager.get_overlapping_sstables(1, &min_key, &max_key);
-    
-        // Merge all overlapping SSTables from both levels
-        let mut all_sstables = level_0_sstables.clone();
-        all_sstables.extend(level_1_overlapping.clone());
-
-        // Merge into new Level 1 SSTable
-        let new_sstables = self.merge_sstables(all_sstables, 1)?;
-
-        // Remove old SSTables
-        let mut old_sstables = level_0_sstables;
-        old_sstables.extend(level_1_overlapping);
-        level_manager.remove_sstables(&old_sstables);
-
-        // Add new SSTables to Level 1
-        for sstable in new_sstables {
-            level_manager.add_sstable(sstable, 1);
-        }
+    pub fn with_max_sstable_bytes(mut self, max_sstable_bytes: usize) -> Self {
+        self.max_sstable_bytes = max_sstable_bytes;
+        self
+    }
 
-        println!("Level 0 to Level 1 compaction completed");
-        Ok(())
+    pub fn with_rate_limit(mut self, rate_limit_bytes_per_sec: Option<u64>) -> Self {
+        self.rate_limit_bytes_per_sec = rate_limit_bytes_per_sec;
+        self
     }
 
+    // Report every merge into the given metrics
+    pub fn set_metrics(&mut self, metrics: Arc<PerformanceMetrics>) {
+        self.metrics = Some(metrics);
+    }
 
-    // Level N to N+1: Standard leveled compaction
-    pub fn compact_level_n_to_n_plus_1(&mut self, level_manager: &mut LevelManager, level: usize) -> DbResult<()> {
-        println!("Starting Level {} to Level {} compaction...", level, level + 1);
+    // Work out what compact_level would consume, without touching any files
+    pub fn plan(&self, level_manager: &LevelManager, level: usize) -> CompactionPlan {
+        let (sources, targets) = Self::select_inputs(level_manager, level);
+        let paths = |sstables: &[SSTable]| sstables.iter().map(|s| s.file_path().to_path_buf()).collect();
+        let input_records = sources.iter().chain(&targets).map(|s| s.len()).sum();
+
+        CompactionPlan {
+            source_level: level,
+            target_level: level + 1,
+            source_files: paths(&sources),
+            target_files: paths(&targets),
+            input_records,
+            estimated_output_records: input_records,
+            estimated_bytes: sources.iter().chain(&targets).map(|s| s.size_bytes()).sum(),
+        }
+    }
 
-        // Get compaction candidates from source level
-        let source_sstables = level_manager.get_compaction_candidates(level);
-        if source_sstables.is_empty() {
-            return Ok(());
+    // Key range covered by a set of SSTables
+    fn key_range(sstables: &[SSTable]) -> (String, String) {
+        let min_key = sstables.iter().map(|sstable| sstable.min_key()).min().unwrap_or("");
+        let max_key = sstables.iter().map(|sstable| sstable.max_key()).max().unwrap_or("");
+        (min_key.to_string(), max_key.to_string())
+    }
+
+    // Source files for a level plus the overlapping files one level down
+    fn select_inputs(level_manager: &LevelManager, level: usize) -> (Vec<SSTable>, Vec<SSTable>) {
+        // Level 0 files can overlap, so all of them go; deeper levels use the candidate selection
+        let sources = match level {
+            0 => level_manager.get_sstables_at_level(0),
+            _ => level_manager.get_compaction_candidates(level),
+        };
+        if sources.is_empty() {
+            return (Vec::new(), Vec::new());
         }
 
-        // Calculate key range of source SSTables
-        let min_key = source_sstables.iter()
-            .map(|s| s.min_key())
-            .min()
-            .unwrap_or("")
-            .to_string();
-        let max_key = source_sstables.iter()
-            .map(|s| s.max_key())
-            .max()
-            .unwrap_or("")
-            .to_string();
-
-        // Find overlapping SSTables in target level
-        let target_level = level + 1;
-        let target_overlapping = level_manager.get_overlapping_sstables(target_level, &min_key, &max_key);
-
-        // Merge source and overlapping target SSTables
-        let mut all_sstables = source_sstables.clone();
-        all_sstables.extend(target_overlapping.clone());
-
-        // Merge into new target level SSTables
-        let new_sstables = self.merge_sstables(all_sstables, target_level)?;
-
-        // Remove old SSTables
-        let mut old_sstables = source_sstables;
-        old_sstables.extend(target_overlapping);
-        level_manager.remove_sstables(&old_sstables);
-
-        // Add new SSTables to target level
-        for sstable in new_sstables {
-            level_manager.add_sstable(sstable, target_level);
+        // Find the key range covered by the source SSTables
+        let (min_key, max_key) = Self::key_range(&sources);
+
+        // Find overlapping SSTables in the target level
+        let targets = level_manager.get_overlapping_sstables(level + 1, &min_key, &max_key);
+        (sources, targets)
+    }
+
+    // Main compaction entry point: runs all three phases while the caller holds the level manager
+    pub fn compact_level(&mut self, level_manager: &mut LevelManager, level: usize) -> DbResult<CompactionResult> {
+        let Some(job) = Self::prepare(level_manager, level) else {
+            return Ok(CompactionResult::default());
+        };
+        let (outputs, result) = self.execute(&job)?;
+        Self::install(level_manager, &job, outputs);
+        Self::remove_inputs(&job);
+        Ok(result)
+    }
+
+    // Phase 1, under a read lock: capture the input files. SSTable handles are cheap clones,
+    // so the lock is held only for the selection itself
+    pub fn prepare(level_manager: &LevelManager, level: usize) -> Option<CompactionJob> {
+        let (source_files, target_files) = Self::select_inputs(level_manager, level);
+        if source_files.is_empty() {
+            return None;
         }
 
-        println!("Level {} → Level {} compaction completed", level, level + 1);
-        Ok(())
+        // Range tombstones still have older data to hide while a deeper level overlaps them
+        let inputs: Vec<SSTable> = source_files.iter().chain(&target_files).cloned().collect();
+        let (min_key, max_key) = Self::key_range(&inputs);
+        let deeper_overlap = (level + 2..=level_manager.get_max_level())
+            .any(|deeper| !level_manager.get_overlapping_sstables(deeper, &min_key, &max_key).is_empty());
+
+        Some(CompactionJob {
+            source_level: level,
+            target_level: level + 1,
+            source_files,
+            target_files,
+            deeper_overlap,
+        })
     }
 
-        // Helper method to merge multiple SSTables
-    fn merge_sstables(&mut self, sstables: Vec<SSTable>, target_level: usize) -> DbResult<Vec<SSTable>> {
-        if sstables.is_empty() {
-            return Ok(Vec::new());
+    // Phase 2, with no level manager lock: merge the inputs and write the new files.
+    // The inputs stay registered and on disk, so readers keep finding every key in them
+    pub fn execute(&mut self, job: &CompactionJob) -> DbResult<(Vec<SSTable>, CompactionResult)> {
+        log::info!("Starting Level {} to Level {} compaction...", job.source_level, job.target_level);
+
+        // Merge all overlapping SSTables from both levels. The target level holds the older
+        // data, so its files go first and the source files override them
+        let mut all_sstables = job.target_files.clone();
+        all_sstables.extend(job.source_files.iter().cloned());
+        self.merge_sstables(all_sstables, job.target_level, job.deeper_overlap)
+    }
+
+    // Phase 3, under a brief write lock: swap the inputs for the outputs in one step,
+    // so a reader sees either the old files or the new ones, never neither
+    pub fn install(level_manager: &mut LevelManager, job: &CompactionJob, outputs: Vec<SSTable>) {
+        level_manager.remove_sstables(&job.inputs());
+        for sstable in outputs {
+            level_manager.add_sstable(sstable, job.target_level);
         }
 
-        // Load all records from all SSTables
-        let mut all_records = BTreeMap::new();
-        
-        for sstable in &sstables {
-            let records = sstable.load_records()?;
-            for record in records {
-                // Later records override earlier ones (newer data wins)
-                all_records.insert(record.key.clone(), record.value.clone());
+        log::info!("Level {} → Level {} compaction completed", job.source_level, job.target_level);
+    }
+
+    // Delete the input files once they are no longer registered. Readers take the level
+    // manager read lock for the whole lookup, so none can still be reading them
+    pub fn remove_inputs(job: &CompactionJob) {
+        for sstable in job.inputs() {
+            sstable.evict_from_cache();
+            if let Err(e) = std::fs::remove_file(sstable.file_path()) {
+                log::warn!("Failed to delete old SSTable file: {}", e);
             }
         }
+    }
 
-        // Remove tombstones (deleted entries)
-        all_records.retain(|_, value| !matches!(value, Value::Tombstone));
+    // Helper method to merge multiple SSTables. Inputs are streamed through a k-way merge and
+    // output files are written as records arrive, so memory use doesn't grow with the inputs.
+    // A record covered by a range tombstone from a later (newer) input is dropped. Point
+    // tombstones, and the range tombstones coalesced, are kept while `deeper_overlap` says older
+    // data below may still need hiding
+    fn merge_sstables(
+        &mut self,
+        sstables: Vec<SSTable>,
+        target_level: usize,
+        deeper_overlap: bool,
+    ) -> DbResult<(Vec<SSTable>, CompactionResult)> {
+        if sstables.is_empty() {
+            return Ok((Vec::new(), CompactionResult::default()));
+        }
 
-        if all_records.is_empty() {
-            return Ok(Vec::new());
+        let start = Instant::now();
+        let mut result = CompactionResult {
+            input_files: sstables.len(),
+            ..CompactionResult::default()
+        };
+
+        // Later inputs override earlier ones (newer data wins)
+        let streams = sstables.iter().map(|sstable| sstable.records_iter()).collect::<DbResult<Vec<_>>>()?;
+        let mut merged = MergeIterator::new(streams)?;
+        let expected_records = sstables.iter().map(|sstable| sstable.len()).sum();
+
+        let range_tombstones: Vec<(usize, &RangeTombstone)> = sstables
+            .iter()
+            .enumerate()
+            .flat_map(|(input, sstable)| sstable.range_tombstones().iter().map(move |t| (input, t)))
+            .collect();
+        let mut pending_tombstones = if deeper_overlap {
+            RangeTombstone::coalesce(range_tombstones.iter().map(|(_, t)| (*t).clone()).collect())
+        } else {
+            Vec::new()
         }
+        .into_iter()
+        .peekable();
+
+        let mut throttle = self.rate_limit_bytes_per_sec.map(Throttle::new);
 
         // Split into multiple SSTables if too large
-        const MAX_SSTABLE_SIZE: usize = 64 * 1024 * 1024; // 64MB per SSTable
         let mut new_sstables = Vec::new();
-        let mut current_data = BTreeMap::new();
+        let mut current: Option<SSTableWriter> = None;
         let mut current_size = 0;
 
-        for (key, value) in all_records {
-            let estimated_size = key.len() + 
-                if let Value::Data(ref s) = value { s.len() } else { 0 };
-            
-            if current_size + estimated_size > MAX_SSTABLE_SIZE && !current_data.is_empty() {
-                // Create SSTable from current data
-                let sstable_id = self.next_sstable_id();
-                let filename = format!("sstable_L{:02}_{:06}.sst", target_level, sstable_id);
-                let filepath = self.data_dir.join(filename);
-                
-                let sstable = SSTable::create_with_level(&filepath, &current_data, target_level)?;
-                new_sstables.push(sstable);
-                
-                // Reset for next SSTable
-                current_data.clear();
+        while let Some(record) = merged.next() {
+            let record = record?;
+
+            let source = merged.last_source();
+            if range_tombstones.iter().any(|&(input, t)| input > source && t.covers(&record.key)) {
+                continue;
+            }
+
+            // A tombstone can go once nothing older below could still hold the key
+            if record.value.is_tombstone() && !deeper_overlap {
+                result.tombstones_dropped += 1;
+                continue;
+            }
+
+            // Range tombstones starting at or before this key go in the file it lands in
+            while let Some(tombstone) = pending_tombstones.next_if(|t| t.start <= record.key) {
+                let writer = match current {
+                    Some(ref mut writer) => writer,
+                    None => current.insert(self.new_writer(target_level, expected_records)?),
+                };
+                writer.append_range_tombstone(tombstone);
+            }
+
+            let estimated_size = record.key.len() + record.value.data_len();
+
+            // Never split inside a range tombstone, so files on a level stay disjoint
+            if current_size + estimated_size > self.max_sstable_bytes
+                && current.as_ref().is_some_and(|writer| {
+                    writer.range_tombstone_end().is_none_or(|end| end < record.key.as_str())
+                })
+                && let Some(writer) = current.take()
+            {
+                new_sstables.push(self.prepare_sstable(writer.finish()?)?);
                 current_size = 0;
             }
-            
-            current_data.insert(key, value);
+
+            let writer = match current {
+                Some(ref mut writer) => writer,
+                None => current.insert(self.new_writer(target_level, expected_records)?),
+            };
+            writer.append(&record)?;
             current_size += estimated_size;
+            result.records_out += 1;
+            if let Some(ref mut throttle) = throttle {
+                throttle.record(estimated_size);
+            }
         }
 
-        // Create final SSTable if there's remaining data
-        if !current_data.is_empty() {
-            let sstable_id = self.next_sstable_id();
-            let filename = format!("sstable_L{:02}_{:06}.sst", target_level, sstable_id);
-            let filepath = self.data_dir.join(filename);
-            
-            let sstable = SSTable::create_with_level(&filepath, &current_data, target_level)?;
-            new_sstables.push(sstable);
+        // Range tombstones past the last record go in the last file
+        for tombstone in pending_tombstones {
+            let writer = match current {
+                Some(ref mut writer) => writer,
+                None => current.insert(self.new_writer(target_level, expected_records)?),
+            };
+            writer.append_range_tombstone(tombstone);
         }
 
-        // Delete old SSTable files
-        for sstable in &sstables {
-            if let Err(e) = std::fs::remove_file(sstable.file_path()) {
-                eprintln!("Warning: Failed to delete old SSTable file: {}", e);
-            }
+        // Finish the last SSTable if there's remaining data
+        if let Some(writer) = current {
+            new_sstables.push(self.prepare_sstable(writer.finish()?)?);
         }
 
-        Ok(new_sstables)
+        result.records_in = merged.records_read();
+        result.output_files = new_sstables.len();
+        self.report_compaction(target_level, sstables.len(), &new_sstables, start);
+        Ok((new_sstables, result))
+    }
+
+    // Writer for the next output file at the target level
+    fn new_writer(&self, target_level: usize, expected_records: usize) -> DbResult<SSTableWriter> {
+        let sstable_id = self.next_sstable_id();
+        let filename = format!("sstable_L{:02}_{:06}.sst", target_level, sstable_id);
+        let filepath = self.data_dir.join(filename);
+        SSTableWriter::create(&filepath, target_level, expected_records, self.bloom_false_positive_rate)
+    }
+
+    fn report_compaction(&self, target_level: usize, input_files: usize, output: &[SSTable], start: Instant) {
+        if let Some(ref metrics) = self.metrics {
+            let bytes_written = output.iter()
+                .filter_map(|sstable| std::fs::metadata(sstable.file_path()).ok())
+                .map(|metadata| metadata.len())
+                .sum();
+            metrics.record_compaction(target_level - 1, input_files, output.len(), bytes_written, start.elapsed());
+        }
     }
 
     // Helper method to generate next SSTable ID
     fn next_sstable_id(&self) -> u64 {
         self.next_sstable_id.fetch_add(1, Ordering::SeqCst)
     }
+
+    // Give compaction output the same read path (mmap, block cache) as the rest of the tree
+    fn prepare_sstable(&self, sstable: SSTable) -> DbResult<SSTable> {
+        let sstable = if self.use_mmap { sstable.with_mmap()? } else { sstable };
+        Ok(match self.block_cache {
+            Some(ref block_cache) => sstable.with_block_cache(block_cache.clone()),
+            None => sstable,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -225,7 +449,7 @@ mod tests {
     #[test]
     fn test_sstable_merging() {
         let temp_dir = tempdir().unwrap();
-        let mut compactor = LeveledCompactor::new(temp_dir.path().to_path_buf(), 1);
+        let mut compactor = LeveledCompactor::new(temp_dir.path().to_path_buf(), Arc::new(AtomicU64::new(1)));
 
         // Create test SSTables with overlapping data
         let mut data1 = BTreeMap::new();
@@ -240,7 +464,7 @@ mod tests {
         let sstable2 = create_test_sstable_with_data(0, data2);
 
         let sstables = vec![sstable1, sstable2];
-        let merged = compactor.merge_sstables(sstables, 1).unwrap();
+        let (merged, _) = compactor.merge_sstables(sstables, 1, false).unwrap();
 
         assert!(!merged.is_empty());
         
@@ -268,7 +492,7 @@ mod tests {
     #[test]
     fn test_tombstone_removal() {
         let temp_dir = tempdir().unwrap();
-        let mut compactor = LeveledCompactor::new(temp_dir.path().to_path_buf(), 1);
+        let mut compactor = LeveledCompactor::new(temp_dir.path().to_path_buf(), Arc::new(AtomicU64::new(1)));
 
         // Create SSTable with tombstone
         let mut data = BTreeMap::new();
@@ -276,7 +500,8 @@ mod tests {
         data.insert("key2".to_string(), Value::Tombstone);
 
         let sstable = create_test_sstable_with_data(0, data);
-        let merged = compactor.merge_sstables(vec![sstable], 1).unwrap();
+        let (merged, result) = compactor.merge_sstables(vec![sstable], 1, false).unwrap();
+        assert_eq!(result.tombstones_dropped, 1);
 
         // Verify tombstone is removed
         let mut total_records = 0;
@@ -299,4 +524,76 @@ mod tests {
         assert_eq!(total_records, 1, "Should only have 1 record after tombstone removal");
         assert!(found_key1, "key1 should be present");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rate_limit_slows_merge_without_changing_output() {
+        let data: BTreeMap<String, Value> = (0..200)
+            .map(|i| (format!("key{:04}", i), Value::Data(format!("value{:04}", i))))
+            .collect();
+        let input_bytes: usize = data.iter().map(|(key, value)| key.len() + value.data_len()).sum();
+        let rate_limit = input_bytes as u64 * 4; // A quarter of a second's worth of output
+
+        let merge = |rate_limit: Option<u64>| {
+            let temp_dir = tempdir().unwrap();
+            let mut compactor = LeveledCompactor::new(temp_dir.path().to_path_buf(), Arc::new(AtomicU64::new(1)))
+                .with_rate_limit(rate_limit);
+            let start = Instant::now();
+            let (merged, _) = compactor.merge_sstables(vec![create_test_sstable_with_data(0, data.clone())], 1, false).unwrap();
+            let elapsed = start.elapsed();
+            let records: Vec<_> = merged.iter()
+                .flat_map(|sstable| sstable.load_records().unwrap())
+                .map(|record| (record.key, record.value))
+                .collect();
+            (records, elapsed)
+        };
+
+        let (unthrottled, unthrottled_elapsed) = merge(None);
+        let (throttled, throttled_elapsed) = merge(Some(rate_limit));
+
+        assert_eq!(throttled, unthrottled);
+        assert_eq!(throttled.len(), 200);
+        assert!(throttled_elapsed >= Duration::from_millis(200), "throttled merge took {:?}", throttled_elapsed);
+        assert!(throttled_elapsed > unthrottled_elapsed);
+    }
+
+    #[test]
+    fn test_plan_matches_compacted_files() {
+        let temp_dir = tempdir().unwrap();
+        let mut compactor = LeveledCompactor::new(temp_dir.path().to_path_buf(), Arc::new(AtomicU64::new(1)));
+        let mut level_manager = LevelManager::new();
+
+        let data = |keys: &[&str]| -> BTreeMap<String, Value> {
+            keys.iter().map(|k| (k.to_string(), Value::Data(format!("v_{}", k)))).collect()
+        };
+        level_manager.add_sstable(create_test_sstable_with_data(0, data(&["b", "d"])), 0);
+        level_manager.add_sstable(create_test_sstable_with_data(0, data(&["c", "f"])), 0);
+        level_manager.add_sstable(create_test_sstable_with_data(1, data(&["a", "c"])), 1);
+        level_manager.add_sstable(create_test_sstable_with_data(1, data(&["x", "z"])), 1);
+
+        let plan = compactor.plan(&level_manager, 0);
+        assert_eq!((plan.source_level, plan.target_level), (0, 1));
+        assert_eq!(plan.source_files.len(), 2);
+        assert_eq!(plan.target_files.len(), 1); // Only [a, c] overlaps [b, f]
+        assert_eq!(plan.input_records, 6);
+        assert!(plan.estimated_bytes > 0);
+
+        // Planning did not change anything
+        assert_eq!(level_manager.get_level_count(0), 2);
+        assert_eq!(level_manager.get_level_count(1), 2);
+
+        let paths = |level_manager: &LevelManager| -> std::collections::BTreeSet<PathBuf> {
+            level_manager.get_all_sstables().iter().map(|s| s.file_path().to_path_buf()).collect()
+        };
+        let before = paths(&level_manager);
+        compactor.compact_level(&mut level_manager, 0).unwrap();
+        let after = paths(&level_manager);
+
+        let consumed: std::collections::BTreeSet<PathBuf> = before.difference(&after).cloned().collect();
+        let planned: std::collections::BTreeSet<PathBuf> =
+            plan.source_files.iter().chain(&plan.target_files).cloned().collect();
+        assert_eq!(consumed, planned);
+
+        // Nothing left to do at Level 0
+        assert!(compactor.plan(&level_manager, 0).is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+// Manifest - records which SSTable files make up the tree and the level each belongs to
+
+use crate::engine::LevelManager;
+use crate::engine::sstable::sync_dir;
+use crate::{DbError, DbResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+pub const MANIFEST_FILE_NAME: &str = "MANIFEST";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub file_name: String,
+    pub level: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub next_sstable_id: u64,
+    pub sstables: Vec<ManifestEntry>, // Level order, Level 0 oldest first
+}
+
+impl Manifest {
+    // Snapshot the current SSTable layout of a LevelManager
+    pub fn from_level_manager(level_manager: &LevelManager, next_sstable_id: u64) -> Self {
+        let sstables = level_manager
+            .get_all_sstables()
+            .iter()
+            .filter_map(|sstable| {
+                let file_name = sstable.file_path().file_name()?.to_str()?.to_string();
+                Some(ManifestEntry {
+                    file_name,
+                    level: sstable.level(),
+                })
+            })
+            .collect();
+
+        Self {
+            next_sstable_id,
+            sstables,
+        }
+    }
+
+    // Write the manifest to a temp file first and rename it over the old one,
+    // so a crash mid-write never leaves a truncated manifest behind
+    pub fn write_to(&self, dir: &Path) -> DbResult<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to serialize manifest: {}", e))
+        })?;
+
+        let temp_path = dir.join(format!("{}.tmp", MANIFEST_FILE_NAME));
+        let mut file = fs::File::create(&temp_path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to write manifest: {}", e))
+        })?;
+        file.write_all(contents.as_bytes()).and_then(|_| file.sync_all()).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to write manifest: {}", e))
+        })?;
+
+        fs::rename(&temp_path, dir.join(MANIFEST_FILE_NAME)).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to replace manifest: {}", e))
+        })?;
+        sync_dir(dir)
+    }
+
+    // Read the manifest from a directory, returning None if there isn't one
+    pub fn read_from(dir: &Path) -> DbResult<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to read manifest: {}", e))
+        })?;
+
+        serde_json::from_str(&contents).map(Some).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to parse manifest: {}", e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp_dir = tempdir().unwrap();
+
+        assert_eq!(Manifest::read_from(temp_dir.path()).unwrap(), None);
+
+        let manifest = Manifest {
+            next_sstable_id: 7,
+            sstables: vec![
+                ManifestEntry { file_name: "sstable_L01_000003.sst".to_string(), level: 1 },
+                ManifestEntry { file_name: "sstable_000006.sst".to_string(), level: 0 },
+            ],
+        };
+        manifest.write_to(temp_dir.path()).unwrap();
+
+        let read_back = Manifest::read_from(temp_dir.path()).unwrap();
+        assert_eq!(read_back, Some(manifest));
+    }
+}
@@ -0,0 +1,173 @@
+// K-way merge over sorted record streams - the core of compaction
+//
+// Each input is already sorted by key, so a min-heap holding the head record of every
+// input yields the merged stream in key order while keeping one record per input in memory.
+
+use crate::engine::sstable::Record;
+use crate::DbResult;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+struct HeapEntry {
+    record: Record,
+    source: usize, // Index of the input the record came from
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.record.key.cmp(&other.record.key).then(self.source.cmp(&other.source))
+    }
+}
+
+// Inputs are given oldest first: when several hold the same key, the record from the
+// last of them wins and the others are skipped. Tombstones are passed through
+pub struct MergeIterator<I> {
+    sources: Vec<I>,
+    heap: BinaryHeap<Reverse<HeapEntry>>,
+    records_read: usize,
+    last_source: usize,
+}
+
+impl<I> MergeIterator<I>
+where
+    I: Iterator<Item = DbResult<Record>>,
+{
+    pub fn new(sources: Vec<I>) -> DbResult<Self> {
+        let mut merge = Self {
+            heap: BinaryHeap::with_capacity(sources.len()),
+            sources,
+            records_read: 0,
+            last_source: 0,
+        };
+        for source in 0..merge.sources.len() {
+            merge.advance(source)?;
+        }
+        Ok(merge)
+    }
+
+    // Records pulled from the inputs so far, duplicates included
+    pub fn records_read(&self) -> usize {
+        self.records_read
+    }
+
+    // Index of the input the most recently returned record came from
+    pub fn last_source(&self) -> usize {
+        self.last_source
+    }
+
+    // Move an input's next record onto the heap
+    fn advance(&mut self, source: usize) -> DbResult<()> {
+        if let Some(record) = self.sources[source].next() {
+            self.records_read += 1;
+            self.heap.push(Reverse(HeapEntry { record: record?, source }));
+        }
+        Ok(())
+    }
+
+    fn next_record(&mut self) -> DbResult<Option<Record>> {
+        let Some(Reverse(mut winner)) = self.heap.pop() else {
+            return Ok(None);
+        };
+        self.advance(winner.source)?;
+
+        // Drain every other input's copy of the same key
+        while let Some(Reverse(next)) = self.heap.peek() {
+            if next.record.key != winner.record.key {
+                break;
+            }
+            let Some(Reverse(duplicate)) = self.heap.pop() else {
+                break;
+            };
+            self.advance(duplicate.source)?;
+            if duplicate.source > winner.source {
+                winner = duplicate;
+            }
+        }
+
+        self.last_source = winner.source;
+        Ok(Some(winner.record))
+    }
+}
+
+impl<I> Iterator for MergeIterator<I>
+where
+    I: Iterator<Item = DbResult<Record>>,
+{
+    type Item = DbResult<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::SSTable;
+    use crate::Value;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_merge_matches_btreemap() {
+        let temp_dir = tempdir().unwrap();
+
+        // Overlapping inputs with updates and deletes spread across them
+        let sstables: Vec<SSTable> = (0..5)
+            .map(|input| {
+                let data: BTreeMap<String, Value> = (0..200)
+                    .filter(|i| (i * 7 + input * 3) % 5 != 0)
+                    .map(|i| {
+                        let value = if (i + input) % 11 == 0 {
+                            Value::Tombstone
+                        } else {
+                            Value::Data(format!("v{}-{}", input, i))
+                        };
+                        (format!("key{:03}", i * (input + 1) % 250), value)
+                    })
+                    .collect();
+                SSTable::create(temp_dir.path().join(format!("{}.sst", input)), &data, 0).unwrap()
+            })
+            .collect();
+
+        // The approach compaction used to take: load everything, later inputs overwrite
+        let mut expected = BTreeMap::new();
+        for sstable in &sstables {
+            for record in sstable.load_records().unwrap() {
+                expected.insert(record.key, record.value);
+            }
+        }
+        let total_records: usize = sstables.iter().map(|s| s.len()).sum();
+
+        let streams = sstables.iter().map(|s| s.records_iter()).collect::<DbResult<Vec<_>>>().unwrap();
+        let mut merge = MergeIterator::new(streams).unwrap();
+        let merged: Vec<(String, Value)> = merge
+            .by_ref()
+            .map(|record| record.map(|r| (r.key, r.value)))
+            .collect::<DbResult<_>>()
+            .unwrap();
+
+        assert_eq!(merged, expected.into_iter().collect::<Vec<_>>());
+        assert_eq!(merge.records_read(), total_records);
+    }
+
+    #[test]
+    fn test_merge_of_no_inputs_is_empty() {
+        let mut merge = MergeIterator::<std::vec::IntoIter<DbResult<Record>>>::new(Vec::new()).unwrap();
+        assert!(merge.next().is_none());
+    }
+}
@@ -5,12 +5,22 @@ pub mod wal;
 pub mod bloom;
 pub mod level;
 pub mod leveled_compaction;
+pub mod manifest;
+pub mod block_cache;
+pub mod merge_iterator;
+#[cfg(feature = "async")]
+pub mod async_ops;
 
 pub use sstable::SSTable;
-pub use lsm::{LSMTree, LSMConfig, LSMStats};
+pub use lsm::{HealthStatus, KeyOrdering, LSMTree, DEFAULT_COLUMN_FAMILY, LSMConfig, LSMStats, LevelReadPlan, ReadPlan, VerifyReport, WriteStallPolicy};
 pub use compaction::Compactor;
-pub use wal::WAL;
+pub use wal::{GroupCommitWAL, WAL};
 pub use bloom::BloomFilter;
-pub use level::{LevelManager, LevelManagerStats, LevelStats};
-pub use leveled_compaction::LeveledCompactor;
+pub use level::{FileSpan, KeyDistribution, LevelManager, LevelManagerStats, LevelStats};
+pub use leveled_compaction::{CompactionJob, CompactionPlan, CompactionResult, LeveledCompactor};
+pub use manifest::{Manifest, ManifestEntry};
+pub use block_cache::BlockCache;
+pub use merge_iterator::MergeIterator;
+#[cfg(feature = "async")]
+pub use async_ops::SharedLSMTree;
 pub use crate::etl::{ETLLoader, CSVParser};
\ No newline at end of file
@@ -1,13 +1,24 @@
 // SSTable (Sorted String Table) implementation
 // An immutable, sorted file format for storing key-value pairs
 
-use crate::engine::BloomFilter;
+use crate::engine::{BlockCache, BloomFilter};
 use crate::{DbError, DbResult, Value};
+use bincode::Options;
+use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const DEFAULT_BLOOM_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+// Footer written after the records: a CRC32 of everything before it, then this marker.
+// Files that don't end in the marker predate checksums and are read unverified
+const FOOTER_MAGIC: u32 = 0x5353_5443; // "SSTC"
+const FOOTER_LEN: usize = 8;
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,14 +27,90 @@ pub struct Record {
     pub value: Value,
 }
 
+// Deletes every key in [start, end) with one entry instead of a tombstone per key. A range
+// tombstone only hides data older than itself: entries in the same MemTable or SSTable are
+// always newer, so it only applies to older sources. On disk it is a record keyed by start
+// whose value is Value::RangeTombstone(end), written after the file's point records
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RangeTombstone {
+    pub start: String,
+    pub end: String,
+}
+
+impl RangeTombstone {
+    pub fn new(start: impl Into<String>, end: impl Into<String>) -> Self {
+        Self { start: start.into(), end: end.into() }
+    }
+
+    pub fn covers(&self, key: &str) -> bool {
+        self.start.as_str() <= key && key < self.end.as_str()
+    }
+
+    // Collapse tombstones into the fewest that cover the same keys, sorted by start
+    pub fn coalesce(mut tombstones: Vec<RangeTombstone>) -> Vec<RangeTombstone> {
+        tombstones.retain(|tombstone| tombstone.start < tombstone.end);
+        tombstones.sort();
+
+        let mut merged: Vec<RangeTombstone> = Vec::with_capacity(tombstones.len());
+        for tombstone in tombstones {
+            match merged.last_mut() {
+                Some(last) if tombstone.start <= last.end => {
+                    if tombstone.end > last.end {
+                        last.end = tombstone.end;
+                    }
+                }
+                _ => merged.push(tombstone),
+            }
+        }
+        merged
+    }
+
+    fn to_record(&self) -> Record {
+        Record { key: self.start.clone(), value: Value::RangeTombstone(self.end.clone()) }
+    }
+}
+
+// Separate a file's point records from the range tombstones stored after them
+fn split_range_tombstones(mut records: Vec<Record>) -> (Vec<Record>, Vec<RangeTombstone>) {
+    let points = records.iter().position(|r| matches!(r.value, Value::RangeTombstone(_))).unwrap_or(records.len());
+    let tombstones = records
+        .split_off(points)
+        .into_iter()
+        .filter_map(|record| match record.value {
+            Value::RangeTombstone(end) => Some(RangeTombstone { start: record.key, end }),
+            _ => None,
+        })
+        .collect();
+    (records, tombstones)
+}
+
+// Key span of a file's points widened to its range tombstones. A tombstone's end is exclusive
+// but is stored as an inclusive max_key, which only ever overstates the span
+fn key_span(
+    min_key: Option<&str>,
+    max_key: Option<&str>,
+    range_tombstones: &[RangeTombstone],
+) -> (String, String) {
+    let starts = range_tombstones.iter().map(|t| t.start.as_str());
+    let ends = range_tombstones.iter().map(|t| t.end.as_str());
+    let min = min_key.into_iter().chain(starts).min().unwrap_or_default();
+    let max = max_key.into_iter().chain(ends).max().unwrap_or_default();
+    (min.to_string(), max.to_string())
+}
+
 #[derive(Debug, Clone)]
 pub struct SSTable {
     file_path: PathBuf,
     record_count: usize,
+    size_bytes: u64, // Size of the data file on disk
     bloom_filter: BloomFilter,
     level: usize,
     min_key: String,
     max_key: String,
+    range_tombstones: Arc<Vec<RangeTombstone>>, // Sorted by start and non-overlapping
+    mmap: Option<Arc<Mmap>>, // Mapped file contents, shared across clones, when reads go through mmap
+    block_cache: Option<Arc<BlockCache>>, // Shared cache of decoded records, checked before reading the file
+    bloom_lookups: Arc<AtomicU64>, // Bloom filter checks made against this file, shared across clones
 }
 
 impl SSTable {
@@ -33,55 +120,25 @@ impl SSTable {
         data: &BTreeMap<String, Value>,
         level: usize,
     ) -> DbResult<Self> {
-        let path = file_path.as_ref().to_path_buf();
-
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| {
-                DbError::InvalidOperation(format!("Failed to create directory: {}", e))
-            })?;
-        }
-
-        let file = File::create(&path).map_err(|e| {
-            DbError::InvalidOperation(format!("Failed to create SSTable file: {}", e))
-        })?;
-
-        let mut writer = BufWriter::new(file);
-
-        // Convert BTreeMap to sorted records
-        let records: Vec<Record> = data
-            .iter()
-            .map(|(k, v)| Record {
-                key: k.clone(),
-                value: v.clone(),
-            })
-            .collect();
-
-        bincode::serialize_into(&mut writer, &records).map_err(|e| {
-            DbError::InvalidOperation(format!("Failed to serialize SSTable: {}", e))
-        })?;
-
-        // Build bloom filter for all keys
-        let mut bloom_filter = BloomFilter::new(data.len(), 0.01); // 1% false positive rate
-        for key in data.keys() {
-            bloom_filter.insert(key);
-        }
-
-        // Calculate min and max keys
-        let min_key = data.keys().next().unwrap_or(&String::new()).clone();
-        let max_key = data.keys().last().unwrap_or(&String::new()).clone();
-
-        Ok(SSTable {
-            file_path: path,
-            record_count: records.len(),
-            bloom_filter,
-            level, 
-            min_key,
-            max_key,
-        })    
+        Self::create_with_bloom_rate(file_path, data, level, DEFAULT_BLOOM_FALSE_POSITIVE_RATE)
     }
 
     // Open an existing SSTable from disk
     pub fn open<P: AsRef<Path>>(file_path: P) -> DbResult<Self> {
+        Self::open_with_level(file_path, 0)
+    }
+
+    // Open an existing SSTable from disk, placing it at a known level
+    pub fn open_with_level<P: AsRef<Path>>(file_path: P, level: usize) -> DbResult<Self> {
+        Self::open_with_bloom_rate(file_path, level, DEFAULT_BLOOM_FALSE_POSITIVE_RATE)
+    }
+
+    // Open an existing SSTable, rebuilding its bloom filter for the given false-positive rate
+    pub fn open_with_bloom_rate<P: AsRef<Path>>(
+        file_path: P,
+        level: usize,
+        false_positive_rate: f64,
+    ) -> DbResult<Self> {
         let path = file_path.as_ref().to_path_buf();
 
         if !path.exists() {
@@ -93,43 +150,74 @@ impl SSTable {
 
         // Read the file to count records
         // In real implementation, we would store metadata separately
-        let records = Self::load_records_from_path(&path)?;
+        let (records, range_tombstones) = split_range_tombstones(Self::load_records_from_path(&path)?);
+        let size_bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
 
         // Build bloom filter by reading all keys from the loaded records
-        let mut bloom_filter = BloomFilter::new(records.len(), 0.01);
+        let mut bloom_filter = BloomFilter::new(records.len(), false_positive_rate);
         for record in &records {
             bloom_filter.insert(&record.key);
         }
 
         // Calculate min and max keys from records
-        let min_key = records.first().map(|r| r.key.clone()).unwrap_or_default();
-        let max_key = records.last().map(|r| r.key.clone()).unwrap_or_default();
+        let (min_key, max_key) = key_span(
+            records.first().map(|r| r.key.as_str()),
+            records.last().map(|r| r.key.as_str()),
+            &range_tombstones,
+        );
 
         Ok(SSTable {
             file_path: path,
             record_count: records.len(),
+            size_bytes,
             bloom_filter,
-            level: 0, // Default to level 0
+            level,
             min_key,
             max_key,
+            range_tombstones: Arc::new(range_tombstones),
+            mmap: None,
+            block_cache: None,
+            bloom_lookups: Arc::new(AtomicU64::new(0)),
         })
     }
 
     pub fn get(&self, key: &str) -> DbResult<Option<String>> {
-        // Check bloom filter first - fast negative lookup
-        if !self.bloom_filter.contains(key) {
-            return Ok(None); // Definitely not in this SSTable
+        match self.get_value(key)? {
+            Some(value) => value.to_text(key),
+            None => Ok(None),
+        }
+    }
+
+    // Like get, but a tombstone comes back as Some(Value::Tombstone) so callers can tell
+    // "deleted here" apart from "not in this SSTable". That includes keys in a range tombstone
+    pub fn get_value(&self, key: &str) -> DbResult<Option<Value>> {
+        self.find(key, Value::clone)
+    }
+
+    // Some(true) for live data, Some(false) for a tombstone, None if the key isn't here.
+    // Unlike get_value the stored value is never copied
+    pub fn contains_live(&self, key: &str) -> DbResult<Option<bool>> {
+        self.find(key, |value| !value.is_tombstone())
+    }
+
+    // Locate a key and map its stored value by reference
+    fn find<T>(&self, key: &str, map: impl FnOnce(&Value) -> T) -> DbResult<Option<T>> {
+        // Keys outside [min_key, max_key] can't be here; cheaper than hashing for the bloom filter
+        if !self.key_in_range(key) {
+            return Ok(None);
+        }
+
+        // Check bloom filter next - fast negative lookup
+        if !self.might_contain(key) {
+            return Ok(self.range_deleted(key).then(|| map(&Value::Tombstone))); // Definitely not in this SSTable
         }
 
         // If bloom filter passed, we can do a full scan
-        let records = Self::load_records_from_path(&self.file_path)?;
+        let records = self.records()?;
 
-        for record in records {
+        for record in records.iter() {
             if record.key == key { // Since PartialEq is derived, we can use == directly
-                match &record.value {
-                    Value::Data(s) => return Ok(Some(s.clone())),
-                    Value::Tombstone => return Ok(None), // Tombstone means key was deleted
-                }
+                return Ok(Some(map(&record.value)));
             }
 
             // Rust does not implement PartialOrd between String and &str,
@@ -138,28 +226,58 @@ impl SSTable {
             }
         }
 
-        Ok(None)
+        Ok(self.range_deleted(key).then(|| map(&Value::Tombstone)))
+    }
+
+    // Range tombstones stored in this file, sorted by start
+    pub fn range_tombstones(&self) -> &[RangeTombstone] {
+        &self.range_tombstones
+    }
+
+    // Whether one of this file's range tombstones covers the key
+    pub fn range_deleted(&self, key: &str) -> bool {
+        self.range_tombstones.iter().any(|tombstone| tombstone.covers(key))
     }
 
     pub fn might_contain(&self, key: &str) -> bool {
+        self.bloom_lookups.fetch_add(1, Ordering::Relaxed);
+        self.bloom_may_contain(key)
+    }
+
+    // The bloom filter's answer without counting a lookup, for planning reads
+    pub fn bloom_may_contain(&self, key: &str) -> bool {
         self.bloom_filter.contains(key)
     }
 
+    // Whether the key falls within this file's key range
+    pub fn key_in_range(&self, key: &str) -> bool {
+        !self.is_empty() && key >= self.min_key.as_str() && key <= self.max_key.as_str()
+    }
+
+    pub fn bloom_lookups(&self) -> u64 {
+        self.bloom_lookups.load(Ordering::Relaxed)
+    }
+
     pub fn bloom_filter_stats(&self) -> (usize, f64) {
         (self.bloom_filter.len(), self.bloom_filter.estimated_false_positive_rate())
     }
 
     // Get all records from the SSTable (for debugging or testing)
     pub fn scan(&self) -> DbResult<Vec<Record>> {
-        Self::load_records_from_path(&self.file_path)
+        self.load_records()
     }
 
     pub fn len(&self) -> usize {
         self.record_count
     }
 
+    pub fn size_bytes(&self) -> u64 {
+        self.size_bytes
+    }
+
+    // No point records and no range tombstones
     pub fn is_empty(&self) -> bool {
-        self.record_count == 0
+        self.record_count == 0 && self.range_tombstones.is_empty()
     }
 
     // Get the file path of the SSTable
@@ -168,23 +286,140 @@ impl SSTable {
     }
 
     // Help method to load records from disk
+    // Map the file into memory so later reads deserialize straight from the mapping
+    // instead of opening and reading the file each time
+    pub fn with_mmap(mut self) -> DbResult<Self> {
+        let file = File::open(&self.file_path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to open SSTable file: {}", e))
+        })?;
+
+        // SAFETY: SSTable files are never modified after creation. Compaction may unlink
+        // one while it is still mapped, which keeps the mapping valid until it is dropped
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to mmap SSTable file: {}", e))
+        })?;
+
+        self.mmap = Some(Arc::new(mmap));
+        Ok(self)
+    }
+
+    pub fn is_mmapped(&self) -> bool {
+        self.mmap.is_some()
+    }
+
+    // Serve reads through a shared record cache
+    pub fn with_block_cache(mut self, block_cache: Arc<BlockCache>) -> Self {
+        self.block_cache = Some(block_cache);
+        self
+    }
+
+    // Forget this file's cached records, e.g. once compaction has deleted it
+    pub fn evict_from_cache(&self) {
+        if let Some(ref block_cache) = self.block_cache {
+            block_cache.invalidate(&self.file_path);
+        }
+    }
+
     pub fn load_records(&self) -> DbResult<Vec<Record>> {
-        Self::load_records_from_path(&self.file_path)
+        let records = self.records()?;
+        Ok(Arc::try_unwrap(records).unwrap_or_else(|shared| (*shared).clone()))
     }
 
-    // Static helper method to load records from disk
-    fn load_records_from_path(file_path: &Path) -> DbResult<Vec<Record>> {
-        let file = File::open(file_path).map_err(|e| {
+    // Records from the block cache when there is one, otherwise straight from the file
+    fn records(&self) -> DbResult<Arc<Vec<Record>>> {
+        match &self.block_cache {
+            Some(block_cache) => block_cache.get_or_load(&self.file_path, || self.read_records()),
+            None => self.read_records().map(Arc::new),
+        }
+    }
+
+    // Re-read the file from disk and check it against its checksum, bypassing the block cache
+    // and any memory map
+    pub fn verify(&self) -> DbResult<()> {
+        Self::load_records_from_path(&self.file_path).map(|_| ())
+    }
+
+    // Point records only: range tombstones were read when the file was opened
+    fn read_records(&self) -> DbResult<Vec<Record>> {
+        let records = match &self.mmap {
+            Some(mmap) => Self::decode_records(&self.file_path, &mmap[..])?,
+            None => Self::load_records_from_path(&self.file_path)?,
+        };
+        Ok(split_range_tombstones(records).0)
+    }
+
+    // Verify the footer checksum, then deserialize the record block
+    fn decode_records(file_path: &Path, bytes: &[u8]) -> DbResult<Vec<Record>> {
+        let body = Self::verified_body(file_path, bytes)?;
+        bincode::deserialize(body).map_err(|e| {
+            DbError::Corruption(format!("Failed to deserialize SSTable {}: {}", file_path.display(), e))
+        })
+    }
+
+    // The file contents without the footer, once its checksum matches
+    fn verified_body<'a>(file_path: &Path, bytes: &'a [u8]) -> DbResult<&'a [u8]> {
+        let Some(split) = bytes.len().checked_sub(FOOTER_LEN) else {
+            return Ok(bytes);
+        };
+        let (body, footer) = bytes.split_at(split);
+        let (stored, magic) = footer.split_at(4);
+        if magic != FOOTER_MAGIC.to_le_bytes() {
+            return Ok(bytes);
+        }
+
+        let stored = u32::from_le_bytes(stored.try_into().expect("footer checksum is 4 bytes"));
+        Self::check_crc(file_path, stored, crc32fast::hash(body))?;
+        Ok(body)
+    }
+
+    fn check_crc(file_path: &Path, stored: u32, computed: u32) -> DbResult<()> {
+        if stored != computed {
+            return Err(DbError::Corruption(format!(
+                "SSTable {} checksum mismatch (stored {:08x}, computed {:08x})",
+                file_path.display(), stored, computed
+            )));
+        }
+        Ok(())
+    }
+
+    // Stream point records from the file one at a time, in key order, without the block cache.
+    // Compaction reads through this so a merge holds one record per input, not whole files.
+    // A bincode Vec<Record> is a u64 count followed by the records back to back, so files
+    // written as one whole vector stream the same way as SSTableWriter output
+    pub fn records_iter(&self) -> DbResult<RecordIter> {
+        let file = File::open(&self.file_path).map_err(|e| {
             DbError::InvalidOperation(format!("Failed to open SSTable file: {}", e))
         })?;
 
-        let reader = BufReader::new(file);
+        let file_len = file.metadata().map(|m| m.len()).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to read SSTable metadata: {}", e))
+        })?;
+        let mut reader = HashingReader {
+            inner: BufReader::new(file),
+            hasher: crc32fast::Hasher::new(),
+        };
+        let remaining: u64 = bincode::deserialize_from(&mut reader).map_err(|e| {
+            DbError::Corruption(format!("Failed to read SSTable {} record count: {}", self.file_path.display(), e))
+        })?;
 
-        bincode::deserialize_from(reader).map_err(|e| {
-            DbError::InvalidOperation(format!("Failed to deserialize SSTable: {}", e))
+        Ok(RecordIter {
+            file_path: self.file_path.clone(),
+            file_len,
+            reader,
+            remaining,
+            verified: false,
         })
     }
 
+    // Static helper method to load records from disk
+    fn load_records_from_path(file_path: &Path) -> DbResult<Vec<Record>> {
+        let bytes = std::fs::read(file_path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to open SSTable file: {}", e))
+        })?;
+
+        Self::decode_records(file_path, &bytes)
+    }
+
 
     pub fn level(&self) -> usize {
         self.level
@@ -202,6 +437,160 @@ impl SSTable {
         file_path: P,
         data: &BTreeMap<String, Value>,
         level: usize,
+    ) -> DbResult<Self> {
+        Self::create_with_bloom_rate(file_path, data, level, DEFAULT_BLOOM_FALSE_POSITIVE_RATE)
+    }
+
+    // Create an SSTable whose bloom filter targets the given false-positive rate
+    pub fn create_with_bloom_rate<P: AsRef<Path>>(
+        file_path: P,
+        data: &BTreeMap<String, Value>,
+        level: usize,
+        false_positive_rate: f64,
+    ) -> DbResult<Self> {
+        let path = file_path.as_ref().to_path_buf();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to create directory: {}", e))
+            })?;
+        }
+
+        // BTreeMap iteration is already sorted, which is what the writer needs
+        let mut writer = SSTableWriter::create(&path, level, data.len(), false_positive_rate)?;
+        for (key, value) in data {
+            writer.append(&Record {
+                key: key.clone(),
+                value: value.clone(),
+            })?;
+        }
+        writer.finish()
+    }
+}
+
+// Make renames and newly created files in a directory durable. Only Unix can open a
+// directory to fsync it; elsewhere the file syncs have to do
+pub(crate) fn sync_dir(dir: &Path) -> DbResult<()> {
+    #[cfg(unix)]
+    {
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        File::open(dir).and_then(|d| d.sync_all()).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to sync directory {}: {}", dir.display(), e))
+        })?;
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+// Checksums bytes as they are read
+struct HashingReader<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+// Records read back one at a time from an SSTable file. The footer checksum is checked
+// after the last record, so a corrupt file ends the stream with an error
+pub struct RecordIter {
+    file_path: PathBuf,
+    file_len: u64, // No record can be longer than the file; bounds allocations on a corrupt length
+    reader: HashingReader<BufReader<File>>,
+    remaining: u64,
+    verified: bool,
+}
+
+impl RecordIter {
+    fn verify_footer(&mut self) -> DbResult<()> {
+        let computed = self.reader.hasher.clone().finalize();
+        let mut footer = [0u8; FOOTER_LEN];
+        match self.reader.inner.read_exact(&mut footer) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()), // No footer to check
+            Err(e) => {
+                return Err(DbError::InvalidOperation(format!("Failed to read SSTable footer: {}", e)));
+            }
+        }
+
+        let (stored, magic) = footer.split_at(4);
+        if magic != FOOTER_MAGIC.to_le_bytes() {
+            return Ok(());
+        }
+        let stored = u32::from_le_bytes(stored.try_into().expect("footer checksum is 4 bytes"));
+        SSTable::check_crc(&self.file_path, stored, computed)
+    }
+}
+
+impl Iterator for RecordIter {
+    type Item = DbResult<Record>;
+
+    // Range tombstones trail the point records and are skipped; SSTable::range_tombstones has them
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_record() {
+                Some(Ok(Record { value: Value::RangeTombstone(_), .. })) => continue,
+                other => return other,
+            }
+        }
+    }
+}
+
+impl RecordIter {
+    fn next_record(&mut self) -> Option<DbResult<Record>> {
+        if self.remaining == 0 {
+            if self.verified {
+                return None;
+            }
+            self.verified = true;
+            return self.verify_footer().err().map(Err);
+        }
+        self.remaining -= 1;
+
+        // Same encoding as the top-level bincode functions, plus a size limit
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(self.file_len);
+        let record = options.deserialize_from(&mut self.reader).map_err(|e| {
+            // A corrupt record leaves the reader at an unknown offset, so stop here
+            self.remaining = 0;
+            self.verified = true;
+            DbError::Corruption(format!("Failed to deserialize SSTable {} record: {}", self.file_path.display(), e))
+        });
+        Some(record)
+    }
+}
+
+// Writes an SSTable one record at a time, so its contents never have to be in memory at once.
+// The layout is the same as a serialized Vec<Record> (a u64 count, then the records), with the
+// count patched in by finish once it is known
+pub struct SSTableWriter {
+    file_path: PathBuf,
+    writer: BufWriter<File>,
+    level: usize,
+    record_count: u64,
+    size_bytes: u64,
+    bloom_filter: BloomFilter,
+    min_key: Option<String>,
+    max_key: Option<String>,
+    range_tombstones: Vec<RangeTombstone>, // Written after the point records by finish
+    records_crc: crc32fast::Hasher, // Over the records; the count is folded in by finish
+}
+
+impl SSTableWriter {
+    // expected_records sizes the bloom filter; an upper bound is fine
+    pub fn create<P: AsRef<Path>>(
+        file_path: P,
+        level: usize,
+        expected_records: usize,
+        false_positive_rate: f64,
     ) -> DbResult<Self> {
         let path = file_path.as_ref().to_path_buf();
 
@@ -216,37 +605,139 @@ impl SSTable {
         })?;
 
         let mut writer = BufWriter::new(file);
+        Self::write_count(&mut writer, 0)?;
+
+        Ok(Self {
+            file_path: path,
+            writer,
+            level,
+            record_count: 0,
+            size_bytes: std::mem::size_of::<u64>() as u64,
+            bloom_filter: BloomFilter::new(expected_records, false_positive_rate),
+            min_key: None,
+            max_key: None,
+            range_tombstones: Vec::new(),
+            records_crc: crc32fast::Hasher::new(),
+        })
+    }
+
+    // Records must arrive in strictly increasing key order
+    pub fn append(&mut self, record: &Record) -> DbResult<()> {
+        if matches!(record.value, Value::RangeTombstone(_)) {
+            return Err(DbError::InvalidOperation(
+                "Range tombstones are added with append_range_tombstone".to_string(),
+            ));
+        }
+        if let Some(ref max_key) = self.max_key
+            && record.key.as_str() <= max_key.as_str()
+        {
+            return Err(DbError::InvalidOperation(format!(
+                "SSTable records out of order: '{}' after '{}'",
+                record.key, max_key
+            )));
+        }
+        self.write_record(record)?;
+
+        self.bloom_filter.insert(&record.key);
+        if self.min_key.is_none() {
+            self.min_key = Some(record.key.clone());
+        }
+        self.max_key = Some(record.key.clone());
+        Ok(())
+    }
+
+    // Range tombstones may arrive in any order and are stored once the points are written
+    pub fn append_range_tombstone(&mut self, tombstone: RangeTombstone) {
+        self.range_tombstones.push(tombstone);
+    }
+
+    // Largest end among the range tombstones added so far
+    pub fn range_tombstone_end(&self) -> Option<&str> {
+        self.range_tombstones.iter().map(|t| t.end.as_str()).max()
+    }
 
-        // Convert BTreeMap to sorted records
-        let records: Vec<Record> = data
-            .iter()
-            .map(|(k, v)| Record {
-                key: k.clone(),
-                value: v.clone(),
-            })
-            .collect();
-
-        bincode::serialize_into(&mut writer, &records).map_err(|e| {
-            DbError::InvalidOperation(format!("Failed to serialize SSTable: {}", e))
+    fn write_record(&mut self, record: &Record) -> DbResult<()> {
+        let bytes = bincode::serialize(record).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to serialize SSTable record: {}", e))
+        })?;
+        self.writer.write_all(&bytes).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to write SSTable record: {}", e))
         })?;
+        self.records_crc.update(&bytes);
+        self.size_bytes += bytes.len() as u64;
+        self.record_count += 1;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.record_count as usize
+    }
 
-        // Build bloom filter for all keys
-        let mut bloom_filter = BloomFilter::new(data.len(), 0.01);
-        for key in data.keys() {
-            bloom_filter.insert(key);
+    pub fn is_empty(&self) -> bool {
+        self.record_count == 0 && self.range_tombstones.is_empty()
+    }
+
+    // Bytes written so far, count prefix included
+    pub fn size_bytes(&self) -> u64 {
+        self.size_bytes
+    }
+
+    pub fn finish(mut self) -> DbResult<SSTable> {
+        let point_count = self.record_count as usize;
+        let range_tombstones = RangeTombstone::coalesce(std::mem::take(&mut self.range_tombstones));
+        for tombstone in &range_tombstones {
+            self.write_record(&tombstone.to_record())?;
         }
 
-        // Calculate min/max keys
-        let min_key = data.keys().next().unwrap_or(&String::new()).clone();
-        let max_key = data.keys().last().unwrap_or(&String::new()).clone();
+        // The checksum covers the final count followed by the records
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(&self.record_count.to_le_bytes());
+        crc.combine(&self.records_crc);
 
+        let mut footer = [0u8; FOOTER_LEN];
+        footer[..4].copy_from_slice(&crc.finalize().to_le_bytes());
+        footer[4..].copy_from_slice(&FOOTER_MAGIC.to_le_bytes());
+        self.writer.write_all(&footer).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to write SSTable footer: {}", e))
+        })?;
+        self.size_bytes += FOOTER_LEN as u64;
+
+        self.writer.seek(SeekFrom::Start(0)).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to seek SSTable file: {}", e))
+        })?;
+        Self::write_count(&mut self.writer, self.record_count)?;
+        self.writer.flush().map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to flush SSTable file: {}", e))
+        })?;
+
+        // Callers go on to drop the data's other copy (the WAL, or compaction inputs), so the
+        // file and its directory entry must be on disk before this returns
+        self.writer.get_ref().sync_all().map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to sync SSTable file: {}", e))
+        })?;
+        if let Some(dir) = self.file_path.parent() {
+            sync_dir(dir)?;
+        }
+
+        let (min_key, max_key) = key_span(self.min_key.as_deref(), self.max_key.as_deref(), &range_tombstones);
         Ok(SSTable {
-            file_path: path,
-            record_count: records.len(),
-            bloom_filter,
-            level,
+            file_path: self.file_path,
+            record_count: point_count,
+            size_bytes: self.size_bytes,
+            bloom_filter: self.bloom_filter,
+            level: self.level,
             min_key,
             max_key,
+            range_tombstones: Arc::new(range_tombstones),
+            mmap: None,
+            block_cache: None,
+            bloom_lookups: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    fn write_count(writer: &mut BufWriter<File>, count: u64) -> DbResult<()> {
+        bincode::serialize_into(writer, &count).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to write SSTable record count: {}", e))
         })
     }
 }
@@ -258,6 +749,109 @@ mod tests {
     // use std::collections::BTreeMap;
     // use tempfile::tempdir;
 
+    #[test]
+    fn test_empty_sstable() {
+        use super::*;
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("empty.sst");
+
+        let sstable = SSTable::create(&path, &BTreeMap::new(), 0).unwrap();
+        assert!(sstable.is_empty());
+        assert_eq!(sstable.get("key").unwrap(), None);
+        assert!(sstable.scan().unwrap().is_empty());
+
+        let reopened = SSTable::open(&path).unwrap();
+        assert!(reopened.is_empty());
+        assert_eq!(reopened.get("key").unwrap(), None);
+        assert!(!reopened.might_contain("key"));
+    }
+
+    #[test]
+    fn test_mmap_reads_match_buffered_reads() {
+        use super::*;
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let mut data = BTreeMap::new();
+        for i in 0..100 {
+            data.insert(format!("key{:03}", i), Value::Data(format!("value{}", i)));
+        }
+        data.insert("key050".to_string(), Value::Tombstone);
+
+        let buffered = SSTable::create_with_level(temp_dir.path().join("test.sst"), &data, 0).unwrap();
+        let mapped = buffered.clone().with_mmap().unwrap();
+        assert!(!buffered.is_mmapped());
+        assert!(mapped.is_mmapped());
+
+        assert_eq!(
+            format!("{:?}", buffered.scan().unwrap()),
+            format!("{:?}", mapped.scan().unwrap())
+        );
+        for key in ["key000", "key050", "key099", "missing"] {
+            assert_eq!(buffered.get_value(key).unwrap(), mapped.get_value(key).unwrap());
+        }
+        assert_eq!(mapped.get("key050").unwrap(), None);
+        assert_eq!(mapped.get("key042").unwrap(), Some("value42".to_string()));
+    }
+
+    #[test]
+    fn test_records_iter_matches_scan() {
+        use super::*;
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let mut data = BTreeMap::new();
+        for i in 0..500 {
+            data.insert(format!("key{:03}", i), Value::Data(format!("value{}", i)));
+        }
+        data.insert("key250".to_string(), Value::Tombstone);
+
+        let sstable = SSTable::create(temp_dir.path().join("new.sst"), &data, 0).unwrap();
+        let streamed = sstable.records_iter().unwrap().collect::<DbResult<Vec<_>>>().unwrap();
+        assert_eq!(format!("{:?}", streamed), format!("{:?}", sstable.scan().unwrap()));
+
+        // Files written as one serialized vector, as before SSTableWriter, stream the same way
+        let old_path = temp_dir.path().join("old.sst");
+        let records = sstable.scan().unwrap();
+        bincode::serialize_into(BufWriter::new(File::create(&old_path).unwrap()), &records).unwrap();
+        let old = SSTable::open(&old_path).unwrap();
+        let streamed_old = old.records_iter().unwrap().collect::<DbResult<Vec<_>>>().unwrap();
+        assert_eq!(format!("{:?}", streamed_old), format!("{:?}", records));
+        assert_eq!(old.size_bytes() + FOOTER_LEN as u64, sstable.size_bytes());
+    }
+
+    #[test]
+    fn test_flipped_byte_is_reported_as_corruption() {
+        use super::*;
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("corrupt.sst");
+        let mut data = BTreeMap::new();
+        for i in 0..50 {
+            data.insert(format!("key{:02}", i), Value::Data(format!("value{}", i)));
+        }
+        let sstable = SSTable::create(&path, &data, 0).unwrap();
+        assert!(SSTable::open(&path).is_ok());
+
+        // Flip one bit inside a value in the middle of the record block
+        let mut bytes = std::fs::read(&path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0x01;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let is_corruption = |result: DbResult<Vec<Record>>| match result {
+            Err(DbError::Corruption(msg)) => msg.contains("corrupt.sst"),
+            _ => false,
+        };
+        assert!(matches!(SSTable::open(&path), Err(DbError::Corruption(_))));
+        assert!(is_corruption(sstable.load_records()));
+        assert!(is_corruption(sstable.clone().with_mmap().unwrap().load_records()));
+        assert!(is_corruption(sstable.records_iter().unwrap().collect()));
+    }
+
 //     #[test]
 //     fn test_sstable_create_and_read() {
 //         // Create temporary directory
@@ -1,57 +1,211 @@
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use crate::{DbError, DbResult, WALEntry};
+use parking_lot::{Condvar, Mutex};
 
+pub const WAL_FILE_NAME: &str = "wal.log";
+
+// The log is a sequence of segment files: the base path (e.g. wal.log) is segment 0,
+// later segments append their number (wal.log.1, wal.log.2, ...). Without a segment
+// size cap everything stays in the base file.
 #[derive(Debug)]
 pub struct WAL {
     file_path: PathBuf,
     writer: BufWriter<File>,
+    segment: u64,               // Segment currently being written
+    segment_len: u64,           // Bytes in the current segment
+    segment_bytes: Option<u64>, // Roll to a new segment once the current one reaches this
+    flushes: u64,               // Writer flushes issued so far
 }
 
 impl WAL {
     pub fn new<P: AsRef<Path>>(file_path: P) -> DbResult<Self> {
         let file_path = file_path.as_ref().to_path_buf();
 
+        // Keep appending to the newest existing segment
+        let segment = Self::segment_numbers(&file_path)?.last().copied().unwrap_or(0);
+        let (writer, segment_len) = Self::open_segment(&Self::segment_path(&file_path, segment))?;
+
+        Ok(Self {
+            file_path,
+            writer,
+            segment,
+            segment_len,
+            segment_bytes: None,
+            flushes: 0,
+        })
+    }
+
+    // Cap each segment at roughly this many bytes
+    pub fn with_segment_bytes(mut self, segment_bytes: Option<u64>) -> Self {
+        self.segment_bytes = segment_bytes;
+        self
+    }
+
+    // Existing segment files for a WAL base path, oldest first
+    pub fn segment_paths(file_path: &Path) -> DbResult<Vec<PathBuf>> {
+        Ok(Self::segment_numbers(file_path)?
+            .into_iter()
+            .map(|segment| Self::segment_path(file_path, segment))
+            .collect())
+    }
+
+    fn segment_path(file_path: &Path, segment: u64) -> PathBuf {
+        if segment == 0 {
+            return file_path.to_path_buf();
+        }
+        let mut name = file_path.as_os_str().to_os_string();
+        name.push(format!(".{}", segment));
+        PathBuf::from(name)
+    }
+
+    fn segment_numbers(file_path: &Path) -> DbResult<Vec<u64>> {
+        let mut segments = Vec::new();
+        if file_path.exists() {
+            segments.push(0);
+        }
+
+        let base_name = match file_path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => format!("{}.", name),
+            None => return Ok(segments),
+        };
+        let dir = match file_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        if !dir.exists() {
+            return Ok(segments);
+        }
+
+        let entries = fs::read_dir(dir)
+            .map_err(|e| DbError::InvalidOperation(format!("Failed to list WAL segments: {}", e)))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if let Some(segment) = name.to_str()
+                .and_then(|n| n.strip_prefix(&base_name))
+                .and_then(|suffix| suffix.parse::<u64>().ok())
+            {
+                segments.push(segment);
+            }
+        }
+
+        segments.sort_unstable();
+        Ok(segments)
+    }
+
+    fn open_segment(path: &Path) -> DbResult<(BufWriter<File>, u64)> {
         let file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open(&file_path)
+            .open(path)
             .map_err(|e| DbError::InvalidOperation(format!("Failed to open WAL file: {}", e)))?;
 
-        let writer = BufWriter::new(file);
+        let len = file.metadata()
+            .map_err(|e| DbError::InvalidOperation(format!("Failed to read WAL metadata: {}", e)))?
+            .len();
 
-        Ok(Self {
-            file_path,
-            writer,
-        })
+        Ok((BufWriter::new(file), len))
     }
 
-    pub fn append(&mut self, entry: &WALEntry) -> DbResult<()> {
+    // Start a new segment if the current one has reached the cap
+    fn maybe_rotate(&mut self) -> DbResult<()> {
+        let Some(cap) = self.segment_bytes else {
+            return Ok(());
+        };
+        if self.segment_len == 0 || self.segment_len < cap {
+            return Ok(());
+        }
+
+        self.writer.flush()
+            .map_err(|e| DbError::InvalidOperation(format!("Failed to flush WAL: {}", e)))?;
+
+        let (writer, segment_len) = Self::open_segment(&Self::segment_path(&self.file_path, self.segment + 1))?;
+        self.writer = writer;
+        self.segment += 1;
+        self.segment_len = segment_len;
+        Ok(())
+    }
+
+    // Length-prefixed entry into the current segment, without flushing
+    fn write_entry(&mut self, entry: &WALEntry) -> DbResult<()> {
         let serialized = bincode::serialize(entry)
             .map_err(|e| DbError::InvalidOperation(format!("Failed to serialize WAL entry: {}", e)))?;
-        
+
         // Write the length first then the data
         let len = serialized.len() as u32;
         self.writer.write_all(&len.to_le_bytes())
             .map_err(|e| DbError::InvalidOperation(format!("Failed to write WAL entry length: {}", e)))?;
-        
+
         self.writer.write_all(&serialized)
             .map_err(|e| DbError::InvalidOperation(format!("Failed to write WAL entry: {}", e)))?;
 
+        self.segment_len += 4 + serialized.len() as u64;
+        Ok(())
+    }
+
+    // Number of segment files currently making up the log
+    pub fn segment_count(&self) -> DbResult<usize> {
+        Ok(Self::segment_numbers(&self.file_path)?.len())
+    }
+
+    pub fn append(&mut self, entry: &WALEntry) -> DbResult<()> {
+        self.maybe_rotate()?;
+        self.write_entry(entry)?;
+
         // Force sync to disk for durability
+        self.flush_writer()
+    }
+
+    // Append several entries with a single flush at the end. The batch may roll over
+    // into a new segment; recovery reads segments back to back so that's harmless
+    pub fn append_batch(&mut self, entries: &[WALEntry]) -> DbResult<()> {
+        for entry in entries {
+            self.maybe_rotate()?;
+            self.write_entry(entry)?;
+        }
+
+        self.flush_writer()
+    }
+
+    fn flush_writer(&mut self) -> DbResult<()> {
+        self.flushes += 1;
         self.writer.flush()
-            .map_err(|e| DbError::InvalidOperation(format!("Failed to flush WAL: {}", e)))?;
+            .map_err(|e| DbError::InvalidOperation(format!("Failed to flush WAL: {}", e)))
+    }
 
-        Ok(())
+    // How many times appends have flushed the writer
+    pub fn flush_count(&self) -> u64 {
+        self.flushes
+    }
+
+    // Flush buffered writes and fsync the WAL file
+    pub fn sync(&mut self) -> DbResult<()> {
+        self.writer.flush()
+            .map_err(|e| DbError::InvalidOperation(format!("Failed to flush WAL: {}", e)))?;
+        self.writer.get_ref().sync_all()
+            .map_err(|e| DbError::InvalidOperation(format!("Failed to sync WAL: {}", e)))
     }
 
+    // Every entry across all segments, in the order they were written
     pub fn read_all(&self) -> DbResult<Vec<WALEntry>> {
-        let file = File::open(&self.file_path)
+        Self::read_path(&self.file_path)
+    }
+
+    // Like read_all, but without opening the WAL for writing, so nothing is created or changed
+    pub fn read_path(file_path: &Path) -> DbResult<Vec<WALEntry>> {
+        let mut entries = Vec::new();
+        for path in Self::segment_paths(file_path)? {
+            Self::read_segment(&path, &mut entries)?;
+        }
+        Ok(entries)
+    }
+
+    fn read_segment(path: &Path, entries: &mut Vec<WALEntry>) -> DbResult<()> {
+        let file = File::open(path)
             .map_err(|e| DbError::InvalidOperation(format!("Failed to open WAL for reading: {}", e)))?;
 
         let mut reader = BufReader::new(file);
-        let mut entries = Vec::new();
 
         loop {
             let mut len_bytes = [0u8; 4];
@@ -80,23 +234,253 @@ impl WAL {
             }
         }
 
-        Ok(entries)
+        Ok(())
     }
 
+    // Drop every entry: later segments are deleted and the base file is emptied
     pub fn truncate(&mut self) -> DbResult<()> {
-        // Close the current writer
-        self.writer.flush()
-            .map_err(|e| DbError::InvalidOperation(format!("Failed to flush before truncate: {}", e)))?;
+        for segment in Self::segment_numbers(&self.file_path)? {
+            if segment != 0 {
+                fs::remove_file(Self::segment_path(&self.file_path, segment))
+                    .map_err(|e| DbError::InvalidOperation(format!("Failed to remove WAL segment: {}", e)))?;
+            }
+        }
     
         let file = OpenOptions::new()
+            .create(true)
             .write(true)
             .truncate(true)
             .open(&self.file_path)
             .map_err(|e| DbError::InvalidOperation(format!("Failed to open WAL for truncation: {}", e)))?;
     
-        // Recreate the writer
-        self.writer = BufWriter::new(file);
+        // Recreate the writer. Anything the old one still buffers is discarded rather than
+        // flushed: it's being dropped anyway, and after a failed write (e.g. a full disk)
+        // the flush would only fail again
+        let (_, _discarded) = std::mem::replace(&mut self.writer, BufWriter::new(file)).into_parts();
+        self.segment = 0;
+        self.segment_len = 0;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// Group commit - lets many threads append at once while one leader writes and flushes
+// everything queued so far in a single batch. Each appender returns once the batch
+// holding its entries is durable (or has failed).
+#[derive(Debug)]
+pub struct GroupCommitWAL {
+    wal: Mutex<WAL>,
+    queue: Mutex<CommitQueue>,
+    committed: Condvar,
+    fsync: bool, // fsync each batch rather than just flushing it to the OS
+}
+
+#[derive(Debug, Default)]
+struct CommitQueue {
+    pending: Vec<WALEntry>,
+    pending_callers: usize,   // append_batch calls with entries in `pending`
+    next_seq: u64,            // Sequence number of the last enqueued entry
+    durable_seq: u64,         // Every entry up to here has been written and flushed
+    leader_active: bool,      // Someone is writing a batch right now
+    error: Option<String>,    // A failed batch refuses later appends until truncate rewrites the log
+    failed: Vec<FailedBatch>, // Lost entries whose callers haven't all been told yet
+}
+
+// Entries after_seq+1..=through_seq never reached the log
+#[derive(Debug)]
+struct FailedBatch {
+    after_seq: u64,
+    through_seq: u64,
+    error: String,
+    callers: usize, // Callers still waiting to see the error
+}
+
+impl GroupCommitWAL {
+    pub fn new(wal: WAL) -> Self {
+        Self {
+            wal: Mutex::new(wal),
+            queue: Mutex::new(CommitQueue::default()),
+            committed: Condvar::new(),
+            fsync: false,
+        }
+    }
+
+    // fsync every batch; the cost is shared by all appenders whose entries it carries
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    pub fn append(&self, entry: &WALEntry) -> DbResult<()> {
+        self.append_batch(std::slice::from_ref(entry))
+    }
+
+    // Entries of one call are queued together, so they land contiguously in the log
+    pub fn append_batch(&self, entries: &[WALEntry]) -> DbResult<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mut queue = self.queue.lock();
+        if let Some(ref error) = queue.error {
+            return Err(Self::commit_failed(error));
+        }
+        queue.pending.extend_from_slice(entries);
+        queue.pending_callers += 1;
+        queue.next_seq += entries.len() as u64;
+        let my_seq = queue.next_seq;
+
+        loop {
+            // Checked first: a later batch can be durable while ours was lost
+            if let Some(pos) = queue.failed.iter().position(|f| f.after_seq < my_seq && my_seq <= f.through_seq) {
+                let failed = &mut queue.failed[pos];
+                failed.callers -= 1;
+                let error = Self::commit_failed(&failed.error);
+                if failed.callers == 0 {
+                    queue.failed.remove(pos);
+                }
+                return Err(error);
+            }
+            if queue.durable_seq >= my_seq {
+                return Ok(());
+            }
+
+            if queue.leader_active {
+                // Someone else is writing; our entries go out with the next batch
+                self.committed.wait(&mut queue);
+                continue;
+            }
+
+            // Become the leader for everything queued so far
+            queue.leader_active = true;
+            let batch = std::mem::take(&mut queue.pending);
+            let batch_callers = std::mem::take(&mut queue.pending_callers);
+            let batch_seq = queue.next_seq;
+            drop(queue);
+
+            let mut wal = self.wal.lock();
+            let result = wal.append_batch(&batch).and_then(|_| if self.fsync { wal.sync() } else { Ok(()) });
+
+            // Record the outcome before releasing the log, so a truncate can't clear the
+            // error ahead of it
+            queue = self.queue.lock();
+            drop(wal);
+            queue.leader_active = false;
+            match result {
+                Ok(()) => queue.durable_seq = batch_seq,
+                Err(e) => {
+                    // The log may now end in a torn record, so entries queued behind the batch
+                    // are dropped with it and appends stop until truncate rewrites the log
+                    queue.pending.clear();
+                    let callers = batch_callers + std::mem::take(&mut queue.pending_callers);
+                    let failed = FailedBatch {
+                        after_seq: queue.durable_seq,
+                        through_seq: queue.next_seq,
+                        error: e.to_string(),
+                        callers,
+                    };
+                    queue.failed.push(failed);
+                    queue.error = Some(e.to_string());
+                }
+            }
+            self.committed.notify_all();
+        }
+    }
+
+    fn commit_failed(error: &str) -> DbError {
+        DbError::InvalidOperation(format!("WAL group commit failed: {}", error))
+    }
+
+    pub fn sync(&self) -> DbResult<()> {
+        self.wal.lock().sync()
+    }
+
+    pub fn read_all(&self) -> DbResult<Vec<WALEntry>> {
+        self.wal.lock().read_all()
+    }
+
+    // Rewriting the log also drops any torn batch, so appends resume after a failure
+    pub fn truncate(&self) -> DbResult<()> {
+        let mut wal = self.wal.lock();
+        wal.truncate()?;
+        self.queue.lock().error = None;
+        Ok(())
+    }
+
+    pub fn flush_count(&self) -> u64 {
+        self.wal.lock().flush_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_group_commit_batches_concurrent_appends() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(WAL_FILE_NAME);
+        let wal = Arc::new(GroupCommitWAL::new(WAL::new(&path).unwrap()).with_fsync(true));
+
+        let threads = 8;
+        let per_thread = 500;
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                let wal = wal.clone();
+                thread::spawn(move || {
+                    for i in 0..per_thread {
+                        wal.append(&WALEntry::Insert {
+                            key: format!("t{}_key{:04}", t, i),
+                            value: "x".repeat(32),
+                        }).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let appends = (threads * per_thread) as u64;
+        let flushes = wal.flush_count();
+        assert!(flushes < appends / 2, "expected batching, got {} flushes for {} appends", flushes, appends);
+
+        // Every entry is in the log, and each thread's entries kept their order
+        let entries = WAL::new(&path).unwrap().read_all().unwrap();
+        assert_eq!(entries.len() as u64, appends);
+        for t in 0..threads {
+            let keys: Vec<_> = entries
+                .iter()
+                .filter_map(|entry| entry.key())
+                .filter(|key| key.starts_with(&format!("t{}_", t)))
+                .collect();
+            let expected: Vec<_> = (0..per_thread).map(|i| format!("t{}_key{:04}", t, i)).collect();
+            assert_eq!(keys, expected);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_failed_batch_clears_once_the_log_is_truncated() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(WAL_FILE_NAME);
+        let wal = GroupCommitWAL::new(WAL::new(&path).unwrap());
+        let entry = |key: &str| WALEntry::Insert { key: key.to_string(), value: "v".to_string() };
+        wal.append(&entry("before")).unwrap();
+
+        // Point the writer at a device that is always full
+        let full = OpenOptions::new().write(true).open("/dev/full").unwrap();
+        wal.wal.lock().writer = BufWriter::new(full);
+        assert!(wal.append(&entry("lost")).is_err());
+        assert!(wal.append(&entry("refused")).is_err());
+
+        // Once the log is rewritten (as a flush does) appends go through again
+        wal.truncate().unwrap();
+        wal.append(&entry("after")).unwrap();
+        let keys: Vec<_> = wal.read_all().unwrap().iter().filter_map(|e| e.key().map(str::to_string)).collect();
+        assert_eq!(keys, vec!["after"]);
+    }
+}
@@ -31,6 +31,8 @@ pub struct CSVParser {
     has_headers: bool,
     key_column: usize,
     value_column: usize,
+    value_columns: Option<Vec<usize>>,
+    column_names: Option<(String, String)>, // Key and value columns by header name, overriding the indices
     schema: Option<CSVSchema>,
 }
 
@@ -41,6 +43,8 @@ impl CSVParser {
             has_headers: true,
             key_column,
             value_column,
+            value_columns: None,
+            column_names: None,
             schema: None,
         }
     }
@@ -50,6 +54,14 @@ impl CSVParser {
         self
     }
 
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    pub fn has_headers(&self) -> bool {
+        self.has_headers
+    }
+
     pub fn with_custom_delimiter(mut self, delimiter: char) -> Self {
         self.delimiter = delimiter as u8;
         self
@@ -112,15 +124,53 @@ impl CSVParser {
         self
     }
 
+    // Store several columns as one JSON object value, keyed by header name (or column_N without headers)
+    pub fn with_value_columns(mut self, value_columns: Vec<usize>) -> Self {
+        self.value_columns = Some(value_columns);
+        self
+    }
+
+    // Pick the key and value columns by name; they're looked up in the header row when parsing.
+    // Either one may be a plain number instead, which is taken as a column index
+    pub fn with_named_columns(mut self, key: &str, value: &str) -> Self {
+        self.column_names = Some((key.to_string(), value.to_string()));
+        self
+    }
+
+    // Key and value column indices for an input with the given header row
+    pub fn resolve_columns(&self, headers: Option<&StringRecord>) -> DbResult<(usize, usize)> {
+        let Some((key, value)) = &self.column_names else {
+            return Ok((self.key_column, self.value_column));
+        };
+        let position = |name: &str| {
+            if let Ok(index) = name.parse() {
+                return Ok(index);
+            }
+            let headers = headers.ok_or_else(|| {
+                DbError::InvalidOperation("Columns can only be given by name for CSV with a header row".to_string())
+            })?;
+            headers.iter().position(|header| header.trim() == name).ok_or_else(|| {
+                DbError::InvalidOperation(format!(
+                    "Column '{}' not found in CSV header ({})",
+                    name,
+                    headers.iter().collect::<Vec<_>>().join(", ")
+                ))
+            })
+        };
+        Ok((position(key)?, position(value)?))
+    }
+
     pub fn with_schema(mut self, schema: CSVSchema) -> Self {
         self.schema = Some(schema);
         self
     }
 
     pub fn detect_schema<R: Read>(&self, reader: R) -> DbResult<CSVSchema> {
+        // Flexible so a ragged row in the sample doesn't prevent inference; it is reported by the real parse
         let mut csv_reader = csv::ReaderBuilder::new()
             .delimiter(self.delimiter)
             .has_headers(self.has_headers)
+            .flexible(true)
             .from_reader(reader);
 
         let mut columns = Vec::new();
@@ -213,35 +263,73 @@ impl CSVParser {
             .has_headers(self.has_headers)
             .from_reader(reader);
         
+        let headers = if self.has_headers {
+            Some(csv_reader.headers().map_err(|e| {
+                DbError::InvalidOperation(format!("CSV parsing error: {}", e))
+            })?.clone())
+        } else {
+            None
+        };
+        let (key_column, value_column) = self.resolve_columns(headers.as_ref())?;
+
         let mut records = Vec::new();
         
         for result in csv_reader.records() {
             let record = result.map_err(|e| {
                 DbError::InvalidOperation(format!("CSV parsing error: {}", e))
             })?;
+
+            self.validate_record(&record)?;
             
-            let key = self.extract_key(&record)?;
-            let value = self.extract_value(&record)?;
+            let key = self.extract_key(&record, key_column)?;
+            let value = match &self.value_columns {
+                Some(columns) if columns.len() > 1 => self.extract_composite_value(&record, columns, headers.as_ref())?,
+                Some(columns) => self.extract_column(&record, columns.first().copied().unwrap_or(value_column))?,
+                None => self.extract_value(&record, value_column)?,
+            };
             
             records.push((key, value));
         }
         
-        println!("Parsed {} records from CSV", records.len());
+        log::debug!("Parsed {} records from CSV", records.len());
         Ok(records)
     }
 
-    fn extract_key(&self, record: &StringRecord) -> DbResult<String> {
-        record.get(self.key_column)
+    fn extract_key(&self, record: &StringRecord, key_column: usize) -> DbResult<String> {
+        record.get(key_column)
             .ok_or_else(|| DbError::InvalidOperation("Key column not found".to_string()))
             .map(|s| s.to_string())
     }
 
-    fn extract_value(&self, record: &StringRecord) -> DbResult<Value> {
-        record.get(self.value_column)
+    fn extract_value(&self, record: &StringRecord, value_column: usize) -> DbResult<Value> {
+        record.get(value_column)
             .ok_or_else(|| DbError::InvalidOperation("Value column not found".to_string()))
             .map(|s| Value::Data(s.to_string()))
     }
 
+    fn extract_column(&self, record: &StringRecord, column: usize) -> DbResult<Value> {
+        record.get(column)
+            .ok_or_else(|| DbError::InvalidOperation(format!("Value column {} not found", column)))
+            .map(|s| Value::Data(s.to_string()))
+    }
+
+    fn extract_composite_value(&self, record: &StringRecord, columns: &[usize], headers: Option<&StringRecord>) -> DbResult<Value> {
+        let mut object = serde_json::Map::new();
+
+        for &column in columns {
+            let field = record.get(column).ok_or_else(|| {
+                DbError::InvalidOperation(format!("Value column {} not found", column))
+            })?;
+            let name = headers
+                .and_then(|h| h.get(column))
+                .map(|h| h.to_string())
+                .unwrap_or_else(|| format!("column_{}", column));
+            object.insert(name, serde_json::Value::String(field.to_string()));
+        }
+
+        Ok(Value::Data(serde_json::Value::Object(object).to_string()))
+    }
+
     pub fn validate_record(&self, record: &StringRecord) -> DbResult<()> {
         if let Some(schema) = &self.schema {
             for (i, field) in record.iter().enumerate() {
@@ -1,12 +1,14 @@
-use crate::{DbResult, DbError, Value};
+use crate::{DbResult, DbError, Value, WriteOp};
 use crate::engine::LSMTree;
-use crate::etl::csv_parser::CSVParser;
+use crate::config::EtlConfig;
+use crate::etl::csv_parser::{CSVParser, CSVSchema};
 use rayon::prelude::*;
 use std::path::Path;
 use std::fs::File;
-use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
-use parking_lot::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(Debug, Clone)]
 pub struct ETLError {
@@ -21,6 +23,13 @@ pub struct ETLResult {
     pub errors: Vec<ETLError>,
 }
 
+// What an import_dump wrote
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportResult {
+    pub upserts: usize,
+    pub deletes: usize,
+}
+
 impl ETLResult {
     pub fn success_rate(&self) -> f64 {
         if self.total_rows == 0 {
@@ -32,32 +41,238 @@ impl ETLResult {
 
 pub struct ETLLoader {
     batch_size: usize,
-    parallel_threads: usize,
+    pool: Option<Arc<rayon::ThreadPool>>, // Runs the parallel stages; None falls back to rayon's global pool
     recovery_mode: bool,
+    auto_delimiter: bool,
+    schema_validation: bool,
+    delimiter: u8,
+    has_headers: bool,
+    progress: Option<ProgressCallback>,
+    key_transform: Option<Transform>,
+    value_transform: Option<Transform>,
+    duplicate_policy: DuplicatePolicy,
+    column_names: Option<(String, String)>,
+}
+
+// Called as a load goes with (rows processed so far, total rows to process)
+pub type ProgressCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+// What a load does with a key that already appeared earlier in the same load
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    #[default]
+    Overwrite, // Last row wins
+    Skip, // First row wins, later ones are dropped silently
+    Error, // First row wins, later ones are reported as errors
+}
+
+// Rewrites a key or value between parsing and insertion
+pub type Transform = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+// Running row count for one load
+struct Progress<'a> {
+    callback: Option<&'a ProgressCallback>,
+    done: AtomicUsize,
+    total: usize,
+}
+
+impl<'a> Progress<'a> {
+    fn new(callback: Option<&'a ProgressCallback>, total: usize) -> Self {
+        Self { callback, done: AtomicUsize::new(0), total }
+    }
+
+    fn advance(&self, rows: usize) {
+        if let Some(callback) = self.callback {
+            let done = self.done.fetch_add(rows, Ordering::SeqCst) + rows;
+            callback(done, self.total);
+        }
+    }
+}
+
+// Built once per loader so repeated loads reuse the same worker threads
+fn build_pool(threads: usize) -> Option<Arc<rayon::ThreadPool>> {
+    match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => Some(Arc::new(pool)),
+        Err(e) => {
+            log::warn!("Failed to build {}-thread pool, using the global pool: {}", threads, e);
+            None
+        }
+    }
 }
 
+// Number of bytes sampled from the start of a file for delimiter detection
+const DELIMITER_SAMPLE_BYTES: u64 = 8192;
+
 impl ETLLoader {
     pub fn new() -> Self {
         Self {
             batch_size: 1000,
-            parallel_threads: 4, // Default to 4 threads
+            pool: build_pool(4), // Default to 4 threads
             recovery_mode: false,
+            auto_delimiter: false,
+            schema_validation: false,
+            delimiter: b',',
+            has_headers: true,
+            progress: None,
+            key_transform: None,
+            value_transform: None,
+            duplicate_policy: DuplicatePolicy::Overwrite,
+            column_names: None,
         }
     }
 
     pub fn with_config(batch_size: usize, parallel_threads: usize) -> Self {
         Self {
             batch_size,
-            parallel_threads,
+            pool: build_pool(parallel_threads),
             recovery_mode: false,
+            auto_delimiter: false,
+            schema_validation: false,
+            delimiter: b',',
+            has_headers: true,
+            progress: None,
+            key_transform: None,
+            value_transform: None,
+            duplicate_policy: DuplicatePolicy::Overwrite,
+            column_names: None,
         }
     }
 
+    // Build a loader from the `etl` section of DatabaseConfig
+    pub fn from_config(config: &EtlConfig) -> Self {
+        Self::with_config(config.batch_size, config.parallel_threads)
+            .with_delimiter(config.delimiter as u8)
+            .with_headers(config.has_headers)
+    }
+
     pub fn with_recovery_mode(mut self, recovery_mode: bool) -> Self {
         self.recovery_mode = recovery_mode;
         self
     }
 
+    pub fn with_auto_delimiter(mut self, auto_delimiter: bool) -> Self {
+        self.auto_delimiter = auto_delimiter;
+        self
+    }
+
+    pub fn with_schema_validation(mut self, schema_validation: bool) -> Self {
+        self.schema_validation = schema_validation;
+        self
+    }
+
+    // Delimiter used when auto-detection is off
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    // Report progress after every batch of a CSV or JSON array load. With parallel threads the
+    // callback may run on any of them, but never on two at once, so counts only go up
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    // Rewrite every key before it is inserted, e.g. to trim or lowercase it. Keys that become
+    // equal after the transform overwrite each other like any duplicate key
+    pub fn with_key_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.key_transform = Some(Arc::new(transform));
+        self
+    }
+
+    // Rewrite every value before it is inserted
+    pub fn with_value_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.value_transform = Some(Arc::new(transform));
+        self
+    }
+
+    // Duplicates are judged on keys after the key transform, within one load call
+    pub fn with_duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = duplicate_policy;
+        self
+    }
+
+    // Transform records (in parallel) and then apply the duplicate policy in input order.
+    // `seen` carries keys across calls for loads that arrive in pieces
+    fn prepare_records(
+        &self,
+        records: Vec<(usize, String, Value)>,
+        seen: &mut HashSet<String>,
+    ) -> (Vec<(usize, String, Value)>, Vec<ETLError>) {
+        let records = if self.key_transform.is_some() || self.value_transform.is_some() {
+            let mut transformed = Vec::new();
+            self.run_parallel(|| {
+                transformed = records
+                    .into_par_iter()
+                    .map(|(row_number, key, value)| {
+                        let (key, value) = self.transform_record(&key, &value);
+                        (row_number, key, value)
+                    })
+                    .collect();
+            });
+            transformed
+        } else {
+            records
+        };
+
+        if self.duplicate_policy == DuplicatePolicy::Overwrite {
+            return (records, Vec::new());
+        }
+
+        let mut errors = Vec::new();
+        let kept = records
+            .into_iter()
+            .filter(|(row_number, key, _)| {
+                if seen.insert(key.clone()) {
+                    return true;
+                }
+                if self.duplicate_policy == DuplicatePolicy::Error {
+                    errors.push(ETLError {
+                        row_number: *row_number,
+                        error: format!("Duplicate key {}", key),
+                    });
+                }
+                false
+            })
+            .collect();
+        (kept, errors)
+    }
+
+    // Take the key and value from the named header columns; the column indices passed to the
+    // load methods are then ignored. A name that is a plain number is taken as an index
+    pub fn with_named_columns(mut self, key: &str, value: &str) -> Self {
+        self.column_names = Some((key.to_string(), value.to_string()));
+        self
+    }
+
+    // Apply the configured transforms to one parsed record
+    fn transform_record(&self, key: &str, value: &Value) -> (String, Value) {
+        let key = match self.key_transform {
+            Some(ref transform) => transform(key),
+            None => key.to_string(),
+        };
+        let value = match (value, &self.value_transform) {
+            (Value::Data(data), Some(transform)) => Value::Data(transform(data)),
+            (value, _) => value.clone(),
+        };
+        (key, value)
+    }
+
+    // Header setting used by `load_csv`
+    pub fn with_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
     pub fn load_csv<P: AsRef<Path>>(
         &self,
         file_path: P,
@@ -65,7 +280,7 @@ impl ETLLoader {
         key_column: usize,
         value_column: usize,
     ) -> DbResult<usize> {
-        self.load_csv_with_options(file_path, lsm_tree, key_column, value_column, true)
+        self.load_csv_with_options(file_path, lsm_tree, key_column, value_column, self.has_headers)
     }
 
     pub fn load_csv_with_options<P: AsRef<Path>>(
@@ -76,62 +291,116 @@ impl ETLLoader {
         value_column: usize,
         has_headers: bool,
     ) -> DbResult<usize> {
-        let file = File::open(file_path).map_err(|e| {
+        Ok(self.load_csv_detailed(file_path, lsm_tree, key_column, value_column, has_headers)?.successful_inserts)
+    }
+
+    // Load a CSV file and report the row totals and every error. In recovery mode bad rows are
+    // skipped and listed; otherwise the first bad row fails the whole load
+    pub fn load_csv_detailed<P: AsRef<Path>>(
+        &self,
+        file_path: P,
+        lsm_tree: &mut LSMTree,
+        key_column: usize,
+        value_column: usize,
+        has_headers: bool,
+    ) -> DbResult<ETLResult> {
+        let mut file = File::open(file_path).map_err(|e| {
             DbError::InvalidOperation(format!("Failed to open CSV file: {}", e))
         })?;
 
-        let parser = CSVParser::new(key_column, value_column)
-            .with_headers(has_headers);
-        let records = parser.parse_records(file)?;
+        let parser = self.prepare_parser(&mut file, key_column, value_column, has_headers)?;
+        self.load_parsed(file, &parser, lsm_tree)
+    }
 
-        println!("Loaded {} records from CSV, starting parallel insertion...", records.len());
-        
-        if records.is_empty() {
-            println!("No records to insert!");
-            return Ok(0);
+    // Load CSV from any reader, e.g. stdin or a network stream. Auto-detecting the delimiter
+    // and schema validation both sample the head of the input and then start over, so with
+    // either one on the whole input is read into memory first
+    pub fn load_reader<R: Read>(
+        &self,
+        mut reader: R,
+        lsm_tree: &mut LSMTree,
+        key_column: usize,
+        value_column: usize,
+        has_headers: bool,
+    ) -> DbResult<ETLResult> {
+        if self.auto_delimiter || self.schema_validation {
+            let mut buffer = Vec::new();
+            reader.read_to_end(&mut buffer).map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to read CSV input: {}", e))
+            })?;
+            let mut input = Cursor::new(buffer);
+            let parser = self.prepare_parser(&mut input, key_column, value_column, has_headers)?;
+            return self.load_parsed(input, &parser, lsm_tree);
         }
 
-        // Process records in parallel batches
-        let total_inserted = Arc::new(Mutex::new(0));
-        let lsm_tree = Arc::new(Mutex::new(lsm_tree));
+        let parser = self.csv_parser(key_column, value_column, has_headers, self.delimiter);
+        self.load_parsed(reader, &parser, lsm_tree)
+    }
 
-        records
-            .chunks(self.batch_size)
-            .enumerate()
-            .collect::<Vec<_>>()
-            .into_par_iter()
-            .for_each(|(batch_idx, chunk)| {
-                let mut batch_data = BTreeMap::new();
-
-                // Prepare batch
-                for (key, value) in chunk {
-                    batch_data.insert(key.clone(), value.clone());
-                }
+    // Parser for an input, with the delimiter and schema resolved from its head if configured.
+    // The input is left rewound to the start
+    fn prepare_parser<R: Read + Seek>(
+        &self,
+        input: &mut R,
+        key_column: usize,
+        value_column: usize,
+        has_headers: bool,
+    ) -> DbResult<CSVParser> {
+        let delimiter = self.resolve_delimiter(input)?;
+        let mut parser = self.csv_parser(key_column, value_column, has_headers, delimiter);
+        if let Some(schema) = self.resolve_schema(input, delimiter, has_headers)? {
+            parser = parser.with_schema(schema);
+        }
+        Ok(parser)
+    }
 
-                // Insert batch into LSM tree
-                let mut lsm = lsm_tree.lock();
-                let mut inserted_count = 0;
+    fn csv_parser(&self, key_column: usize, value_column: usize, has_headers: bool, delimiter: u8) -> CSVParser {
+        let parser = CSVParser::new(key_column, value_column)
+            .with_headers(has_headers)
+            .with_delimiter(delimiter);
+        match &self.column_names {
+            Some((key, value)) => parser.with_named_columns(key, value),
+            None => parser,
+        }
+    }
 
-                for (key, value) in batch_data {
-                    if let Value::Data(data) = value {
-                        match lsm.insert(key, data) {
-                            Ok(_) => inserted_count += 1,
-                            Err(e) => eprintln!("Error inserting records: {}", e),
-                        }
-                    }
-                }
+    // Parse and insert everything from the input: in recovery mode bad rows are skipped and
+    // reported, otherwise the first one fails the load
+    fn load_parsed<R: Read>(
+        &self,
+        input: R,
+        parser: &CSVParser,
+        lsm_tree: &mut LSMTree,
+    ) -> DbResult<ETLResult> {
+        if self.recovery_mode {
+            return self.load_parsed_with_recovery(input, parser, lsm_tree);
+        }
 
-                // Update total count
-                let mut total = total_inserted.lock();
-                *total += inserted_count;
+        let records = parser.parse_records(input)?;
 
-                println!("Batch {} completed: {} records inserted", batch_idx + 1, inserted_count);
-            });
+        log::info!("Loaded {} records from CSV, starting parallel insertion...", records.len());
+        
+        let total_rows = records.len();
+        if records.is_empty() {
+            log::info!("No records to insert!");
+            return Ok(ETLResult { total_rows, successful_inserts: 0, errors: Vec::new() });
+        }
 
-        let final_count = *total_inserted.lock();
-        println!("ETL load complete: {} records inserted into LSM tree", final_count);
+        // Row numbers count lines as the recovery path does, header included
+        let first_row = if parser.has_headers() { 2 } else { 1 };
+        let records = records
+            .into_iter()
+            .enumerate()
+            .map(|(index, (key, value))| (first_row + index, key, value))
+            .collect();
+        let (final_count, errors) = self.insert_batches(records, &mut HashSet::new(), lsm_tree, self.progress.as_ref());
+        log::info!("ETL load complete: {} records inserted into LSM tree", final_count);
 
-        Ok(final_count)
+        Ok(ETLResult {
+            total_rows,
+            successful_inserts: final_count,
+            errors,
+        })
     }
 
     pub fn load_csv_with_recovery<P: AsRef<Path>>(
@@ -142,18 +411,37 @@ impl ETLLoader {
         value_column: usize,
         has_headers: bool,
     ) -> DbResult<ETLResult> {
-        let file = File::open(file_path).map_err(|e| {
+        let mut file = File::open(file_path).map_err(|e| {
             DbError::InvalidOperation(format!("Failed to open CSV file: {}", e))
         })?;
 
-        let _parser = CSVParser::new(key_column, value_column)
-            .with_headers(has_headers);
-        
+        let parser = self.prepare_parser(&mut file, key_column, value_column, has_headers)?;
+        self.load_parsed_with_recovery(file, &parser, lsm_tree)
+    }
+
+    fn load_parsed_with_recovery<R: Read>(
+        &self,
+        input: R,
+        parser: &CSVParser,
+        lsm_tree: &mut LSMTree,
+    ) -> DbResult<ETLResult> {
+        let has_headers = parser.has_headers();
+
         // Use CSV reader directly for error recovery
         let mut csv_reader = csv::ReaderBuilder::new()
-            .delimiter(b',')
+            .delimiter(parser.delimiter())
             .has_headers(has_headers)
-            .from_reader(file);
+            .from_reader(input);
+
+        // A header row that can't be read is a problem with the whole file, not one row
+        let headers = if has_headers {
+            Some(csv_reader.headers().map_err(|e| {
+                DbError::InvalidOperation(format!("CSV parsing error: {}", e))
+            })?.clone())
+        } else {
+            None
+        };
+        let (key_column, value_column) = parser.resolve_columns(headers.as_ref())?;
 
         let mut successful_records = Vec::new();
         let mut errors = Vec::new();
@@ -165,10 +453,19 @@ impl ETLLoader {
             
             match result {
                 Ok(record) => {
+                    // Reject rows whose fields don't match the detected column types
+                    if let Err(e) = parser.validate_record(&record) {
+                        errors.push(ETLError {
+                            row_number,
+                            error: format!("Schema validation failed: {}", e),
+                        });
+                        continue;
+                    }
+
                     // Try to extract key and value
                     match self.extract_key_value(&record, key_column, value_column) {
                         Ok((key, value)) => {
-                            successful_records.push((key, value));
+                            successful_records.push((row_number, key, value));
                         }
                         Err(e) => {
                             errors.push(ETLError {
@@ -188,7 +485,7 @@ impl ETLLoader {
         }
 
         let total_rows = successful_records.len() + errors.len();
-        println!("Parsed {} successful records, {} errors from CSV", successful_records.len(), errors.len());
+        log::info!("Parsed {} successful records, {} errors from CSV", successful_records.len(), errors.len());
 
         if successful_records.is_empty() {
             return Ok(ETLResult {
@@ -199,123 +496,515 @@ impl ETLLoader {
         }
 
         // Process successful records in parallel
-        let total_inserted = Arc::new(Mutex::new(0));
-        let lsm_tree = Arc::new(Mutex::new(lsm_tree));
-        let insertion_errors = Arc::new(Mutex::new(Vec::new()));
+        let (final_count, insertion_errors) =
+            self.insert_batches(successful_records, &mut HashSet::new(), lsm_tree, self.progress.as_ref());
 
-        successful_records
-            .chunks(self.batch_size)
-            .enumerate()
-            .collect::<Vec<_>>()
-            .into_par_iter()
-            .for_each(|(batch_idx, chunk)| {
-                let mut batch_data = BTreeMap::new();
+        let mut final_errors = errors;
+        final_errors.extend(insertion_errors);
 
-                for (key, value) in chunk {
-                    batch_data.insert(key.clone(), value.clone());
-                }
+        log::info!("ETL load with recovery complete: {} records inserted, {} errors", final_count, final_errors.len());
 
-                let mut lsm = lsm_tree.lock();
-                let mut inserted_count = 0;
-
-                for (key, value) in batch_data {
-                    if let Value::Data(data) = value {
-                        match lsm.insert(key.clone(), data) {
-                            Ok(_) => inserted_count += 1,
-                            Err(e) => {
-                                let mut errors = insertion_errors.lock();
-                                errors.push(ETLError {
-                                    row_number: 0, // We don't track individual row numbers in batches
-                                    error: format!("Failed to insert {}: {}", key, e),
-                                });
-                            }
-                        }
-                    }
-                }
+        Ok(ETLResult {
+            total_rows,
+            successful_inserts: final_count,
+            errors: final_errors,
+        })
+    }
 
-                let mut total = total_inserted.lock();
-                *total += inserted_count;
+    // Load a JSON array of objects, mapping obj[key_field] -> obj[value_field]
+    pub fn load_json<P: AsRef<Path>>(
+        &self,
+        file_path: P,
+        lsm_tree: &mut LSMTree,
+        key_field: &str,
+        value_field: &str,
+    ) -> DbResult<ETLResult> {
+        let file = File::open(file_path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to open JSON file: {}", e))
+        })?;
 
-                println!("Batch {} completed: {} records inserted", batch_idx + 1, inserted_count);
-            });
+        let document: serde_json::Value = serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            DbError::InvalidOperation(format!("JSON parsing error: {}", e))
+        })?;
 
-        let final_count = *total_inserted.lock();
-        let mut final_errors = errors;
-        final_errors.extend(insertion_errors.lock().clone());
+        let elements = match document {
+            serde_json::Value::Array(elements) => elements,
+            _ => return Err(DbError::InvalidOperation(
+                "JSON loader expects a top-level array of objects".to_string()
+            )),
+        };
+
+        let mut successful_records = Vec::new();
+        let mut errors = Vec::new();
+
+        for (index, element) in elements.iter().enumerate() {
+            match self.extract_json_key_value(element, key_field, value_field) {
+                Ok((key, value)) => successful_records.push((index + 1, key, value)),
+                Err(e) => errors.push(ETLError {
+                    row_number: index + 1,
+                    error: format!("Failed to extract key/value: {}", e),
+                }),
+            }
+        }
+
+        let total_rows = elements.len();
+        log::info!("Parsed {} successful records, {} errors from JSON", successful_records.len(), errors.len());
 
-        println!("ETL load with recovery complete: {} records inserted, {} errors", final_count, final_errors.len());
+        let (final_count, insertion_errors) =
+            self.insert_batches(successful_records, &mut HashSet::new(), lsm_tree, self.progress.as_ref());
+        errors.extend(insertion_errors);
+
+        log::info!("JSON load complete: {} records inserted, {} errors", final_count, errors.len());
 
         Ok(ETLResult {
             total_rows,
             successful_inserts: final_count,
-            errors: final_errors,
+            errors,
         })
     }
 
-    fn extract_key_value(&self, record: &csv::StringRecord, key_column: usize, value_column: usize) -> DbResult<(String, Value)> {
-        let key = record.get(key_column).ok_or_else(|| {
-            DbError::InvalidOperation(format!("Key column {} not found in record", key_column))
+    // Stream newline-delimited JSON, inserting every batch_size records so the whole file is never held in memory
+    pub fn load_jsonl<P: AsRef<Path>>(
+        &self,
+        file_path: P,
+        lsm_tree: &mut LSMTree,
+        key_field: &str,
+        value_field: &str,
+    ) -> DbResult<ETLResult> {
+        let file = File::open(file_path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to open JSONL file: {}", e))
         })?;
 
-        let value = record.get(value_column).ok_or_else(|| {
-            DbError::InvalidOperation(format!("Value column {} not found in record", value_column))
+        let reader = BufReader::new(file);
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut errors = Vec::new();
+        let mut total_rows = 0;
+        let mut total_inserted = 0;
+        let mut seen = HashSet::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to read JSONL line {}: {}", line_number, e))
+            })?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+            total_rows += 1;
+
+            let parsed = serde_json::from_str::<serde_json::Value>(&line)
+                .map_err(|e| DbError::InvalidOperation(format!("JSON parsing error: {}", e)))
+                .and_then(|element| self.extract_json_key_value(&element, key_field, value_field));
+
+            match parsed {
+                Ok((key, value)) => batch.push((line_number, key, value)),
+                Err(e) if self.recovery_mode => errors.push(ETLError {
+                    row_number: line_number,
+                    error: e.to_string(),
+                }),
+                Err(e) => {
+                    return Err(DbError::InvalidOperation(format!("Line {}: {}", line_number, e)));
+                }
+            }
+
+            if batch.len() >= self.batch_size {
+                let (inserted, insertion_errors) = self.insert_batches(std::mem::take(&mut batch), &mut seen, lsm_tree, None);
+                total_inserted += inserted;
+                errors.extend(insertion_errors);
+            }
+        }
+
+        let (inserted, insertion_errors) = self.insert_batches(batch, &mut seen, lsm_tree, None);
+        total_inserted += inserted;
+        errors.extend(insertion_errors);
+
+        log::info!("JSONL load complete: {} records inserted, {} errors", total_inserted, errors.len());
+
+        Ok(ETLResult {
+            total_rows,
+            successful_inserts: total_inserted,
+            errors,
+        })
+    }
+
+    // Write every live key-value pair to a CSV file, returning the number of rows written
+    pub fn export_csv<P: AsRef<Path>>(
+        &self,
+        lsm_tree: &LSMTree,
+        file_path: P,
+        has_headers: bool,
+    ) -> DbResult<usize> {
+        let mut writer = csv::Writer::from_path(file_path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to create CSV file: {}", e))
         })?;
 
-        Ok((key.to_string(), Value::Data(value.to_string())))
+        if has_headers {
+            writer.write_record(["key", "value"]).map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to write CSV header: {}", e))
+            })?;
+        }
+
+        let mut exported = 0;
+        for (key, value) in lsm_tree.iter()? {
+            writer.write_record([&key, &value]).map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to write CSV record: {}", e))
+            })?;
+            exported += 1;
+        }
+
+        writer.flush().map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to flush CSV file: {}", e))
+        })?;
+
+        log::info!("Exported {} records to CSV", exported);
+        Ok(exported)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
-    use std::io::Write;
-    use crate::engine::{LSMTree, LSMConfig};
+    // Like export_csv, but keys whose newest entry is a deletion are written too, so import_dump
+    // can re-apply them. A .jsonl path gets one object per line, {"key":..,"value":..} or
+    // {"key":..,"deleted":true}; anything else gets CSV under a key,value,deleted header.
+    // Returns the number of entries written
+    pub fn export_dump<P: AsRef<Path>>(&self, lsm_tree: &LSMTree, file_path: P) -> DbResult<usize> {
+        let entries = lsm_tree.dump_entries()?;
+        let write_error = |e: &dyn std::fmt::Display| DbError::InvalidOperation(format!("Failed to write dump: {}", e));
 
-    #[test]
-    fn test_csv_loading() {
-        let temp_dir = tempdir().unwrap();
-        
-        // Create test CSV file
-        let csv_path = temp_dir.path().join("test.csv");
-        let mut file = File::create(&csv_path).unwrap();
-        writeln!(file, "key,value").unwrap();
-        writeln!(file, "key1,value1").unwrap();
-        writeln!(file, "key2,value2").unwrap();
-        writeln!(file, "key3,value3").unwrap();
-        
-        // Setup LSM tree
-        let config = LSMConfig {
-            memtable_size_limit: 100,
-            data_dir: temp_dir.path().join("db"),
-            background_compaction: false,
-            background_compaction_interval: std::time::Duration::from_secs(1),
-            enable_wal: false,
+        if Self::is_jsonl(file_path.as_ref()) {
+            let file = File::create(&file_path).map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to create dump file: {}", e))
+            })?;
+            let mut writer = BufWriter::new(file);
+            for (key, value) in &entries {
+                let line = match value {
+                    Some(value) => serde_json::json!({ "key": key, "value": value }),
+                    None => serde_json::json!({ "key": key, "deleted": true }),
+                };
+                writeln!(writer, "{}", line).map_err(|e| write_error(&e))?;
+            }
+            writer.flush().map_err(|e| write_error(&e))?;
+        } else {
+            let mut writer = csv::Writer::from_path(&file_path).map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to create dump file: {}", e))
+            })?;
+            writer.write_record(["key", "value", "deleted"]).map_err(|e| write_error(&e))?;
+            for (key, value) in &entries {
+                let record = match value {
+                    Some(value) => [key.as_str(), value.as_str(), ""],
+                    None => [key.as_str(), "", "true"],
+                };
+                writer.write_record(record).map_err(|e| write_error(&e))?;
+            }
+            writer.flush().map_err(|e| write_error(&e))?;
+        }
+
+        log::info!("Exported {} entries to dump", entries.len());
+        Ok(entries.len())
+    }
+
+    // Replay a dump written by export_dump, or a CSV from export_csv with its header row:
+    // live entries are written and deleted ones are deleted again, so the keys in the dump end
+    // up exactly as they were. Writes are applied batch_size at a time, each batch atomically
+    pub fn import_dump<P: AsRef<Path>>(&self, file_path: P, lsm_tree: &mut LSMTree) -> DbResult<ImportResult> {
+        let ops = if Self::is_jsonl(file_path.as_ref()) {
+            Self::read_jsonl_dump(file_path.as_ref())?
+        } else {
+            Self::read_csv_dump(file_path.as_ref())?
         };
-        
-        let mut lsm_tree = LSMTree::with_config(config).unwrap();
-        
-        // Load CSV
-        let loader = ETLLoader::new();
-        let count = loader.load_csv(&csv_path, &mut lsm_tree, 0, 1).unwrap();
-        
-        assert_eq!(count, 3);
-        
-        // Verify data
-        assert_eq!(lsm_tree.get("key1").unwrap(), Some("value1".to_string()));
-        assert_eq!(lsm_tree.get("key2").unwrap(), Some("value2".to_string()));
-        assert_eq!(lsm_tree.get("key3").unwrap(), Some("value3".to_string()));
+
+        let mut result = ImportResult::default();
+        for op in &ops {
+            match op {
+                WriteOp::Put(..) => result.upserts += 1,
+                WriteOp::Delete(_) => result.deletes += 1,
+            }
+        }
+        for batch in ops.chunks(self.batch_size.max(1)) {
+            lsm_tree.transaction(batch.to_vec())?;
+        }
+
+        log::info!("Imported dump: {} upserts, {} deletes", result.upserts, result.deletes);
+        Ok(result)
     }
 
-    #[test]
-    fn test_csv_loading_no_headers() {
-        let temp_dir = tempdir().unwrap();
-        
-        // Create test CSV file without headers
-        let csv_path = temp_dir.path().join("test_no_headers.csv");
-        let mut file = File::create(&csv_path).unwrap();
-        writeln!(file, "user1,data1").unwrap();
-        writeln!(file, "user2,data2").unwrap();
+    fn is_jsonl(path: &Path) -> bool {
+        path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("jsonl"))
+    }
+
+    fn read_jsonl_dump(path: &Path) -> DbResult<Vec<WriteOp>> {
+        let file = File::open(path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to open dump file: {}", e))
+        })?;
+
+        let mut ops = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to read dump line {}: {}", line_number, e))
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: serde_json::Value = serde_json::from_str(&line).map_err(|e| {
+                DbError::InvalidOperation(format!("Line {}: JSON parsing error: {}", line_number, e))
+            })?;
+            let key = entry.get("key").and_then(|key| key.as_str()).ok_or_else(|| {
+                DbError::InvalidOperation(format!("Line {}: dump entry has no string 'key'", line_number))
+            })?;
+
+            if entry.get("deleted").and_then(|deleted| deleted.as_bool()) == Some(true) {
+                ops.push(WriteOp::Delete(key.to_string()));
+                continue;
+            }
+            let value = match entry.get("value") {
+                Some(serde_json::Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+                None => {
+                    return Err(DbError::InvalidOperation(format!(
+                        "Line {}: dump entry for '{}' has neither a value nor a deleted marker", line_number, key
+                    )));
+                }
+            };
+            ops.push(WriteOp::Put(key.to_string(), value));
+        }
+        Ok(ops)
+    }
+
+    fn read_csv_dump(path: &Path) -> DbResult<Vec<WriteOp>> {
+        let mut reader = csv::ReaderBuilder::new().has_headers(true).from_path(path).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to open dump file: {}", e))
+        })?;
+
+        let headers = reader.headers().map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to read dump header: {}", e))
+        })?;
+        let column = |name: &str| headers.iter().position(|header| header == name);
+        let (Some(key_column), Some(value_column)) = (column("key"), column("value")) else {
+            return Err(DbError::InvalidOperation(format!(
+                "Dump header must name key and value columns ({})",
+                headers.iter().collect::<Vec<_>>().join(", ")
+            )));
+        };
+        let deleted_column = column("deleted");
+
+        let mut ops = Vec::new();
+        for (index, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| {
+                DbError::InvalidOperation(format!("Row {}: {}", index + 1, e))
+            })?;
+            let field = |column: usize| record.get(column).unwrap_or_default();
+
+            let key = field(key_column).to_string();
+            let deleted = deleted_column.is_some_and(|column| field(column).eq_ignore_ascii_case("true"));
+            ops.push(if deleted {
+                WriteOp::Delete(key)
+            } else {
+                WriteOp::Put(key, field(value_column).to_string())
+            });
+        }
+        Ok(ops)
+    }
+
+    // Insert (row_number, key, value) records in batches, returning the inserted count and any
+    // errors tagged with the source rows they came from
+    fn insert_batches(
+        &self,
+        records: Vec<(usize, String, Value)>,
+        seen: &mut HashSet<String>,
+        lsm_tree: &mut LSMTree,
+        progress: Option<&ProgressCallback>,
+    ) -> (usize, Vec<ETLError>) {
+        let (records, mut errors) = self.prepare_records(records, seen);
+
+        // Build each batch in parallel, then apply them in file order with one insert_batch
+        // apiece: the tree is never shared between threads, each batch costs a single WAL
+        // write, and a key repeated across batches keeps its last value from the file
+        let mut batches = Vec::new();
+        self.run_parallel(|| {
+            batches = records
+                .par_chunks(self.batch_size)
+                .map(|chunk| {
+                    // Later rows win for duplicate keys within the batch
+                    let batch_data: BTreeMap<&String, &Value> = chunk.iter().map(|(_, key, value)| (key, value)).collect();
+                    let pairs = batch_data
+                        .into_iter()
+                        .filter_map(|(key, value)| value.as_data().map(|data| (key.clone(), data.clone())))
+                        .collect::<Vec<_>>();
+                    let first = chunk.first().map_or(0, |(row_number, _, _)| *row_number);
+                    let last = chunk.last().map_or(0, |(row_number, _, _)| *row_number);
+                    (chunk.len(), first..=last, pairs)
+                })
+                .collect();
+        });
+        let progress = Progress::new(progress, records.len());
+
+        let mut final_count = 0;
+        for (batch_idx, (rows, row_numbers, batch)) in batches.into_iter().enumerate() {
+            let result = lsm_tree.insert_batch(batch);
+            progress.advance(rows);
+            match result {
+                Ok(inserted_count) => {
+                    final_count += inserted_count;
+                    log::debug!("Batch {} completed: {} records inserted", batch_idx + 1, inserted_count);
+                }
+                Err(e) => {
+                    log::error!("Error inserting batch {}: {}", batch_idx + 1, e);
+                    errors.push(ETLError {
+                        row_number: *row_numbers.start(),
+                        error: format!("Failed to insert rows {}-{}: {}", row_numbers.start(), row_numbers.end(), e),
+                    });
+                }
+            }
+        }
+        (final_count, errors)
+    }
+
+    // Run parallel work on the loader's pool, or rayon's global pool if it couldn't be built
+    fn run_parallel<F: FnOnce() + Send>(&self, work: F) {
+        match self.pool {
+            Some(ref pool) => pool.install(work),
+            None => work(),
+        }
+    }
+
+    // Pick the delimiter for a CSV file, sampling its head when auto-detection is on.
+    // The file is rewound afterwards so the real parse starts from the beginning.
+    fn resolve_delimiter<R: Read + Seek>(&self, file: &mut R) -> DbResult<u8> {
+        if !self.auto_delimiter {
+            return Ok(self.delimiter);
+        }
+
+        let mut sample = Vec::new();
+        file.by_ref().take(DELIMITER_SAMPLE_BYTES).read_to_end(&mut sample).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to read CSV sample: {}", e))
+        })?;
+
+        file.seek(SeekFrom::Start(0)).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to rewind CSV file: {}", e))
+        })?;
+
+        // Only consider complete lines so a partial last line (or split UTF-8 char) doesn't skew detection
+        if sample.len() as u64 == DELIMITER_SAMPLE_BYTES
+            && let Some(last_newline) = sample.iter().rposition(|&b| b == b'\n')
+        {
+            sample.truncate(last_newline);
+        }
+
+        let sample = String::from_utf8_lossy(&sample);
+        let delimiter = CSVParser::new(0, 1).detect_delimiter(sample.as_bytes())?;
+        log::info!("Auto-detected CSV delimiter: '{}'", delimiter as char);
+        Ok(delimiter)
+    }
+
+    // Infer column types from the head of the file when schema validation is on, then rewind
+    fn resolve_schema<R: Read + Seek>(&self, file: &mut R, delimiter: u8, has_headers: bool) -> DbResult<Option<CSVSchema>> {
+        if !self.schema_validation {
+            return Ok(None);
+        }
+
+        let schema = CSVParser::new(0, 1)
+            .with_delimiter(delimiter)
+            .with_headers(has_headers)
+            .detect_schema(file.by_ref())?;
+
+        file.seek(SeekFrom::Start(0)).map_err(|e| {
+            DbError::InvalidOperation(format!("Failed to rewind CSV file: {}", e))
+        })?;
+
+        Ok(Some(schema))
+    }
+
+    fn extract_json_key_value(&self, element: &serde_json::Value, key_field: &str, value_field: &str) -> DbResult<(String, Value)> {
+        let object = element.as_object().ok_or_else(|| {
+            DbError::InvalidOperation("JSON element is not an object".to_string())
+        })?;
+
+        let key = match object.get(key_field) {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(serde_json::Value::Null) | None => {
+                return Err(DbError::InvalidOperation(format!("Key field '{}' not found in record", key_field)));
+            }
+            Some(other) => other.to_string(),
+        };
+
+        // Strings are stored as-is; objects, arrays, numbers and booleans keep their JSON encoding
+        let value = match object.get(value_field) {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => {
+                return Err(DbError::InvalidOperation(format!("Value field '{}' not found in record", value_field)));
+            }
+        };
+
+        Ok((key, Value::Data(value)))
+    }
+
+    fn extract_key_value(&self, record: &csv::StringRecord, key_column: usize, value_column: usize) -> DbResult<(String, Value)> {
+        let key = record.get(key_column).ok_or_else(|| {
+            DbError::InvalidOperation(format!("Key column {} not found in record", key_column))
+        })?;
+
+        let value = record.get(value_column).ok_or_else(|| {
+            DbError::InvalidOperation(format!("Value column {} not found in record", value_column))
+        })?;
+
+        Ok((key.to_string(), Value::Data(value.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use std::io::Write;
+    use crate::engine::{LSMTree, LSMConfig};
+    use parking_lot::Mutex;
+
+    #[test]
+    fn test_csv_loading() {
+        let temp_dir = tempdir().unwrap();
+        
+        // Create test CSV file
+        let csv_path = temp_dir.path().join("test.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "key,value").unwrap();
+        writeln!(file, "key1,value1").unwrap();
+        writeln!(file, "key2,value2").unwrap();
+        writeln!(file, "key3,value3").unwrap();
+        
+        // Setup LSM tree
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+        
+        // Load CSV
+        let loader = ETLLoader::new();
+        let count = loader.load_csv(&csv_path, &mut lsm_tree, 0, 1).unwrap();
+        
+        assert_eq!(count, 3);
+        
+        // Verify data
+        assert_eq!(lsm_tree.get("key1").unwrap(), Some("value1".to_string()));
+        assert_eq!(lsm_tree.get("key2").unwrap(), Some("value2".to_string()));
+        assert_eq!(lsm_tree.get("key3").unwrap(), Some("value3".to_string()));
+    }
+
+    #[test]
+    fn test_csv_loading_no_headers() {
+        let temp_dir = tempdir().unwrap();
+        
+        // Create test CSV file without headers
+        let csv_path = temp_dir.path().join("test_no_headers.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "user1,data1").unwrap();
+        writeln!(file, "user2,data2").unwrap();
         writeln!(file, "user3,data3").unwrap();
         
         // Setup LSM tree
@@ -325,6 +1014,7 @@ mod tests {
             background_compaction: false,
             background_compaction_interval: std::time::Duration::from_secs(1),
             enable_wal: false,
+            ..LSMConfig::default()
         };
         
         let mut lsm_tree = LSMTree::with_config(config).unwrap();
@@ -360,6 +1050,7 @@ mod tests {
             background_compaction: false,
             background_compaction_interval: std::time::Duration::from_secs(1),
             enable_wal: false,
+            ..LSMConfig::default()
         };
         
         let mut lsm_tree = LSMTree::with_config(config).unwrap();
@@ -392,6 +1083,7 @@ mod tests {
             background_compaction: false,
             background_compaction_interval: std::time::Duration::from_secs(1),
             enable_wal: false,
+            ..LSMConfig::default()
         };
         
         let mut lsm_tree = LSMTree::with_config(config).unwrap();
@@ -423,6 +1115,7 @@ mod tests {
             background_compaction: false,
             background_compaction_interval: std::time::Duration::from_secs(1),
             enable_wal: false,
+            ..LSMConfig::default()
         };
         
         let mut lsm_tree = LSMTree::with_config(config).unwrap();
@@ -463,6 +1156,7 @@ mod tests {
             background_compaction: false,
             background_compaction_interval: std::time::Duration::from_secs(1),
             enable_wal: false,
+            ..LSMConfig::default()
         };
         
         let _lsm_tree = LSMTree::with_config(config).unwrap();
@@ -478,4 +1172,700 @@ mod tests {
         assert_eq!(records[0].0, "Alice");
         assert_eq!(records[1].0, "Bob");
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_json_loading() {
+        let temp_dir = tempdir().unwrap();
+
+        let json_path = temp_dir.path().join("users.json");
+        let mut file = File::create(&json_path).unwrap();
+        writeln!(file, r#"[
+            {{"id": "user1", "profile": "Alice"}},
+            {{"id": "user2", "profile": {{"name": "Bob", "age": 30}}}},
+            {{"id": 3, "profile": 42}}
+        ]"#).unwrap();
+
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let loader = ETLLoader::new();
+        let result = loader.load_json(&json_path, &mut lsm_tree, "id", "profile").unwrap();
+
+        assert_eq!(result.total_rows, 3);
+        assert_eq!(result.successful_inserts, 3);
+        assert!(result.errors.is_empty());
+
+        assert_eq!(lsm_tree.get("user1").unwrap(), Some("Alice".to_string()));
+        assert_eq!(lsm_tree.get("3").unwrap(), Some("42".to_string()));
+
+        // Nested objects are stored as serialized JSON
+        let nested = lsm_tree.get("user2").unwrap().unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&nested).unwrap();
+        assert_eq!(parsed["name"], "Bob");
+        assert_eq!(parsed["age"], 30);
+    }
+
+    #[test]
+    fn test_last_row_wins_across_batches() {
+        let temp_dir = tempdir().unwrap();
+
+        // One row per batch, all writing the same key, with workers to spare
+        let json_path = temp_dir.path().join("dup.json");
+        let rows: Vec<String> = (0..256).map(|i| format!(r#"{{"id": "dup", "value": "v{}"}}"#, i)).collect();
+        std::fs::write(&json_path, format!("[{}]", rows.join(","))).unwrap();
+        let csv_path = temp_dir.path().join("dup.csv");
+        let rows: Vec<String> = (0..256).map(|i| format!("dup,v{}", i)).collect();
+        std::fs::write(&csv_path, format!("key,value\n{}\n", rows.join("\n"))).unwrap();
+
+        for round in 0..20 {
+            let config = LSMConfig {
+                memtable_size_limit: 1000,
+                data_dir: temp_dir.path().join(format!("db{}", round)),
+                background_compaction: false,
+                enable_wal: false,
+                ..LSMConfig::default()
+            };
+            let mut lsm_tree = LSMTree::with_config(config).unwrap();
+            let loader = ETLLoader::with_config(1, 8);
+
+            loader.load_json(&json_path, &mut lsm_tree, "id", "value").unwrap();
+            assert_eq!(lsm_tree.get("dup").unwrap(), Some("v255".to_string()), "JSON, round {}", round);
+
+            lsm_tree.insert("dup".to_string(), "stale".to_string()).unwrap();
+            let result = loader.with_recovery_mode(true).load_csv_detailed(&csv_path, &mut lsm_tree, 0, 1, true).unwrap();
+            assert!(result.errors.is_empty());
+            assert_eq!(lsm_tree.get("dup").unwrap(), Some("v255".to_string()), "CSV recovery, round {}", round);
+        }
+    }
+
+    #[test]
+    fn test_json_loading_missing_key_field() {
+        let temp_dir = tempdir().unwrap();
+
+        let json_path = temp_dir.path().join("users.json");
+        let mut file = File::create(&json_path).unwrap();
+        writeln!(file, r#"[
+            {{"id": "user1", "name": "Alice"}},
+            {{"name": "NoKey"}},
+            {{"id": "user3", "name": "Charlie"}}
+        ]"#).unwrap();
+
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let loader = ETLLoader::new();
+        let result = loader.load_json(&json_path, &mut lsm_tree, "id", "name").unwrap();
+
+        assert_eq!(result.total_rows, 3);
+        assert_eq!(result.successful_inserts, 2);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].row_number, 2);
+
+        assert_eq!(lsm_tree.get("user1").unwrap(), Some("Alice".to_string()));
+        assert_eq!(lsm_tree.get("user3").unwrap(), Some("Charlie".to_string()));
+    }
+    #[test]
+    fn test_jsonl_loading() {
+        let temp_dir = tempdir().unwrap();
+
+        let jsonl_path = temp_dir.path().join("events.jsonl");
+        let mut file = File::create(&jsonl_path).unwrap();
+        for i in 0..25 {
+            writeln!(file, r#"{{"id": "event{}", "payload": "data{}"}}"#, i, i).unwrap();
+        }
+
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        // Small batches so the file is inserted over several passes
+        let loader = ETLLoader::with_config(10, 2);
+        let result = loader.load_jsonl(&jsonl_path, &mut lsm_tree, "id", "payload").unwrap();
+
+        assert_eq!(result.total_rows, 25);
+        assert_eq!(result.successful_inserts, 25);
+        assert!(result.errors.is_empty());
+
+        for i in 0..25 {
+            assert_eq!(lsm_tree.get(&format!("event{}", i)).unwrap(), Some(format!("data{}", i)));
+        }
+    }
+
+    #[test]
+    fn test_jsonl_loading_malformed_line() {
+        let temp_dir = tempdir().unwrap();
+
+        let jsonl_path = temp_dir.path().join("events.jsonl");
+        let mut file = File::create(&jsonl_path).unwrap();
+        writeln!(file, r#"{{"id": "a", "payload": "1"}}"#).unwrap();
+        writeln!(file, r#"{{"id": "b", "payload": "2"}}"#).unwrap();
+        writeln!(file, r#"{{"id": "c", "payload": "#).unwrap(); // Truncated object
+        writeln!(file, r#"{{"id": "d", "payload": "4"}}"#).unwrap();
+
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        // Without recovery mode the load aborts
+        let strict_loader = ETLLoader::new();
+        assert!(strict_loader.load_jsonl(&jsonl_path, &mut lsm_tree, "id", "payload").is_err());
+
+        // With recovery mode the bad line is reported and the rest are loaded
+        let loader = ETLLoader::new().with_recovery_mode(true);
+        let result = loader.load_jsonl(&jsonl_path, &mut lsm_tree, "id", "payload").unwrap();
+
+        assert_eq!(result.total_rows, 4);
+        assert_eq!(result.successful_inserts, 3);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].row_number, 3);
+
+        assert_eq!(lsm_tree.get("a").unwrap(), Some("1".to_string()));
+        assert_eq!(lsm_tree.get("c").unwrap(), None);
+        assert_eq!(lsm_tree.get("d").unwrap(), Some("4".to_string()));
+    }
+    #[test]
+    fn test_auto_delimiter_detection() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("auto_semicolon.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "name;age;city").unwrap();
+        writeln!(file, "Alice;25;NYC").unwrap();
+        writeln!(file, "Bob;30;London").unwrap();
+
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let loader = ETLLoader::new().with_auto_delimiter(true);
+        let count = loader.load_csv(&csv_path, &mut lsm_tree, 0, 2).unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(lsm_tree.get("Alice").unwrap(), Some("NYC".to_string()));
+        assert_eq!(lsm_tree.get("Bob").unwrap(), Some("London".to_string()));
+    }
+    #[test]
+    fn test_schema_validation_rejects_mismatched_rows() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("scores.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "player,score").unwrap();
+        writeln!(file, "alice,10").unwrap();
+        writeln!(file, "bob,20").unwrap();
+        writeln!(file, "carol,not_a_number").unwrap();
+        writeln!(file, "dave,40").unwrap();
+
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let loader = ETLLoader::new()
+            .with_recovery_mode(true)
+            .with_schema_validation(true);
+        let result = loader.load_csv_with_recovery(&csv_path, &mut lsm_tree, 0, 1, true).unwrap();
+
+        assert_eq!(result.total_rows, 4);
+        assert_eq!(result.successful_inserts, 3);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].row_number, 4);
+
+        assert_eq!(lsm_tree.get("alice").unwrap(), Some("10".to_string()));
+        assert_eq!(lsm_tree.get("dave").unwrap(), Some("40".to_string()));
+        assert_eq!(lsm_tree.get("carol").unwrap(), None);
+    }
+
+    #[test]
+    fn test_composite_value_columns() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("users.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "id,name,email").unwrap();
+        writeln!(file, "1,Alice,alice@example.com").unwrap();
+        writeln!(file, "2,Bob,bob@example.com").unwrap();
+
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let parser = CSVParser::new(0, 1).with_value_columns(vec![1, 2]);
+        let records = parser.parse_records(File::open(&csv_path).unwrap()).unwrap();
+        assert_eq!(records.len(), 2);
+
+        for (key, value) in records {
+            if let Value::Data(data) = value {
+                lsm_tree.insert(key, data).unwrap();
+            }
+        }
+
+        let stored = lsm_tree.get("1").unwrap().unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&stored).unwrap();
+        assert_eq!(parsed["name"], "Alice");
+        assert_eq!(parsed["email"], "alice@example.com");
+
+        let stored = lsm_tree.get("2").unwrap().unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&stored).unwrap();
+        assert_eq!(parsed["name"], "Bob");
+    }
+
+    #[test]
+    fn test_insertion_errors_report_source_row() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("rows.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "key,value").unwrap();
+        writeln!(file, "k1,v1").unwrap();
+        writeln!(file, "k2,v2").unwrap();
+        writeln!(file, "k3,v3").unwrap();
+
+        let db_dir = temp_dir.path().join("db");
+        let config = LSMConfig {
+            memtable_size_limit: 2,
+            data_dir: db_dir.clone(),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        // Replace the data directory with a plain file so every flush fails
+        std::fs::remove_dir_all(&db_dir).unwrap();
+        File::create(&db_dir).unwrap();
+
+        // Insert errors are reported per batch, so one row per batch pins them to single rows
+        let loader = ETLLoader::with_config(1, 1).with_recovery_mode(true);
+        let result = loader.load_csv_with_recovery(&csv_path, &mut lsm_tree, 0, 1, true).unwrap();
+
+        // k2 fills the MemTable and triggers the first failed flush; k3 retries and fails again
+        let mut failed_rows: Vec<usize> = result.errors.iter().map(|e| e.row_number).collect();
+        failed_rows.sort();
+        assert_eq!(failed_rows, vec![3, 4]);
+        assert_eq!(result.successful_inserts, 1);
+    }
+
+    #[test]
+    fn test_load_csv_detailed_clean_file() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("clean.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "key,value").unwrap();
+        for i in 0..25 {
+            writeln!(file, "key{},value{}", i, i).unwrap();
+        }
+
+        let config = LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let loader = ETLLoader::with_config(10, 2);
+        let result = loader.load_csv_detailed(&csv_path, &mut lsm_tree, 0, 1, true).unwrap();
+        assert_eq!(result.total_rows, 25);
+        assert_eq!(result.successful_inserts, 25);
+        assert!(result.errors.is_empty());
+        assert_eq!(result.success_rate(), 1.0);
+        assert_eq!(lsm_tree.get("key24").unwrap(), Some("value24".to_string()));
+    }
+
+    #[test]
+    fn test_progress_callback_reaches_total() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("progress.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "key,value").unwrap();
+        for i in 0..95 {
+            writeln!(file, "key{},value{}", i, i).unwrap();
+        }
+
+        let config = LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        for recovery_mode in [false, true] {
+            let calls = Arc::new(Mutex::new(Vec::new()));
+            let recorded = calls.clone();
+            let loader = ETLLoader::with_config(10, 4)
+                .with_recovery_mode(recovery_mode)
+                .with_progress(move |done, total| recorded.lock().push((done, total)));
+            loader.load_csv(&csv_path, &mut lsm_tree, 0, 1).unwrap();
+
+            // One call per batch of 10, counting up to every row
+            let calls = calls.lock();
+            assert_eq!(calls.len(), 10);
+            assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert_eq!(calls.last(), Some(&(95, 95)));
+        }
+    }
+
+    #[test]
+    fn test_key_and_value_transforms() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("raw.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "key,value").unwrap();
+        writeln!(file, "  alice ,1").unwrap();
+        writeln!(file, "bob,2").unwrap();
+
+        let config = LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        for recovery_mode in [false, true] {
+            let loader = ETLLoader::new()
+                .with_recovery_mode(recovery_mode)
+                .with_key_transform(|key| key.trim().to_uppercase())
+                .with_value_transform(move |value| format!("{}-{}", value, recovery_mode));
+            assert_eq!(loader.load_csv(&csv_path, &mut lsm_tree, 0, 1).unwrap(), 2);
+
+            assert_eq!(lsm_tree.get("ALICE").unwrap(), Some(format!("1-{}", recovery_mode)));
+            assert_eq!(lsm_tree.get("BOB").unwrap(), Some(format!("2-{}", recovery_mode)));
+            assert_eq!(lsm_tree.get("bob").unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_load_reader_from_memory() {
+        let temp_dir = tempdir().unwrap();
+        let config = LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let input = std::io::Cursor::new(b"key,value\nk1,v1\nk2,v2\nk3,v3\n".to_vec());
+        let result = ETLLoader::new().load_reader(input, &mut lsm_tree, 0, 1, true).unwrap();
+        assert_eq!(result.successful_inserts, 3);
+        assert!(result.errors.is_empty());
+        assert_eq!(lsm_tree.get("k2").unwrap(), Some("v2".to_string()));
+
+        // Delimiter detection needs to look ahead, which works on a non-seekable reader too
+        let input = std::io::Cursor::new(b"key;value\nk4;v4\n".to_vec()).chain(&b"k5;v5\n"[..]);
+        let result = ETLLoader::new().with_auto_delimiter(true).load_reader(input, &mut lsm_tree, 0, 1, true).unwrap();
+        assert_eq!(result.successful_inserts, 2);
+        assert_eq!(lsm_tree.get("k5").unwrap(), Some("v5".to_string()));
+    }
+
+    #[test]
+    fn test_duplicate_policies() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("dupes.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "key,value").unwrap();
+        writeln!(file, "user1,first").unwrap();
+        writeln!(file, "user2,only").unwrap();
+        writeln!(file, "user1,second").unwrap();
+
+        let cases = [
+            (DuplicatePolicy::Overwrite, "second", 0),
+            (DuplicatePolicy::Skip, "first", 0),
+            (DuplicatePolicy::Error, "first", 1),
+        ];
+        for (index, (policy, expected, expected_errors)) in cases.into_iter().enumerate() {
+            for recovery_mode in [false, true] {
+                let config = LSMConfig {
+                    data_dir: temp_dir.path().join(format!("db{}-{}", index, recovery_mode)),
+                    background_compaction: false,
+                    enable_wal: false,
+                    ..LSMConfig::default()
+                };
+                let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+                let loader = ETLLoader::new().with_recovery_mode(recovery_mode).with_duplicate_policy(policy);
+                let result = loader.load_csv_detailed(&csv_path, &mut lsm_tree, 0, 1, true).unwrap();
+
+                assert_eq!(result.total_rows, 3);
+                assert_eq!(result.successful_inserts, 2, "{:?} recovery={}", policy, recovery_mode);
+                assert_eq!(result.errors.len(), expected_errors, "{:?} recovery={}", policy, recovery_mode);
+                if policy == DuplicatePolicy::Error {
+                    assert_eq!(result.errors[0].row_number, 4);
+                    assert!(result.errors[0].error.contains("user1"));
+                }
+                assert_eq!(lsm_tree.get("user1").unwrap(), Some(expected.to_string()));
+                assert_eq!(lsm_tree.get("user2").unwrap(), Some("only".to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_policy_spans_jsonl_batches() {
+        let temp_dir = tempdir().unwrap();
+
+        let jsonl_path = temp_dir.path().join("dupes.jsonl");
+        let mut file = File::create(&jsonl_path).unwrap();
+        for i in 0..3 {
+            writeln!(file, r#"{{"key": "user1", "value": "v{}"}}"#, i).unwrap();
+        }
+
+        let config = LSMConfig {
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let loader = ETLLoader::with_config(1, 1).with_duplicate_policy(DuplicatePolicy::Skip);
+        assert_eq!(loader.load_jsonl(&jsonl_path, &mut lsm_tree, "key", "value").unwrap().successful_inserts, 1);
+        assert_eq!(lsm_tree.get("user1").unwrap(), Some("v0".to_string()));
+    }
+
+    #[test]
+    fn test_named_columns() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("users.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        writeln!(file, "id,name,email").unwrap();
+        writeln!(file, "1,Alice,alice@example.com").unwrap();
+        writeln!(file, "2,Bob,bob@example.com").unwrap();
+
+        for recovery_mode in [false, true] {
+            let config = LSMConfig {
+                data_dir: temp_dir.path().join(format!("db-{}", recovery_mode)),
+                background_compaction: false,
+                enable_wal: false,
+                ..LSMConfig::default()
+            };
+            let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+            let loader = ETLLoader::new().with_recovery_mode(recovery_mode).with_named_columns("id", "email");
+            assert_eq!(loader.load_csv(&csv_path, &mut lsm_tree, 0, 1).unwrap(), 2);
+            assert_eq!(lsm_tree.get("1").unwrap(), Some("alice@example.com".to_string()));
+            assert_eq!(lsm_tree.get("2").unwrap(), Some("bob@example.com".to_string()));
+
+            // Either column can still be an index
+            let loader = ETLLoader::new().with_recovery_mode(recovery_mode).with_named_columns("name", "0");
+            assert_eq!(loader.load_csv(&csv_path, &mut lsm_tree, 0, 1).unwrap(), 2);
+            assert_eq!(lsm_tree.get("Bob").unwrap(), Some("2".to_string()));
+
+            let loader = ETLLoader::new().with_recovery_mode(recovery_mode).with_named_columns("id", "phone");
+            let error = loader.load_csv(&csv_path, &mut lsm_tree, 0, 1).unwrap_err().to_string();
+            assert!(error.contains("Column 'phone' not found"), "{}", error);
+
+            let loader = ETLLoader::new().with_recovery_mode(recovery_mode).with_named_columns("id", "email");
+            assert!(loader.load_csv_with_options(&csv_path, &mut lsm_tree, 0, 1, false).is_err());
+        }
+    }
+
+    #[test]
+    fn test_dump_round_trip_preserves_deletes() {
+        let temp_dir = tempdir().unwrap();
+        let tree = |name: &str| {
+            LSMTree::with_config(LSMConfig {
+                data_dir: temp_dir.path().join(name),
+                background_compaction: false,
+                enable_wal: false,
+                ..LSMConfig::default()
+            })
+            .unwrap()
+        };
+
+        let mut source = tree("source");
+        for i in 0..20 {
+            source.insert(format!("key{:02}", i), format!("value, \"{}\"", i)).unwrap();
+        }
+        source.flush().unwrap();
+        source.delete("key03").unwrap();
+        source.delete("key11").unwrap();
+        source.delete_range("key15", "key18").unwrap();
+
+        let loader = ETLLoader::with_config(4, 2);
+        for file_name in ["dump.csv", "dump.jsonl"] {
+            let dump_path = temp_dir.path().join(file_name);
+            assert_eq!(loader.export_dump(&source, &dump_path).unwrap(), 20);
+
+            // A fresh tree ends up with the same live set
+            let mut fresh = tree(&format!("fresh_{}", file_name));
+            let result = loader.import_dump(&dump_path, &mut fresh).unwrap();
+            assert_eq!(result, ImportResult { upserts: 15, deletes: 5 });
+            assert_eq!(fresh.iter().unwrap().collect::<Vec<_>>(), source.iter().unwrap().collect::<Vec<_>>());
+
+            // Unlike a plain load, the deletes are applied to a tree that still has those keys
+            let mut stale = tree(&format!("stale_{}", file_name));
+            for key in ["key03", "key11", "key16", "key99"] {
+                stale.insert(key.to_string(), "stale".to_string()).unwrap();
+            }
+            loader.import_dump(&dump_path, &mut stale).unwrap();
+            assert_eq!(stale.get("key03").unwrap(), None);
+            assert_eq!(stale.get("key16").unwrap(), None);
+            assert_eq!(stale.get("key99").unwrap(), Some("stale".to_string()));
+            assert_eq!(stale.get("key04").unwrap(), Some("value, \"4\"".to_string()));
+        }
+
+        // export_csv output has no deleted column and imports as all-live rows
+        let csv_path = temp_dir.path().join("plain.csv");
+        loader.export_csv(&source, &csv_path, true).unwrap();
+        let mut plain = tree("plain");
+        assert_eq!(loader.import_dump(&csv_path, &mut plain).unwrap(), ImportResult { upserts: 15, deletes: 0 });
+
+        loader.export_csv(&source, &csv_path, false).unwrap();
+        assert!(loader.import_dump(&csv_path, &mut plain).is_err());
+    }
+
+    #[test]
+    fn test_parallel_work_reuses_one_pool() {
+        use std::thread::ThreadId;
+
+        let loader = ETLLoader::with_config(10, 3);
+        let workers = Mutex::new(HashSet::<ThreadId>::new());
+        for _ in 0..3 {
+            loader.run_parallel(|| {
+                (0..48).into_par_iter().for_each(|_| {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                    workers.lock().insert(std::thread::current().id());
+                });
+            });
+        }
+
+        // A pool per call would bring fresh threads each time
+        let workers = workers.into_inner().len();
+        assert!((1..=3).contains(&workers), "{} worker threads across three calls", workers);
+    }
+
+    #[test]
+    fn test_export_csv_round_trip() {
+        let temp_dir = tempdir().unwrap();
+
+        let source_config = LSMConfig {
+            memtable_size_limit: 3,
+            data_dir: temp_dir.path().join("source"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        let mut source = LSMTree::with_config(source_config).unwrap();
+        for i in 0..10 {
+            source.insert(format!("key{:02}", i), format!("value, {}", i)).unwrap();
+        }
+        source.delete("key05").unwrap();
+
+        let export_path = temp_dir.path().join("export.csv");
+        let loader = ETLLoader::new();
+        let exported = loader.export_csv(&source, &export_path, true).unwrap();
+        assert_eq!(exported, 9);
+
+        let target_config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("target"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        let mut target = LSMTree::with_config(target_config).unwrap();
+        let loaded = loader.load_csv(&export_path, &mut target, 0, 1).unwrap();
+        assert_eq!(loaded, 9);
+
+        let source_entries: Vec<(String, String)> = source.iter().unwrap().collect();
+        let target_entries: Vec<(String, String)> = target.iter().unwrap().collect();
+        assert_eq!(source_entries, target_entries);
+        assert_eq!(target.get("key05").unwrap(), None);
+    }
+
+    #[test]
+    fn test_loader_from_config() {
+        let temp_dir = tempdir().unwrap();
+
+        let csv_path = temp_dir.path().join("pipes.csv");
+        let mut file = File::create(&csv_path).unwrap();
+        for i in 0..50 {
+            writeln!(file, "key{}|value{}", i, i).unwrap();
+        }
+
+        let etl_config = EtlConfig {
+            batch_size: 7,
+            parallel_threads: 2,
+            delimiter: '|',
+            has_headers: false,
+        };
+
+        let config = LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: std::time::Duration::from_secs(1),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+
+        let loader = ETLLoader::from_config(&etl_config);
+        let count = loader.load_csv(&csv_path, &mut lsm_tree, 0, 1).unwrap();
+
+        assert_eq!(count, 50);
+        assert_eq!(lsm_tree.get("key0").unwrap(), Some("value0".to_string()));
+        assert_eq!(lsm_tree.get("key49").unwrap(), Some("value49".to_string()));
+    }
+}
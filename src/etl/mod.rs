@@ -2,4 +2,4 @@ pub mod csv_parser;
 pub mod loader;
 
 pub use csv_parser::CSVParser;
-pub use loader::ETLLoader;
\ No newline at end of file
+pub use loader::{DuplicatePolicy, ETLLoader, ImportResult};
\ No newline at end of file
@@ -5,9 +5,12 @@ pub mod query;
 pub mod config;
 pub mod args;
 pub mod metrics;
+pub mod logging;
 
 use std::collections::BTreeMap;
+use std::ops::Bound;
 use serde::{Serialize, Deserialize};
+use engine::sstable::RangeTombstone;
 
 pub use config::DatabaseConfig;
 pub use args::{Cli, Commands};
@@ -17,6 +20,8 @@ pub use metrics::PerformanceMetrics;
 #[derive(Debug, Default)]
 pub struct MemTable {
     data: BTreeMap<String, Value>,
+    range_tombstones: Vec<RangeTombstone>, // Older than every entry in data they cover
+    size_bytes: usize, // Key + value bytes currently held
 }
 
 #[derive(Debug, PartialEq)]
@@ -24,29 +29,113 @@ pub enum DbError {
     KeyNotFound(String),
     InvalidOperation(String),
     InvalidQuery(String),
+    Corruption(String), // Stored data failed its integrity check
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     Data(String),
     Tombstone,
+    Bytes(Vec<u8>), // Binary value, kept after the original variants so older files still decode
+    RangeTombstone(String), // End (exclusive) of a range delete starting at the record's key
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WALEntry {
     Insert { key: String, value: String },
     Delete { key: String },
+    BeginTxn { txn_id: u64 },
+    CommitTxn { txn_id: u64 },
+    InsertBytes { key: String, value: Vec<u8> },
+    DeleteRange { start: String, end: String },
 }
 
 impl WALEntry {
-    pub fn key(&self) -> &str {
+    // Transaction markers carry no key
+    pub fn key(&self) -> Option<&str> {
         match self {
-            WALEntry::Insert {key, ..} => key,
-            WALEntry::Delete {key} => key,
+            WALEntry::Insert {key, ..} => Some(key),
+            WALEntry::Delete {key} => Some(key),
+            WALEntry::InsertBytes {key, ..} => Some(key),
+            WALEntry::DeleteRange {start, ..} => Some(start),
+            WALEntry::BeginTxn { .. } | WALEntry::CommitTxn { .. } => None,
         }
     }
 }
 
+// A single write within an atomic transaction
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteOp {
+    Put(String, String),
+    Delete(String),
+}
+
+// Builds composite keys such as `users:email:a@b.c` from segments. The separator and the
+// escape character are escaped inside each segment, so a value containing the separator can't
+// produce the same key as a different namespace, field or value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+    encoded: String,
+    separator: char,
+}
+
+pub const KEY_SEPARATOR: char = ':';
+const KEY_ESCAPE: char = '\\';
+
+impl Key {
+    pub fn new(namespace: &str) -> Self {
+        Self::with_separator(namespace, KEY_SEPARATOR)
+    }
+
+    pub fn with_separator(namespace: &str, separator: char) -> Self {
+        assert_ne!(separator, KEY_ESCAPE, "the key escape character can't be the separator");
+        let mut key = Self { encoded: String::new(), separator };
+        key.push_segment(namespace);
+        key
+    }
+
+    pub fn field(mut self, name: &str) -> Self {
+        self.encoded.push(self.separator);
+        self.push_segment(name);
+        self
+    }
+
+    pub fn value(mut self, value: impl std::fmt::Display) -> Self {
+        self.encoded.push(self.separator);
+        self.push_segment(&value.to_string());
+        self
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.encoded
+    }
+
+    pub fn into_string(self) -> String {
+        self.encoded
+    }
+
+    fn push_segment(&mut self, segment: &str) {
+        for c in segment.chars() {
+            if c == self.separator || c == KEY_ESCAPE {
+                self.encoded.push(KEY_ESCAPE);
+            }
+            self.encoded.push(c);
+        }
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.encoded)
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        key.encoded
+    }
+}
+
 impl Value {
     pub fn is_tombstone(&self) -> bool {
         matches!(self, Value::Tombstone)
@@ -55,7 +144,44 @@ impl Value {
     pub fn as_data(&self) -> Option<&String> {
         match self {
             Value::Data(s) => Some(s),
-            Value::Tombstone => None,
+            Value::Tombstone | Value::Bytes(_) | Value::RangeTombstone(_) => None,
+        }
+    }
+
+    // Stored bytes of a live value, text or binary
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Data(s) => Some(s.as_bytes()),
+            Value::Bytes(bytes) => Some(bytes),
+            Value::Tombstone | Value::RangeTombstone(_) => None,
+        }
+    }
+
+    // Size of the stored value in bytes, 0 for a tombstone
+    pub fn data_len(&self) -> usize {
+        self.as_bytes().map_or(0, <[u8]>::len)
+    }
+
+    // A live value as text for the String API. Binary values come back as text only when they
+    // are valid UTF-8; `key` names the entry in the error otherwise
+    pub fn to_text(&self, key: &str) -> DbResult<Option<String>> {
+        match self {
+            Value::Data(s) => Ok(Some(s.clone())),
+            Value::Bytes(bytes) => String::from_utf8(bytes.clone()).map(Some).map_err(|_| {
+                DbError::InvalidOperation(format!("Value for key '{}' is binary, read it with get_bytes", key))
+            }),
+            Value::Tombstone | Value::RangeTombstone(_) => Ok(None),
+        }
+    }
+
+    // A live value as text for the String scan APIs, which return many entries at once: a
+    // binary value that isn't valid UTF-8 comes back with U+FFFD in place of the invalid bytes
+    // rather than failing the whole scan
+    pub fn to_text_lossy(&self) -> Option<String> {
+        match self {
+            Value::Data(s) => Some(s.clone()),
+            Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+            Value::Tombstone | Value::RangeTombstone(_) => None,
         }
     }
 }
@@ -66,6 +192,7 @@ impl std::fmt::Display for DbError {
             DbError::KeyNotFound(key) => write!(f, "Key not found: {}", key),
             DbError::InvalidOperation(msg) => write!(f, "Invalid operation: {}", msg),
             DbError::InvalidQuery(msg) => write!(f, "Invalid query: {}", msg),
+            DbError::Corruption(msg) => write!(f, "Data corruption: {}", msg),
         }
     }
 }
@@ -81,24 +208,66 @@ impl MemTable {
     pub fn new() -> Self {
         Self {
             data: BTreeMap::new(),
+            range_tombstones: Vec::new(),
+            size_bytes: 0,
         }
     }
 
     pub fn insert(&mut self, key: String, value: String) -> DbResult<()> {
-        self.data.insert(key, Value::Data(value));
+        self.put(key, Value::Data(value));
+        Ok(())
+    }
+
+    pub fn insert_bytes(&mut self, key: String, value: Vec<u8>) -> DbResult<()> {
+        self.put(key, Value::Bytes(value));
+        Ok(())
+    }
+
+    // Delete every key in [start, end). Entries already here are older than the range delete,
+    // so they are dropped rather than kept alongside it
+    pub fn delete_range(&mut self, start: String, end: String) -> DbResult<()> {
+        if start >= end {
+            return Err(DbError::InvalidOperation(format!("Empty key range '{}'..'{}'", start, end)));
+        }
+
+        let bounds = (Bound::Included(start.as_str()), Bound::Excluded(end.as_str()));
+        let covered: Vec<String> = self.data.range::<str, _>(bounds).map(|(key, _)| key.clone()).collect();
+        for key in covered {
+            if let Some(old) = self.data.remove(&key) {
+                self.size_bytes -= key.len() + old.data_len();
+            }
+        }
+
+        self.size_bytes += start.len() + end.len();
+        self.range_tombstones.push(RangeTombstone { start, end });
         Ok(())
     }
 
     pub fn insert_tombstone(&mut self, key: String) -> DbResult<()> {
-        self.data.insert(key, Value::Tombstone);
+        self.put(key, Value::Tombstone);
         Ok(())
     }
 
+    // Insert or replace an entry, keeping size_bytes in step
+    fn put(&mut self, key: String, value: Value) {
+        let key_len = key.len();
+        let value_len = value.data_len();
+        match self.data.insert(key, value) {
+            Some(old) => self.size_bytes -= old.data_len(),
+            None => self.size_bytes += key_len,
+        }
+        self.size_bytes += value_len;
+    }
+
     pub fn get(&self, key: &str) -> DbResult<&String> {
         match self.data.get(key) {
             Some(Value::Data(s)) => Ok(s),
-            Some(Value::Tombstone) => Err(DbError::KeyNotFound(key.to_string())),
-            None => Err(DbError::KeyNotFound(key.to_string())),
+            Some(Value::Bytes(_)) => Err(DbError::InvalidOperation(format!(
+                "Value for key '{}' is binary, read it with get_bytes", key
+            ))),
+            Some(Value::Tombstone) | Some(Value::RangeTombstone(_)) | None => {
+                Err(DbError::KeyNotFound(key.to_string()))
+            }
         }
     }
 
@@ -106,13 +275,18 @@ impl MemTable {
         match self.data.get(key) {
             Some(Value::Data(s)) => {
                 let value = s.clone();
-                self.data.insert(key.to_string(), Value::Tombstone);
+                self.put(key.to_string(), Value::Tombstone);
+                Ok(value)
+            }
+            Some(Value::Bytes(bytes)) => {
+                let value = String::from_utf8_lossy(bytes).into_owned();
+                self.put(key.to_string(), Value::Tombstone);
                 Ok(value)
             }
-            Some(Value::Tombstone) => Err(DbError::KeyNotFound(key.to_string())),
+            Some(Value::Tombstone) | Some(Value::RangeTombstone(_)) => Err(DbError::KeyNotFound(key.to_string())),
             None => {
                 // Key not in MemTable, insert tombstone anyway (might be in SSTable)
-                self.data.insert(key.to_string(), Value::Tombstone);
+                self.put(key.to_string(), Value::Tombstone);
                 Ok("".to_string()) // We dont know the original value
             }
         }
@@ -123,12 +297,26 @@ impl MemTable {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.data.is_empty() && self.range_tombstones.is_empty()
     }
 
     pub fn data(&self) -> &BTreeMap<String, Value> {
         &self.data
     }
+
+    pub fn range_tombstones(&self) -> &[RangeTombstone] {
+        &self.range_tombstones
+    }
+
+    // Whether a range delete here hides the key from older sources. Check data first:
+    // an entry in data is newer than any range tombstone
+    pub fn range_deleted(&self, key: &str) -> bool {
+        self.range_tombstones.iter().any(|tombstone| tombstone.covers(key))
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
 }
 
 #[cfg(test)]
@@ -154,5 +342,43 @@ mod tests {
             assert_eq!(db.get(&key).unwrap(), &expected_value);
         }
     }
+
+    #[test]
+    fn test_size_bytes_tracking() {
+        let mut db = MemTable::new();
+        assert_eq!(db.size_bytes(), 0);
+
+        db.insert("key".to_string(), "value".to_string()).unwrap();
+        assert_eq!(db.size_bytes(), 8);
+
+        // Overwriting replaces the value bytes but counts the key once
+        db.insert("key".to_string(), "longer value".to_string()).unwrap();
+        assert_eq!(db.size_bytes(), 15);
+
+        db.delete("key").unwrap();
+        assert_eq!(db.size_bytes(), 3);
+    }
+
+    #[test]
+    fn test_key_builder_escapes_separator() {
+        let key = Key::new("users").field("email").value("a@b.c");
+        assert_eq!(key.as_str(), "users:email:a@b.c");
+        assert_eq!(Key::new("users").field("id").value(42).into_string(), "users:id:42");
+
+        // A separator inside a value must not read as a segment boundary
+        let with_colon = Key::new("users").field("email").value("a:b");
+        let split = Key::new("users").field("email:a").value("b");
+        assert_eq!(with_colon.as_str(), "users:email:a\\:b");
+        assert_ne!(with_colon, split);
+        assert_ne!(with_colon.as_str(), "users:email:a:b");
+
+        // Escapes are escaped too, so a trailing backslash can't swallow the next separator
+        assert_ne!(
+            Key::new("t").value("a\\").value("b").into_string(),
+            Key::new("t").value("a\\:b").into_string()
+        );
+
+        assert_eq!(Key::with_separator("users", '/').field("name").value("a/b").as_str(), "users/name/a\\/b");
+    }
 }
 
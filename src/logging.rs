@@ -0,0 +1,76 @@
+// Logging setup - the engine reports through the `log` facade, and this installs the
+// logger the CLI uses. Embedding applications can install their own logger instead.
+
+use crate::config::LoggingConfig;
+use crate::{DbError, DbResult};
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::Mutex;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Writes one line per event to stderr, or appends to a file when one is configured
+struct Logger {
+    level: LevelFilter,
+    log_file: Option<Mutex<File>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let line = format!(
+            "[{}.{:03}] {:<5} {}: {}",
+            timestamp.as_secs(),
+            timestamp.subsec_millis(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        // Logging must never take the process down, so write failures are ignored
+        match self.log_file {
+            Some(ref file) => {
+                let _ = writeln!(file.lock(), "{}", line);
+            }
+            None => eprintln!("{}", line),
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(ref file) = self.log_file {
+            let _ = file.lock().flush();
+        }
+    }
+}
+
+// Install the logger for the configured level and destination. Only the first call in a
+// process takes effect, as with any `log` backend
+pub fn init(config: &LoggingConfig) -> DbResult<()> {
+    let level = LevelFilter::from_str(&config.level).map_err(|_| {
+        DbError::InvalidOperation(format!("Invalid configuration: unknown logging level '{}'", config.level))
+    })?;
+
+    let log_file = match config.log_file {
+        Some(ref path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| {
+                DbError::InvalidOperation(format!("Failed to open log file {}: {}", path.display(), e))
+            })?;
+            Some(Mutex::new(file))
+        }
+        None => None,
+    };
+
+    if log::set_boxed_logger(Box::new(Logger { level, log_file })).is_ok() {
+        log::set_max_level(level);
+    }
+    Ok(())
+}
@@ -1,469 +0,0 @@
-// Query DSL macro implementation for compile-time SQL validation
-
-use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{parse::{Parse, ParseStream}, Result, Error, Expr, LitStr, Token};
-
-// Input structure for the query! macro
-pub struct QueryMacroInput {
-    pub db: Expr,
-    pub sql: LitStr,
-}
-
-impl Parse for QueryMacroInput {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let db = input.parse()?;
-        input.parse::<Token![,]>()?;
-        let sql = input.parse()?;
-        Ok(QueryMacroInput { db, sql })
-    }
-}
-
-// Generate compile-time validated query execution
-pub fn generate_query_macro(input: &QueryMacroInput) -> Result<TokenStream> {
-    let db = &input.db;
-    let sql_str = input.sql.value();
-
-    let query_analysis = analyze_sql(&sql_str)?;
-
-    match query_analysis.query_type {
-        QueryType::Select => generate_select_query(db, &sql_str, &query_analysis),
-        QueryType::Insert => generate_insert_query(db, &sql_str, &query_analysis),
-        QueryType::Delete => generate_delete_query(db, &sql_str, &query_analysis),
-        QueryType::Update => generate_update_query(db, &sql_str, &query_analysis),
-    }
-}
-
-
-// SQL query analysis result
-#[derive(Debug)]
-pub struct QueryAnalysis {
-    pub query_type: QueryType,
-    pub table_name: Option<String>,
-    pub columns: Vec<String>,
-    pub where_conditions: Vec<WhereCondition>,
-    pub has_parameters: bool,
-}
-
-#[derive(Debug)]
-pub enum QueryType {
-    Select,
-    Insert,
-    Delete,
-    Update,
-}
-
-#[derive(Debug)]
-pub struct WhereCondition {
-    pub column: String,
-    pub operator: String,
-    pub value: String,
-}
-
-// Analyze SQL string at compile time
-fn analyze_sql(sql: &str) -> Result<QueryAnalysis> {
-    let sql_upper = sql.to_uppercase();
-    let tokens: Vec<&str> = sql.split_whitespace().collect();
-
-    if tokens.is_empty() {
-        return Err(Error::new(
-            proc_macro2::Span::call_site(),
-            "Empty SQL query",
-        ));
-    }
-
-    let query_type = match tokens[0].to_uppercase().as_str() {
-        "SELECT" => QueryType::Select,
-        "INSERT" => QueryType::Insert,
-        "DELETE" => QueryType::Delete,
-        "UPDATE" => QueryType::Update,
-        _ => return Err(Error::new(
-            proc_macro2::Span::call_site(),
-            format!("Unsupported SQL query type: {}", tokens[0]),
-        )),
-    };
-
-    // Extract table name
-    let table_name = extract_table_name(&tokens, &query_type)?;
-
-    // Extract columns (for SELECT)
-    let columns = if matches!(query_type, QueryType::Select) {
-        extract_select_columns(&tokens)?
-    } else {
-        vec![]
-    };
-
-    // Check for parameter placeholders
-    let has_parameters = sql.contains('{') || sql.contains('}');
-
-    // Extract WHERE conditions
-    let where_conditions = extract_where_conditions(&tokens)?;
-
-    Ok(QueryAnalysis {
-        query_type,
-        table_name,
-        columns,
-        where_conditions,
-        has_parameters,
-    })
-}
-
-// Extract table name from SQL tokens
-fn extract_table_name(tokens: &[&str], query_type: &QueryType) -> Result<Option<String>> {
-    match query_type {
-        QueryType::Select => {
-            if let Some(from_pos) = tokens.iter().position(|&t| t.to_uppercase() == "FROM") {
-                if from_pos + 1 < tokens.len() {
-                    return Ok(Some(tokens[from_pos + 1].to_string()));
-                } else {
-                    Err(Error::new(
-                        proc_macro2::Span::call_site(),
-                        "Missing table name after FROM",
-                    ))
-                }
-            } else {
-                Err(Error::new(
-                    proc_macro2::Span::call_site(),
-                    "Missing FROM clause in SELECT",
-                ))
-            }
-        }
-        QueryType::Insert => {
-            if let Some(into_pos) = tokens.iter().position(|&t| t.to_uppercase() == "INTO") {
-                if into_pos + 1 < tokens.len() {
-                    return Ok(Some(tokens[into_pos + 1].to_string()));
-                } else {
-                    Err(Error::new(
-                        proc_macro2::Span::call_site(),
-                        "Missing table name after INTO",
-                    ))
-                }
-            } else {
-                Err(Error::new(
-                    proc_macro2::Span::call_site(),
-                    "Missing INTO clause in INSERT",
-                ))
-            }
-        }
-        QueryType::Delete => {
-            if let Some(from_pos) = tokens.iter().position(|&t| t.to_uppercase() == "FROM") {
-                if from_pos + 1 < tokens.len() {
-                    return Ok(Some(tokens[from_pos + 1].to_string()));
-                } else {
-                    Err(Error::new(
-                        proc_macro2::Span::call_site(),
-                        "Missing table name after FROM",
-                    ))
-                }
-            } else {
-                Err(Error::new(
-                    proc_macro2::Span::call_site(),
-                    "Missing FROM clause in DELETE",
-                ))
-            }
-        }
-        QueryType::Update => {
-            if tokens.len() > 1 {
-                Ok(Some(tokens[1].to_string()))
-            } else {
-                Err(Error::new(
-                    proc_macro2::Span::call_site(),
-                    "Missing table name in UPDATE",
-                ))
-            }
-        }
-    }
-}
-
-// Extract SELECT columns
-fn extract_select_columns(tokens: &[&str]) -> Result<Vec<String>> {
-    if tokens.len() < 2 {
-        return Ok(vec![]);
-    }
-
-    let from_pos = tokens.iter().position(|&t| t.to_uppercase() == "FROM")
-        .unwrap_or(tokens.len());
-
-    let columns_part = &tokens[1..from_pos].join(" ");
-    let columns: Vec<String> = columns_str
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    Ok(columns)
-}
-
-// Extract WHERE conditions
-fn extract_where_conditions(tokens: &[&str]) -> Result<Vec<WhereCondition>> {
-    // Simplified WHERE parsing for demonstration purposes
-    // In a real implementation, this would be much more sophisticated
-    let mut conditions = vec![];
-
-    if let Some(where_pos) = tokens.iter().position(|&t| t.to_uppercase() == "WHERE") {
-        // Simple parsing for "column = value" pattern
-        if where_pos + 3 < tokens.len() {
-            conditions.push(WhereCondition {
-                column: tokens[where_pos + 1].to_string(),
-                operator: tokens[where_pos + 2].to_string(),
-                value: tokens[where_pos + 3].to_string(),
-            });
-        }
-    }
-
-    Ok(conditions)
-}
-
-// Generate SELECT query execution code
-fn generate_select_query(
-    db: &Expr,
-    sql: &str,
-    analysis: &QueryAnalysis,
-) -> Result<TokenStream> {
-    let expanded = quote! {
-        {
-            let mut parser = crate::query::SQLParser::new(#sql);
-            let statement = parser.parse()
-                .map_err(|e| crate::DbError::InvalidQuery(format!("SQL parsing error: {}", e)))?;
-
-            let mut executor = crate::query::QueryExecutor::new(#db);
-            executor.execute(statement)
-        }
-    };
-    Ok(expanded)
-}
-
-// Generate INSERT query execution code
-fn generate_insert_query(
-    db: &Expr,
-    sql: &str,
-    analysis: &QueryAnalysis,
-) -> Result<TokenStream> {
-    let expanded = quote! {
-        {
-            let mut parser = crate::query::SQLParser::new(#sql);
-            let statement = parser.parse()
-                .map_err(|e| crate::DbError::InvalidQuery(format!("SQL parsing error: {}", e)))?;
-
-            let mut executor = crate::query::QueryExecutor::new(#db);
-            executor.execute(statement)
-        }
-    };
-    Ok(expanded)
-}
-
-// Generate DELETE query execution code
-fn generate_delete_query(
-    db: &Expr,
-    sql: &str,
-    analysis: &QueryAnalysis,
-) -> Result<TokenStream> {
-    let expanded = quote! {
-        {
-            let mut parser = crate::query::SQLParser::new(#sql);
-            let statement = parser.parse()
-                .map_err(|e| crate::DbError::InvalidQuery(format!("SQL parsing error: {}", e)))?;
-
-            let mut executor = crate::query::QueryExecutor::new(#db);
-            executor.execute(statement)
-        }
-    };
-    Ok(expanded)
-}
-
-// Generate UPDATE query execution code
-fn generate_update_query(
-    db: &Expr,
-    sql: &str,
-    analysis: &QueryAnalysis,
-) -> Result<TokenStream> {
-    let expanded = quote! {
-        {
-            let mut parser = crate::query::SQLParser::new(#sql);
-            let statement = parser.parse()
-                .map_err(|e| crate::DbError::InvalidQuery(format!("SQL parsing error: {}", e)))?;
-
-            let mut executor = crate::query::QueryExecutor::new(#db);
-            executor.execute(statement)
-        }
-    };
-    Ok(expanded)
-}
-
-// Table definition macro input
-pub struct TableDefinition {
-    pub name: syn::Ident,
-    pub fields: Vec<TableField>,
-}
-
-pub struct TableField {
-    pub name: syn::Ident,
-    pub field_type: syn::Type,
-    pub attributes: Vec<String>,
-}
-
-impl Parse for TableDefinition {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let name = input.parse()?;
-        let content;
-        syn::braced!(content in input);
-
-        let mut fields = Vec::new();
-        while !content.is_empty() {
-            let field_name = content.parse()?;
-            content.parse::<Token![:]>()?;
-            let field_type = content.parse()?;
-
-            let mut attributes = Vec::new();
-            if content.peek(syn::token::Paren) {
-                let attr_content;
-                syn::parenthesized!(attr_content in content);
-                // Parse attributes like (primary_ley), (indexed)
-                while !attr_content.is_empty() {
-                    let attr = attr_content.parse::<syn::Ident>()?;
-                    attributes.push(attr.to_string());
-                    if attr_content.peek(Token![,]) {
-                        attr_content.parse::<Token![,]>()?;
-                    }
-                }
-            }
-
-            fields.push(TableField {
-                name: field_name,
-                field_type,
-                attributes,
-            });
-
-            if content.peek(Token![,]) {
-                content.parse::<Token![,]>()?;
-            }
-        }
-
-        Ok(TableDefinition { name, fields })
-    }
-}
-
-// Generate table definition code
-pub fn generate_table_definition(def: &TableDefinition) -> Result<TokenStream> {
-    let table_name = &input.name;
-    let struct_fields: Vec<_> = input.fields.iter().map(|f| {
-        let name = &f.name;
-        let ty = &f.field_type;
-        quote! { pub #name: #ty }
-    }).collect();
-
-    let field_attributes: Vec<_> = input.fields.iter().map(|f| {
-        let attrs: Vec<_> = f.attributes.iter().map(|attr| {
-            let attr_ident = syn::Ident::new(attr, proc_macros2::Span::call_site());
-            quote! { #[#attr_ident] }
-        }).collect();
-        quote! { #(#attrs)* }
-    }).collect();
-
-    let expanded = quote! {
-        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, QueryBuilder)]
-        pub struct #table_name {
-            #(
-                #field_attributes
-                #struct_fields
-            ),*
-        }
-    };
-
-    Ok(expanded)
-}
-
-// Generate migration code
-pub fn generate_migration(input: &MigrationDefinition) -> Result<TokenStream> {
-    let name_str = input.name.value();
-    let up_sql = input.up_sql.value();
-    let down_sql = input.down_sql.value();
-
-    let migration_name = syn::Ident::new(
-        &format!("Migration{}", name_str.replace("-", "_").replace(" ", "_")),
-        proc_macro2::Span::call_site(),
-    );
-
-    let expanded = quote! {
-        pub struct #migration_name;
-
-        impl #migration_name {
-            pub fn name() -> &'static str {
-                #name_str
-            }
-
-            pub fn up(db: &mut crate::engine::LSMTree) -> crate::DbResult<()> {
-                // In a real implementation, this would parse and execute the SQL
-                println!("Executing migration up: {}", #up_sql);
-                Ok(())
-            }
-
-            pub fn down(db: &mut crate::engine::LSMTree) -> crate::DbResult<()> {
-                // In a real implementation, this would parse and execute the SQL
-                println!("Executing migration down: {}", #down_sql);
-                Ok(())
-            }
-        }
-    };
-
-    Ok(expanded)
-}
-
-// Database configuration input
-pub struct DatabaseConfig {
-    pub settings: Vec<(syn::Ident, syn::Expr)>,
-}
-
-impl Parse for DatabaseConfig {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let mut settings = Vec::new();
-
-        while !input.is_empty() {
-            let key = input.parse()?;
-            input.parse::<Token![:]>()?;
-            let value = input.parse()?;
-            settings.push((key, value));
-
-            if input.peek(Token![,]) {
-                input.parse::<Token![,]>()?;
-            }
-        }
-
-        Ok(DatabaseConfig { settings })
-    }
-}
-
-// Generate database configuration code
-pub fn generate_database_config(config: &DatabaseConfig) -> Result<TokenStream> {
-    let mut path = None;
-    let mut memtable_size = None;
-    let mut enable_wal = None;
-
-    for (key, value) in &input.settings {
-        match key.to_string().as_str() {
-            "path" => path = Some(value),
-            "memtable_size" => memtable_size = Some(value),
-            "enable_wal" => enable_wal = Some(value),
-            _ => return Err(Error::new_spanned(key, format!("Unknown database configuration option: {}", key))),
-        }
-    }
-
-    let data_dir = path.unwrap_or(&syn::parse_quote!("data/default"));
-    let memtable_size = memtable_size.unwrap_or(&syn::parse_quote!(1000));
-    let wal_enabled = enable_wal.unwrap_or(&syn::parse_quote!(true));
-
-    let expanded = quote! {
-        {
-            let config = crate::engine::LSMConfig {
-                memtable_size_limit: #memtable_size_limit,
-                data_dir: std::Path::from(#data_dir),
-                background_compaction: false,
-                background_compaction_interval: std::time::Duration::from_secs(10),
-                enable_wal: #wal_enabled,
-            }
-            crate::engine::LSMTree:with_config(config)
-        }
-    };
-
-    Ok(expanded)
-}
\ No newline at end of file
@@ -5,6 +5,7 @@ use rust_solo_all_db::args::{Cli, Commands, MaintenanceOps};
 use rust_solo_all_db::config::DatabaseConfig;
 use rust_solo_all_db::metrics::PerformanceMetrics;
 use rust_solo_all_db::engine::LSMTree;
+use rust_solo_all_db::query::{QueryExecutor, SQLParser};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -18,6 +19,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("⚠️  Configuration file not found, using defaults");
         DatabaseConfig::default()
     };
+    rust_solo_all_db::logging::init(&config.logging)?;
 
     // Initialize performance metrics
     let metrics = Arc::new(PerformanceMetrics::new());
@@ -25,27 +27,31 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create database
     let lsm_config = config.to_lsm_config();
     let mut db = LSMTree::with_config(lsm_config)?;
+    db.set_metrics(metrics.clone());
     
     match cli.command {
         Commands::Interactive => {
             run_interactive_mode(&mut db, &config, metrics)?;
         }
         
-        Commands::Load { file, key_column: _, value_column: _, threads: _, batch_size: _ } => {
-            run_simple_load_command(&mut db, file)?;
+        Commands::Load { file, key_column, value_column, threads, batch_size } => {
+            run_load_command(&mut db, &config, file, &key_column, &value_column, threads, batch_size)?;
         }
         
-        Commands::Query { sql: _, format: _, limit: _ } => {
-            println!("❌ Query command not yet implemented for your current API");
-            println!("💡 Use 'cargo run -- interactive' to access query functionality");
+        Commands::Import { file } => {
+            run_import_command(&mut db, &config, file)?;
+        }
+        
+        Commands::Query { sql, format, limit } => {
+            run_query_command(&mut db, &config, &sql, &format, limit)?;
         }
         
         Commands::Benchmark { bench_type, operations, threads: _ } => {
             run_benchmark_command(&mut db, bench_type, operations, metrics)?;
         }
         
-        Commands::Stats { live, interval } => {
-            run_stats_command(&db, live, interval as u64, metrics)?;
+        Commands::Stats { live, interval, format } => {
+            run_stats_command(&db, live, interval as u64, &format, metrics)?;
         }
         
         Commands::Maintenance { operation } => {
@@ -64,35 +70,66 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 fn run_interactive_mode(
     _db: &mut LSMTree, 
-    _config: &DatabaseConfig,
+    config: &DatabaseConfig,
     _metrics: Arc<PerformanceMetrics>
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("🚀 Welcome to RustDB Interactive Mode!");
     println!("Type 'help' for commands or 'quit' to exit.");
     
-    // Use your existing CLI module which creates its own database
-    let mut database_cli = rust_solo_all_db::cli::DatabaseCLI::new()?;
+    // Use your existing CLI module which creates its own database, with the loaded settings
+    let database_cli = rust_solo_all_db::cli::DatabaseCLI::with_config(config.clone())?;
     database_cli.run()?;
     Ok(())
 }
 
-fn run_simple_load_command(
+fn run_load_command(
     db: &mut LSMTree,
+    config: &DatabaseConfig,
     file: std::path::PathBuf,
+    key_column: &str,
+    value_column: &str,
+    threads: Option<usize>,
+    batch_size: Option<usize>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("📂 Loading CSV file: {}", file.display());
-    
-    let loader = rust_solo_all_db::etl::ETLLoader::new();
+
+    // Command-line flags override the configured ETL settings
+    let mut loader = rust_solo_all_db::etl::ETLLoader::with_config(
+        batch_size.unwrap_or(config.etl.batch_size),
+        threads.unwrap_or(config.etl.parallel_threads),
+    )
+    .with_delimiter(config.etl.delimiter as u8)
+    .with_headers(config.etl.has_headers)
+    .with_progress(|done, total| eprint!("\r⏳ {}/{} rows", done, total));
+
+    // Each column is given by index or by header name, independently of the other
+    let (key_column, value_column) = match (key_column.parse(), value_column.parse()) {
+        (Ok(key_index), Ok(value_index)) => (key_index, value_index),
+        _ => {
+            loader = loader.with_named_columns(key_column, value_column);
+            (0, 1)
+        }
+    };
     
     let start = Instant::now();
-    let result = loader.load_csv(&file, db, 0, 1);
+    // `-` reads the CSV from stdin, so data can be piped in
+    let result = if file.as_os_str() == "-" {
+        loader.load_reader(std::io::stdin().lock(), db, key_column, value_column, config.etl.has_headers)
+    } else {
+        loader.load_csv_detailed(&file, db, key_column, value_column, config.etl.has_headers)
+    };
     let duration = start.elapsed();
+    eprintln!();
     
     match result {
-        Ok(count) => {
-            println!("✅ Successfully loaded {} records in {:.2}s", 
-                count, duration.as_secs_f64());
+        Ok(result) => {
+            let count = result.successful_inserts;
+            println!("✅ Successfully loaded {} of {} records in {:.2}s ({} errors)", 
+                count, result.total_rows, duration.as_secs_f64(), result.errors.len());
             println!("📊 Rate: {:.2} records/second", count as f64 / duration.as_secs_f64());
+            for error in &result.errors {
+                eprintln!("  Row {}: {}", error.row_number, error.error);
+            }
         }
         Err(e) => {
             eprintln!("❌ Failed to load CSV: {}", e);
@@ -103,6 +140,32 @@ fn run_simple_load_command(
     Ok(())
 }
 
+fn run_import_command(
+    db: &mut LSMTree,
+    config: &DatabaseConfig,
+    file: std::path::PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("📂 Importing dump: {}", file.display());
+
+    let loader = rust_solo_all_db::etl::ETLLoader::with_config(
+        config.etl.batch_size,
+        config.etl.parallel_threads,
+    );
+    let start = Instant::now();
+    match loader.import_dump(&file, db) {
+        Ok(result) => {
+            println!("✅ Imported {} upserts and {} deletes in {:.2}s",
+                result.upserts, result.deletes, start.elapsed().as_secs_f64());
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to import dump: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
 fn run_benchmark_command(
     db: &mut LSMTree,
     bench_type: String,
@@ -116,6 +179,7 @@ fn run_benchmark_command(
         "query" => benchmark_queries(db, operations, metrics),
         "all" => {
             benchmark_inserts(db, operations / 2, metrics.clone())?;
+            metrics.reset(); // Keep the insert phase out of the query phase's numbers
             benchmark_queries(db, operations / 2, metrics)?;
             Ok(())
         }
@@ -126,15 +190,36 @@ fn run_benchmark_command(
     }
 }
 
+fn run_query_command(
+    db: &mut LSMTree,
+    config: &DatabaseConfig,
+    sql: &str,
+    format: &str,
+    limit: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let statement = SQLParser::new(sql).parse()?;
+    let result = QueryExecutor::new(db).with_query_config(&config.query).execute(statement)?;
+    println!("{}", result.render(format, limit)?);
+    Ok(())
+}
+
 fn run_stats_command(
-    _db: &LSMTree,
+    db: &LSMTree,
     live: bool,
     interval: u64,
+    format: &str,
     metrics: Arc<PerformanceMetrics>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if live {
+    metrics.update_memory_usage(db.approx_memory_bytes());
+
+    if format == "prometheus" {
+        print!("{}", metrics.to_prometheus());
+    } else if format == "json" {
+        println!("{}", metrics.to_json(&db.stats())?);
+    } else if live {
         println!("📊 Starting live statistics monitoring (Ctrl+C to exit)...");
         loop {
+            metrics.update_memory_usage(db.approx_memory_bytes());
             metrics.print_live_stats();
             std::thread::sleep(Duration::from_secs(interval));
         }
@@ -147,6 +232,8 @@ fn run_stats_command(
         for (op, stat) in stats.operation_stats {
             println!("{}: {} operations, {:.2} ops/sec", op, stat.count, stat.ops_per_second);
         }
+        println!("Compactions: {} ({:.2}s total)",
+            stats.compaction_stats.count, stats.compaction_stats.total_duration.as_secs_f64());
     }
     
     Ok(())
@@ -165,6 +252,31 @@ fn run_maintenance_command(
             println!("✅ Compaction completed in {:.2}s", duration.as_secs_f64());
         }
         
+        MaintenanceOps::CompactLevel { level } => {
+            println!("🔧 Compacting level {}...", level);
+            let start = Instant::now();
+            let result = db.compact_level_n(level)?;
+            let duration = start.elapsed();
+            println!("✅ {} in {:.2}s", result, duration.as_secs_f64());
+        }
+
+        MaintenanceOps::Plan => {
+            let plans = db.compaction_plan();
+            if plans.is_empty() {
+                println!("📋 No compaction needed");
+            } else {
+                println!("📋 Compaction plan:");
+                for plan in plans {
+                    println!("{}", plan);
+                }
+            }
+        }
+        
+        MaintenanceOps::Distribution => {
+            println!("📊 Key distribution:");
+            print!("{}", db.key_distribution());
+        }
+        
         MaintenanceOps::Vacuum => {
             println!("🧹 Vacuuming deleted entries...");
             // Implement vacuum logic when available
@@ -173,8 +285,16 @@ fn run_maintenance_command(
         
         MaintenanceOps::Verify => {
             println!("🔍 Verifying database integrity...");
-            // Implement verification logic when available
-            println!("✅ Database integrity verified");
+            let report = db.verify();
+            if report.is_ok() {
+                println!("✅ Database integrity verified ({} SSTables)", report.sstables_checked);
+            } else {
+                for (file, error) in &report.corrupt {
+                    eprintln!("❌ {}: {}", file.display(), error);
+                }
+                eprintln!("❌ {} of {} SSTables failed verification", report.corrupt.len(), report.sstables_checked);
+                std::process::exit(1);
+            }
         }
         
         MaintenanceOps::Info => {
@@ -204,6 +324,7 @@ fn benchmark_inserts(db: &mut LSMTree, operations: usize, metrics: Arc<Performan
         let op_duration = op_start.elapsed();
         metrics.record_operation("insert", op_duration);
     }
+    metrics.update_memory_usage(db.approx_memory_bytes());
     
     let duration = start.elapsed();
     let ops_per_sec = operations as f64 / duration.as_secs_f64();
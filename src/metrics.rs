@@ -1,32 +1,72 @@
+use crate::engine::LSMStats;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub struct PerformanceMetrics {
-    start_time: Instant,
+    start_time: Arc<Mutex<Instant>>,
     operation_counts: Arc<Mutex<HashMap<String, u64>>>,
     operation_durations: Arc<Mutex<HashMap<String, Duration>>>,
+    operation_latencies: Arc<Mutex<HashMap<String, LatencyHistogram>>>,
+    operation_windows: Arc<Mutex<HashMap<String, (Instant, Instant)>>>, // First start and last end per operation
     memory_usage: Arc<Mutex<usize>>,
+    compaction_stats: Arc<Mutex<CompactionStats>>,
 }
 
 impl PerformanceMetrics {
     pub fn new() -> Self {
         Self {
-            start_time: Instant::now(),
+            start_time: Arc::new(Mutex::new(Instant::now())),
             operation_counts: Arc::new(Mutex::new(HashMap::new())),
             operation_durations: Arc::new(Mutex::new(HashMap::new())),
+            operation_latencies: Arc::new(Mutex::new(HashMap::new())),
+            operation_windows: Arc::new(Mutex::new(HashMap::new())),
             memory_usage: Arc::new(Mutex::new(0)),
+            compaction_stats: Arc::new(Mutex::new(CompactionStats::default())),
         }
     }
 
     pub fn record_operation(&self, operation: &str, duration: Duration) {
         let mut counts = self.operation_counts.lock().unwrap();
         let mut durations = self.operation_durations.lock().unwrap();
+        let mut latencies = self.operation_latencies.lock().unwrap();
+        let mut windows = self.operation_windows.lock().unwrap();
+
+        let now = Instant::now();
+        let started = now.checked_sub(duration).unwrap_or(now);
+        let window = windows.entry(operation.to_string()).or_insert((started, now));
+        window.0 = window.0.min(started);
+        window.1 = now;
 
         *counts.entry(operation.to_string()).or_insert(0) += 1;
         let total_duration = durations.entry(operation.to_string()).or_insert(Duration::ZERO);
         *total_duration += duration;
+        latencies.entry(operation.to_string()).or_default().record(duration);
+    }
+
+    pub fn record_compaction(&self, level: usize, input_files: usize, output_files: usize, bytes_written: u64, duration: Duration) {
+        let mut stats = self.compaction_stats.lock().unwrap();
+
+        stats.count += 1;
+        stats.input_files += input_files as u64;
+        stats.output_files += output_files as u64;
+        stats.bytes_written += bytes_written;
+        stats.total_duration += duration;
+        *stats.count_by_level.entry(level).or_insert(0) += 1;
+    }
+
+    // Forget every recorded operation and compaction and restart the uptime clock, so the
+    // next phase of a benchmark isn't averaged together with the previous one. The memory
+    // gauge is left as is
+    pub fn reset(&self) {
+        self.operation_counts.lock().unwrap().clear();
+        self.operation_durations.lock().unwrap().clear();
+        self.operation_latencies.lock().unwrap().clear();
+        self.operation_windows.lock().unwrap().clear();
+        *self.compaction_stats.lock().unwrap() = CompactionStats::default();
+        *self.start_time.lock().unwrap() = Instant::now();
     }
 
     pub fn update_memory_usage(&self, bytes: usize) {
@@ -37,9 +77,12 @@ impl PerformanceMetrics {
     pub fn get_stats(&self) -> MetricsSnapshot {
         let counts = self.operation_counts.lock().unwrap();
         let durations = self.operation_durations.lock().unwrap();
+        let latencies = self.operation_latencies.lock().unwrap();
+        let windows = self.operation_windows.lock().unwrap();
         let memory = self.memory_usage.lock().unwrap();
+        let compaction_stats = self.compaction_stats.lock().unwrap().clone();
 
-        let uptime = self.start_time.elapsed();
+        let uptime = self.start_time.lock().unwrap().elapsed();
 
         let mut operation_stats = HashMap::new();
         for (op, &count) in counts.iter() {
@@ -50,12 +93,21 @@ impl PerformanceMetrics {
                 Duration::ZERO
             };
 
+            // Throughput over the span the operation was actually running, so a burst in a
+            // long-lived process isn't diluted by the idle time around it
+            let active = windows.get(op).map_or(Duration::ZERO, |&(first, last)| last - first);
+
+            let percentile = |p: f64| latencies.get(op).map_or(Duration::ZERO, |h| h.percentile(p));
+
             operation_stats.insert(op.clone(), OperationStats {
                 count,
                 total_duration: *total_duration,
                 average_duration: avg_duration,
-                ops_per_second: if uptime.as_secs() > 0 {
-                    count as f64 / uptime.as_secs_f64()
+                p50_duration: percentile(50.0),
+                p95_duration: percentile(95.0),
+                p99_duration: percentile(99.0),
+                ops_per_second: if !active.is_zero() {
+                    count as f64 / active.as_secs_f64()
                 } else {
                     0.0
                 },
@@ -66,9 +118,67 @@ impl PerformanceMetrics {
             uptime,
             memory_usage_bytes: *memory,
             operation_stats,
+            compaction_stats,
         }
     }
 
+    // Render metrics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let stats = self.get_stats();
+        let mut out = String::new();
+
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, f64)>| {
+            out.push_str(&format!("# HELP {} {}\n", name, help));
+            out.push_str(&format!("# TYPE {} {}\n", name, kind));
+            for (labels, value) in samples {
+                out.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+
+        metric("rustdb_uptime_seconds", "gauge", "Time since metrics collection started.",
+            vec![(String::new(), stats.uptime.as_secs_f64())]);
+        metric("rustdb_memory_usage_bytes", "gauge", "Estimated memory used by the database.",
+            vec![(String::new(), stats.memory_usage_bytes as f64)]);
+
+        // Sort so the output is stable between scrapes
+        let mut ops: Vec<_> = stats.operation_stats.iter().collect();
+        ops.sort_by(|a, b| a.0.cmp(b.0));
+        let op_label = |op: &str| format!("{{op=\"{}\"}}", escape_label_value(op));
+
+        metric("rustdb_operation_total", "counter", "Number of operations performed.",
+            ops.iter().map(|(op, s)| (op_label(op), s.count as f64)).collect());
+        metric("rustdb_operation_duration_seconds_total", "counter", "Total time spent in operations.",
+            ops.iter().map(|(op, s)| (op_label(op), s.total_duration.as_secs_f64())).collect());
+        metric("rustdb_operation_ops_per_second", "gauge", "Operation throughput between the first and last recorded call.",
+            ops.iter().map(|(op, s)| (op_label(op), s.ops_per_second)).collect());
+        metric("rustdb_operation_latency_seconds", "summary", "Operation latency quantiles.",
+            ops.iter().flat_map(|(op, s)| {
+                let op = escape_label_value(op);
+                [("0.5", s.p50_duration), ("0.95", s.p95_duration), ("0.99", s.p99_duration)]
+                    .into_iter()
+                    .map(move |(q, d)| (format!("{{op=\"{}\",quantile=\"{}\"}}", op, q), d.as_secs_f64()))
+            }).collect());
+
+        let compaction = &stats.compaction_stats;
+        metric("rustdb_compaction_total", "counter", "Number of compactions run.",
+            vec![(String::new(), compaction.count as f64)]);
+        metric("rustdb_compaction_bytes_written_total", "counter", "Bytes written by compaction.",
+            vec![(String::new(), compaction.bytes_written as f64)]);
+        metric("rustdb_compaction_duration_seconds_total", "counter", "Total time spent compacting.",
+            vec![(String::new(), compaction.total_duration.as_secs_f64())]);
+
+        out
+    }
+
+    // The metrics snapshot and the tree's stats as one JSON document, for tooling.
+    // Durations serialize as {"secs", "nanos"} objects
+    pub fn to_json(&self, db_stats: &LSMStats) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "metrics": self.get_stats(),
+            "database": db_stats,
+        }))
+    }
+
     pub fn print_live_stats(&self) {
         let stats = self.get_stats();
         
@@ -82,48 +192,296 @@ impl PerformanceMetrics {
         println!();
         
         println!("📊 Operation Statistics:");
-        println!("┌─────────────────┬─────────┬─────────────┬─────────────┬─────────────┐");
-        println!("│ Operation       │ Count   │ Total Time  │ Avg Time    │ Ops/sec     │");
-        println!("├─────────────────┼─────────┼─────────────┼─────────────┼─────────────┤");
+        println!("┌─────────────────┬─────────┬─────────────┬─────────────┬─────────────┬─────────────┬─────────────┬─────────────┐");
+        println!("│ Operation       │ Count   │ Total Time  │ Avg Time    │ p50         │ p95         │ p99         │ Ops/sec     │");
+        println!("├─────────────────┼─────────┼─────────────┼─────────────┼─────────────┼─────────────┼─────────────┼─────────────┤");
         
         for (op, stats) in &stats.operation_stats {
-            println!("│ {:<15} │ {:<7} │ {:<11.2}s │ {:<11.2}ms │ {:<11.2} │",
+            println!("│ {:<15} │ {:<7} │ {:<11.2}s │ {:<11.2}ms │ {:<11.2}ms │ {:<11.2}ms │ {:<11.2}ms │ {:<11.2} │",
                 op,
                 stats.count,
                 stats.total_duration.as_secs_f64(),
                 stats.average_duration.as_secs_f64() * 1000.0,
+                stats.p50_duration.as_secs_f64() * 1000.0,
+                stats.p95_duration.as_secs_f64() * 1000.0,
+                stats.p99_duration.as_secs_f64() * 1000.0,
                 stats.ops_per_second
             );
         }
         
-        println!("└─────────────────┴─────────┴─────────────┴─────────────┴─────────────┘");
+        println!("└─────────────────┴─────────┴─────────────┴─────────────┴─────────────┴─────────────┴─────────────┴─────────────┘");
+        println!();
+
+        let compaction = &stats.compaction_stats;
+        println!("🔧 Compactions: {} ({} files in, {} files out, {:.2} MB written, {:.2}s total)",
+            compaction.count,
+            compaction.input_files,
+            compaction.output_files,
+            compaction.bytes_written as f64 / 1024.0 / 1024.0,
+            compaction.total_duration.as_secs_f64()
+        );
         println!("\nPress Ctrl+C to exit live monitoring");
     }
 }
 
-#[derive(Debug)]
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[derive(Debug, Serialize)]
 pub struct MetricsSnapshot {
     pub uptime: Duration,
     pub memory_usage_bytes: usize,
     pub operation_stats: HashMap<String, OperationStats>,
+    pub compaction_stats: CompactionStats,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompactionStats {
+    pub count: u64,
+    pub input_files: u64,
+    pub output_files: u64,
+    pub bytes_written: u64,
+    pub total_duration: Duration,
+    pub count_by_level: HashMap<usize, u64>, // Keyed by source level
+}
+
+#[derive(Debug, Serialize)]
 pub struct OperationStats {
     pub count: u64,
     pub total_duration: Duration,
     pub average_duration: Duration,
+    pub p50_duration: Duration,
+    pub p95_duration: Duration,
+    pub p99_duration: Duration,
     pub ops_per_second: f64,
 }
 
-// Helper macro for timing operations
+// Number of power-of-two microsecond buckets; the last one catches everything above ~9 days
+const LATENCY_BUCKETS: usize = 40;
+
+// Fixed-size log2 histogram of latencies, so memory stays constant however many operations are recorded.
+// Percentiles are reported as the upper bound of the bucket they fall in (at most 2x the true value).
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS],
+    total: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; LATENCY_BUCKETS],
+            total: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, duration: Duration) {
+        // Bucket i holds [2^(i-1), 2^i) microseconds; bucket 0 holds sub-microsecond samples
+        let micros = duration.as_micros().min(u64::MAX as u128) as u64;
+        let index = (64 - micros.leading_zeros() as usize).min(LATENCY_BUCKETS - 1);
+        self.buckets[index] += 1;
+        self.total += 1;
+    }
+
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.total == 0 {
+            return Duration::ZERO;
+        }
+
+        let rank = ((percentile / 100.0) * self.total as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(1u64 << index);
+            }
+        }
+
+        Duration::from_micros(1u64 << (LATENCY_BUCKETS - 1))
+    }
+}
+
+// Time a block and record it under `$operation`, evaluating to the block's value.
+// `$metrics` may be a PerformanceMetrics, a reference to one or an Arc<PerformanceMetrics>:
+//
+//     let value = time_operation!(metrics, "get", { tree.get("key") });
 #[macro_export]
 macro_rules! time_operation {
     ($metrics:expr, $operation:expr, $code:block) => {{
-        let start = std::time::Instant::now();
+        let metrics: &$crate::metrics::PerformanceMetrics = &$metrics;
+        let start = ::std::time::Instant::now();
         let result = $code;
-        let duration = start.elapsed();
-        $metrics.record_operation($operation, duration);
+        metrics.record_operation($operation, start.elapsed());
         result
     }};
-}
\ No newline at end of file
+}
+
+// Async counterpart of time_operation!: awaits `$future` and records how long it took,
+// evaluating to its output. Only usable inside an async fn or block:
+//
+//     let value = time_operation_async!(metrics, "get", LSMTree::get_async(&tree, key))?;
+#[macro_export]
+macro_rules! time_operation_async {
+    ($metrics:expr, $operation:expr, $future:expr) => {{
+        let metrics: &$crate::metrics::PerformanceMetrics = &$metrics;
+        let start = ::std::time::Instant::now();
+        let result = $future.await;
+        metrics.record_operation($operation, start.elapsed());
+        result
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let metrics = PerformanceMetrics::new();
+
+        for _ in 0..90 {
+            metrics.record_operation("insert", Duration::from_millis(1));
+        }
+        for _ in 0..9 {
+            metrics.record_operation("insert", Duration::from_millis(10));
+        }
+        metrics.record_operation("insert", Duration::from_millis(100));
+
+        let stats = metrics.get_stats();
+        let insert = &stats.operation_stats["insert"];
+        assert_eq!(insert.count, 100);
+
+        // Each percentile is the upper bound of its power-of-two bucket
+        assert!(insert.p50_duration >= Duration::from_millis(1) && insert.p50_duration < Duration::from_millis(2));
+        assert!(insert.p95_duration >= Duration::from_millis(10) && insert.p95_duration < Duration::from_millis(20));
+        assert!(insert.p99_duration >= Duration::from_millis(10) && insert.p99_duration < Duration::from_millis(20));
+
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_millis(100));
+        assert!(histogram.percentile(100.0) >= Duration::from_millis(100));
+        assert!(histogram.percentile(100.0) < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_prometheus_export() {
+        let metrics = PerformanceMetrics::new();
+        metrics.record_operation("insert", Duration::from_millis(2));
+        metrics.record_operation("insert", Duration::from_millis(3));
+        metrics.record_operation("get", Duration::from_micros(50));
+        metrics.record_compaction(0, 4, 1, 1024, Duration::from_millis(5));
+
+        let output = metrics.to_prometheus();
+
+        assert!(output.contains("rustdb_operation_total{op=\"insert\"} 2\n"));
+        assert!(output.contains("rustdb_operation_total{op=\"get\"} 1\n"));
+        assert!(output.contains("rustdb_operation_latency_seconds{op=\"insert\",quantile=\"0.99\"}"));
+        assert!(output.contains("rustdb_compaction_total 1\n"));
+        assert!(output.contains("# TYPE rustdb_memory_usage_bytes gauge\n"));
+
+        for line in output.lines() {
+            if line.starts_with('#') {
+                assert!(line.starts_with("# HELP ") || line.starts_with("# TYPE "), "bad comment: {}", line);
+                continue;
+            }
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            assert!(series.starts_with("rustdb_"), "bad metric name: {}", line);
+            assert!(value.parse::<f64>().is_ok(), "bad value: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_json_export() {
+        use crate::engine::{LSMConfig, LSMTree};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut tree = LSMTree::with_config(LSMConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        })
+        .unwrap();
+        tree.insert("key1".to_string(), "value1".to_string()).unwrap();
+        tree.flush().unwrap();
+
+        let metrics = PerformanceMetrics::new();
+        metrics.record_operation("insert", Duration::from_millis(2));
+        metrics.update_memory_usage(4096);
+
+        let json: serde_json::Value = serde_json::from_str(&metrics.to_json(&tree.stats()).unwrap()).unwrap();
+
+        assert!(json["metrics"]["uptime"]["secs"].is_u64());
+        assert_eq!(json["metrics"]["memory_usage_bytes"], 4096);
+        assert_eq!(json["metrics"]["operation_stats"]["insert"]["count"], 1);
+        assert!(json["metrics"]["operation_stats"]["insert"]["p99_duration"].is_object());
+        assert_eq!(json["metrics"]["compaction_stats"]["count"], 0);
+        assert_eq!(json["database"]["sstable_count"], 1);
+        assert_eq!(json["database"]["levels"]["level_stats"]["0"]["file_count"], 1);
+    }
+
+    #[test]
+    fn test_time_operation_through_arc() {
+        let metrics = Arc::new(PerformanceMetrics::new());
+
+        let value = crate::time_operation!(metrics, "sum", { (1..=4).sum::<u32>() });
+        assert_eq!(value, 10);
+        let value = crate::time_operation!(&metrics, "sum", { "borrowed" });
+        assert_eq!(value, "borrowed");
+
+        assert_eq!(metrics.get_stats().operation_stats["sum"].count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_time_operation_async() {
+        let metrics = Arc::new(PerformanceMetrics::new());
+
+        let value = crate::time_operation_async!(metrics, "wait", async {
+            tokio::task::yield_now().await;
+            42
+        });
+        assert_eq!(value, 42);
+
+        let stats = metrics.get_stats().operation_stats;
+        assert_eq!(stats["wait"].count, 1);
+        assert!(!stats.contains_key("sum"));
+    }
+
+    #[test]
+    fn test_reset_clears_operations() {
+        let metrics = PerformanceMetrics::new();
+        metrics.record_operation("insert", Duration::from_millis(2));
+        metrics.record_compaction(0, 4, 1, 1024, Duration::from_millis(5));
+        std::thread::sleep(Duration::from_millis(50));
+
+        metrics.reset();
+
+        let stats = metrics.get_stats();
+        assert!(stats.operation_stats.is_empty());
+        assert_eq!(stats.compaction_stats.count, 0);
+        assert!(stats.uptime < Duration::from_millis(50));
+
+        metrics.record_operation("get", Duration::from_millis(1));
+        let stats = metrics.get_stats().operation_stats;
+        assert_eq!(stats["get"].count, 1);
+        assert!(!stats.contains_key("insert"));
+    }
+
+    #[test]
+    fn test_ops_per_second_uses_active_window() {
+        let metrics = PerformanceMetrics::new();
+        std::thread::sleep(Duration::from_millis(200)); // Idle time that must not count
+
+        for _ in 0..1000 {
+            metrics.record_operation("insert", Duration::from_micros(1));
+        }
+
+        let stats = metrics.get_stats();
+        let ops_per_second = stats.operation_stats["insert"].ops_per_second;
+        // Averaged over uptime this would be at most 1000 / 0.2s = 5000
+        assert!(stats.uptime >= Duration::from_millis(200));
+        assert!(ops_per_second > 50_000.0, "ops/sec {} reflects uptime", ops_per_second);
+    }
+}
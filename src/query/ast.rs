@@ -1,20 +1,40 @@
 use std::fmt;
 
+// How dates are written in literals and stored keys
+#[cfg(feature = "chrono")]
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     Select(SelectStatement),
     Insert(InsertStatement),
     Delete(DeleteStatement),
+    Explain(Box<Statement>), // Describe how the statement would run instead of running it
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SelectStatement {
     pub columns: Vec<String>,
+    pub aggregate: Option<Aggregate>, // Set instead of columns for COUNT/MIN/MAX
     pub table: String,
     pub where_clause: Option<WhereClause>,
+    pub order_by: Option<(String, OrderDir)>,
     pub limit: Option<usize>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aggregate {
+    Count(String), // Column name, or "*" for every row
+    Min(String),
+    Max(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderDir {
+    Asc,
+    Desc,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct InsertStatement {
     pub table: String,
@@ -42,6 +62,8 @@ pub enum Condition {
     GreaterThanOrEqual(String, Value),
     LessThanOrEqual(String, Value),
     Like(String, String),
+    IsNull(String),    // The row has no value for the column, e.g. a missing JSON field
+    IsNotNull(String),
     And(Box<Condition>, Box<Condition>),
     Or(Box<Condition>, Box<Condition>),
 }
@@ -52,15 +74,21 @@ pub enum Value {
     Number(f64),
     Boolean(bool),
     Null,
+    Param(usize), // Placeholder slot in a prepared statement, filled in by `bind`
+    #[cfg(feature = "chrono")]
+    Date(chrono::NaiveDate), // Parsed from a 'YYYY-MM-DD' string literal
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Value::String(s) => write!(f, "'{}'", s),
+            Value::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
             Value::Number(n) => write!(f, "{}", n),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Null => write!(f, "NULL"),
+            Value::Param(_) => write!(f, "?"),
+            #[cfg(feature = "chrono")]
+            Value::Date(date) => write!(f, "'{}'", date.format(DATE_FORMAT)),
         }
     }
 }
@@ -69,13 +97,17 @@ impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Statement::Select(select) => {
-                write!(f, "SELECT {} FROM {}",
-                    select.columns.join(", "),
-                    select.table
-                )?;
+                let columns = match &select.aggregate {
+                    Some(aggregate) => aggregate.to_string(),
+                    None => select.columns.join(", "),
+                };
+                write!(f, "SELECT {} FROM {}", columns, select.table)?;
                 if let Some(where_clause) = &select.where_clause {
                     write!(f, " WHERE {}", where_clause.condition)?;
                 }
+                if let Some((column, dir)) = &select.order_by {
+                    write!(f, " ORDER BY {} {}", column, dir)?;
+                }
                 if let Some(limit) = select.limit {
                     write!(f, " LIMIT {}", limit)?;
                 }
@@ -95,6 +127,26 @@ impl fmt::Display for Statement {
                 }
                 Ok(())
             }
+            Statement::Explain(statement) => write!(f, "EXPLAIN {}", statement),
+        }
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Aggregate::Count(column) => write!(f, "COUNT({})", column),
+            Aggregate::Min(column) => write!(f, "MIN({})", column),
+            Aggregate::Max(column) => write!(f, "MAX({})", column),
+        }
+    }
+}
+
+impl fmt::Display for OrderDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderDir::Asc => write!(f, "ASC"),
+            OrderDir::Desc => write!(f, "DESC"),
         }
     }
 }
@@ -108,7 +160,9 @@ impl fmt::Display for Condition {
             Condition::LessThan(col, val) => write!(f, "{} < {}", col, val),
             Condition::GreaterThanOrEqual(col, val) => write!(f, "{} >= {}", col, val),
             Condition::LessThanOrEqual(col, val) => write!(f, "{} <= {}", col, val),
-            Condition::Like(col, pattern) => write!(f, "{} LIKE '{}'", col, pattern),
+            Condition::Like(col, pattern) => write!(f, "{} LIKE '{}'", col, pattern.replace('\'', "''")),
+            Condition::IsNull(col) => write!(f, "{} IS NULL", col),
+            Condition::IsNotNull(col) => write!(f, "{} IS NOT NULL", col),
             Condition::And(left, right) => write!(f, "({} AND {})", left, right),
             Condition::Or(left, right) => write!(f, "({} OR {})", left, right),
         }
@@ -123,15 +177,17 @@ mod tests {
     fn test_select_statement_display() {
         let select = SelectStatement {
             columns: vec!["name".to_string(), "age".to_string()],
+            aggregate: None,
             table: "users".to_string(),
             where_clause: Some(WhereClause {
                 condition: Condition::Equals("id".to_string(), Value::Number(1.0)),
             }),
+            order_by: Some(("name".to_string(), OrderDir::Desc)),
             limit: Some(10),
         };
         
         let stmt = Statement::Select(select);
-        assert_eq!(stmt.to_string(), "SELECT name, age FROM users WHERE id = 1 LIMIT 10");
+        assert_eq!(stmt.to_string(), "SELECT name, age FROM users WHERE id = 1 ORDER BY name DESC LIMIT 10");
     }
 
     #[test]
@@ -0,0 +1,75 @@
+// Query result cache - remembers SELECT results so repeating a query skips the scan
+//
+// Entries are keyed by the statement's canonical text (its Display form, so spacing and
+// keyword case don't matter) and tagged with the tree's write version. Any write to the
+// tree, through the executor or not, bumps that version and so invalidates every entry.
+
+use crate::config::QueryConfig;
+use crate::query::cursor::Row;
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const DEFAULT_QUERY_CACHE_ENTRIES: usize = 256;
+
+pub struct QueryCache {
+    entries: Mutex<LruCache<String, (u64, Vec<Row>)>>, // Rows plus the write version they were read at
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl QueryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(capacity.max(1)).unwrap())),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // A cache for the configuration, or None when query caching is turned off
+    pub fn for_config(config: &QueryConfig) -> Option<Self> {
+        config.enable_query_cache.then(|| Self::new(DEFAULT_QUERY_CACHE_ENTRIES))
+    }
+
+    pub(crate) fn get(&self, statement: &str, write_version: u64) -> Option<Vec<Row>> {
+        let mut entries = self.entries.lock();
+        let cached = match entries.get(statement) {
+            Some((version, rows)) if *version == write_version => Some(rows.clone()),
+            Some(_) => {
+                entries.pop(statement);
+                None
+            }
+            None => None,
+        };
+
+        let counter = if cached.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    pub(crate) fn put(&self, statement: String, write_version: u64, rows: Vec<Row>) {
+        self.entries.lock().put(statement, (write_version, rows));
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,183 @@
+// Result cursors - hand SELECT rows out a page at a time instead of collecting them all
+//
+// The cursor pulls rows from the executor's row stream only as pages are requested, and
+// enforces the executor's row limit as it goes: past the limit it either stops quietly
+// (truncate) or fails the page that crossed it. A failed row, such as a timeout, ends it.
+
+use crate::{DbError, DbResult};
+use std::collections::HashMap;
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+pub type Row = HashMap<String, String>;
+
+pub struct ResultCursor {
+    rows: Box<dyn Iterator<Item = DbResult<Row>>>,
+    page_size: usize,
+    max_rows: Option<usize>,
+    truncate: bool,
+    rows_returned: usize,
+    done: bool,
+}
+
+impl ResultCursor {
+    pub(crate) fn new(rows: Box<dyn Iterator<Item = DbResult<Row>>>, max_rows: Option<usize>, truncate: bool) -> Self {
+        Self {
+            rows,
+            page_size: DEFAULT_PAGE_SIZE,
+            max_rows,
+            truncate,
+            rows_returned: 0,
+            done: false,
+        }
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn rows_returned(&self) -> usize {
+        self.rows_returned
+    }
+
+    // The next page of up to `page_size` rows, or None once the rows run out
+    pub fn next_page(&mut self) -> DbResult<Option<Vec<Row>>> {
+        let mut page = Vec::with_capacity(self.page_size.min(1024));
+        while page.len() < self.page_size {
+            match self.next_row()? {
+                Some(row) => page.push(row),
+                None => break,
+            }
+        }
+
+        Ok(if page.is_empty() { None } else { Some(page) })
+    }
+
+    // The next row on its own, with the same row limit as next_page
+    fn next_row(&mut self) -> DbResult<Option<Row>> {
+        if self.done {
+            return Ok(None);
+        }
+        let row = match self.rows.next() {
+            Some(Ok(row)) => row,
+            Some(Err(e)) => {
+                self.done = true;
+                return Err(e);
+            }
+            None => {
+                self.done = true;
+                return Ok(None);
+            }
+        };
+
+        if let Some(max_rows) = self.max_rows
+            && self.rows_returned >= max_rows
+        {
+            self.done = true;
+            if self.truncate {
+                return Ok(None);
+            }
+            return Err(DbError::InvalidQuery(format!(
+                "Query returned more than the maximum of {} rows",
+                max_rows
+            )));
+        }
+
+        self.rows_returned += 1;
+        Ok(Some(row))
+    }
+}
+
+impl Iterator for ResultCursor {
+    type Item = DbResult<Vec<Row>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_page().transpose()
+    }
+}
+
+// The rows of a SELECT one at a time, as QueryResult::SelectStream hands them out. Each row
+// is read from the tree when it's pulled, under the same row limit and timeout as a cursor
+pub struct SelectStream {
+    cursor: ResultCursor,
+}
+
+impl SelectStream {
+    pub(crate) fn new(cursor: ResultCursor) -> Self {
+        Self { cursor }
+    }
+
+    pub fn rows_returned(&self) -> usize {
+        self.cursor.rows_returned()
+    }
+}
+
+impl Iterator for SelectStream {
+    type Item = DbResult<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next_row().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::QueryConfig;
+    use crate::engine::{LSMConfig, LSMTree};
+    use crate::query::{QueryExecutor, SQLParser};
+    use tempfile::tempdir;
+
+    fn query_config(max_result_size: usize, truncate_results: bool) -> QueryConfig {
+        QueryConfig {
+            enable_query_cache: false,
+            max_result_size,
+            truncate_results,
+            query_timeout_secs: 30,
+        }
+    }
+
+    #[test]
+    fn test_cursor_pages_through_a_large_range() {
+        let temp_dir = tempdir().unwrap();
+        let mut lsm_tree = LSMTree::with_config(LSMConfig {
+            memtable_size_limit: 500,
+            data_dir: temp_dir.path().to_path_buf(),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        })
+        .unwrap();
+        for i in 0..2_000 {
+            lsm_tree.insert(format!("key{:05}", i), format!("value{}", i)).unwrap();
+        }
+        let statement = || SQLParser::new("SELECT * FROM t WHERE key >= 'key00250'").parse().unwrap();
+
+        let mut executor = QueryExecutor::new(&mut lsm_tree);
+        let mut cursor = executor.execute_cursor(statement()).unwrap().with_page_size(400);
+        let mut page_sizes = Vec::new();
+        let mut next_key = 250;
+        while let Some(page) = cursor.next_page().unwrap() {
+            page_sizes.push(page.len());
+            for row in page {
+                assert_eq!(row["key"], format!("key{:05}", next_key));
+                next_key += 1;
+            }
+        }
+        assert_eq!(page_sizes, vec![400, 400, 400, 400, 150]);
+        assert_eq!(cursor.rows_returned(), 1_750);
+        assert!(cursor.next_page().unwrap().is_none());
+
+        // Over max_result_size the query either fails or stops at the limit
+        let mut executor = QueryExecutor::new(&mut lsm_tree).with_query_config(&query_config(1_000, false));
+        assert!(executor.execute(statement()).is_err());
+        let pages: Vec<_> = executor.execute_cursor(statement()).unwrap().with_page_size(300).collect();
+        assert_eq!(pages.iter().filter(|page| page.is_ok()).count(), 3);
+        assert!(pages.last().unwrap().is_err());
+
+        let mut executor = QueryExecutor::new(&mut lsm_tree).with_query_config(&query_config(1_000, true));
+        let cursor = executor.execute_cursor(statement()).unwrap().with_page_size(300);
+        let page_sizes: Vec<usize> = cursor.map(|page| page.unwrap().len()).collect();
+        assert_eq!(page_sizes, vec![300, 300, 300, 100]);
+    }
+}
@@ -1,15 +1,59 @@
 use crate::query::ast::*;
-use crate::engine::LSMTree;
+use crate::query::cache::QueryCache;
+use crate::query::cursor::{ResultCursor, Row, SelectStream};
+use crate::query::prepared::PreparedStatement;
+use crate::config::QueryConfig;
+use crate::engine::{LSMTree, ReadPlan};
 use crate::{DbResult, DbError};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub struct QueryExecutor<'a> {
     lsm_tree: &'a mut LSMTree,
+    max_result_size: Option<usize>, // Most rows a SELECT may return, unlimited when None
+    truncate_results: bool,         // Past the limit, drop extra rows instead of failing
+    timeout: Option<Duration>,      // How long a scan may run, unlimited when None
+    cache: Option<Arc<QueryCache>>,
 }
 
+type RowStream = Box<dyn Iterator<Item = DbResult<(String, String)>>>;
+
 impl<'a> QueryExecutor<'a> {
     pub fn new(lsm_tree: &'a mut LSMTree) -> Self {
-        Self { lsm_tree }
+        Self {
+            lsm_tree,
+            max_result_size: None,
+            truncate_results: false,
+            timeout: None,
+            cache: None,
+        }
+    }
+
+    // Apply the configured result-size limit and timeout to every SELECT this executor runs
+    pub fn with_query_config(mut self, config: &QueryConfig) -> Self {
+        self.max_result_size = Some(config.max_result_size);
+        self.truncate_results = config.truncate_results;
+        self.timeout = Some(Duration::from_secs(config.query_timeout_secs));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // Serve repeated SELECTs from a cache shared across executors
+    pub fn with_cache(mut self, cache: Arc<QueryCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // Parse a statement once so it can be run many times with different parameters
+    pub fn prepare(sql: &str) -> DbResult<PreparedStatement> {
+        PreparedStatement::new(sql)
     }
 
     pub fn execute(&mut self, statement: Statement) -> DbResult<QueryResult> {
@@ -17,46 +61,232 @@ impl<'a> QueryExecutor<'a> {
             Statement::Select(select) => self.execute_select(select),
             Statement::Insert(insert) => self.execute_insert(insert),
             Statement::Delete(delete) => self.execute_delete(delete),
+            Statement::Explain(statement) => self.execute_explain(*statement),
+        }
+    }
+
+    // Run a SELECT and page through its rows instead of collecting them into a QueryResult
+    pub fn execute_cursor(&mut self, statement: Statement) -> DbResult<ResultCursor> {
+        // The timeout covers the whole query, including time spent between pages
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        match statement {
+            Statement::Select(select) => Ok(ResultCursor::new(
+                self.select_rows(select, deadline)?,
+                self.max_result_size,
+                self.truncate_results,
+            )),
+            _ => Err(DbError::InvalidQuery("Only SELECT statements can be read with a cursor".to_string())),
+        }
+    }
+
+    // Run a SELECT whose rows are read from the tree as the returned QueryResult::SelectStream
+    // is iterated, rather than collected up front. Bypasses the query cache
+    pub fn execute_streaming(&mut self, statement: Statement) -> DbResult<QueryResult> {
+        match statement {
+            Statement::Select(_) => Ok(QueryResult::SelectStream(SelectStream::new(self.execute_cursor(statement)?))),
+            _ => Err(DbError::InvalidQuery("Only SELECT statements can be streamed".to_string())),
         }
     }
 
     fn execute_select(&mut self, select: SelectStatement) -> DbResult<QueryResult> {
+        let cache_key = self.cache.as_ref().map(|_| Statement::Select(select.clone()).to_string());
+        let write_version = self.lsm_tree.write_version();
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key)
+            && let Some(records) = cache.get(key, write_version)
+        {
+            return Ok(QueryResult::Select(records));
+        }
+
+        let mut cursor = self.execute_cursor(Statement::Select(select))?.with_page_size(usize::MAX);
+        let mut records = Vec::new();
+        while let Some(page) = cursor.next_page()? {
+            records.extend(page);
+        }
+
+        // Only results within max_result_size are worth keeping
+        if let (Some(cache), Some(key)) = (&self.cache, cache_key)
+            && self.max_result_size.is_none_or(|max| records.len() <= max)
+        {
+            cache.put(key, write_version, records.clone());
+        }
+        Ok(QueryResult::Select(records))
+    }
+
+    // The rows a SELECT produces, pulled lazily where the statement allows it
+    fn select_rows(&self, select: SelectStatement, deadline: Option<Instant>) -> DbResult<Box<dyn Iterator<Item = DbResult<Row>>>> {
+        if let Some(aggregate) = &select.aggregate {
+            let rows = self.execute_aggregate(aggregate, select.where_clause.as_ref(), deadline)?;
+            return Ok(Box::new(rows.into_iter().map(Ok)));
+        }
+
         // For simplicity, we'll implement a basic key-value lookup
         // In a real implementation, we'd have a proper schema system
-    
-        if select.columns.contains(&"*".to_string()) {
-            // Simple key lookup
-            if let Some(where_clause) = &select.where_clause {
-                if let Some(key) = self.extract_key_from_condition(&where_clause.condition)? {
-                    match self.lsm_tree.get(&key)? {
-                        Some(value) => {
-                            let mut record = HashMap::new();
-                            record.insert("key".to_string(), key);
-                            record.insert("value".to_string(), value);
-
-                            Ok(QueryResult::Select(vec![record]))
-                        }
-                        None => Ok(QueryResult::Select(vec![])),
-                    }
-                } else {
-                    Err(DbError::InvalidOperation(
-                        "Complex WHERE clauses not supported yet".to_string()
-                    ))
+        if !select.columns.contains(&"*".to_string()) {
+            return Err(DbError::InvalidOperation(
+                "Multi-column SELECT not supported in this key-value implementation".to_string()
+            ));
+        }
+
+        // No WHERE clause is not practical for large datasets
+        let Some(where_clause) = select.where_clause else {
+            return Err(DbError::InvalidOperation(
+                "SELECT without WHERE clause is not supported (would return all data)".to_string()
+            ));
+        };
+
+        let mut rows = self.matching_rows(Some(where_clause.condition), deadline)?;
+
+        // Sorting needs every row; sort before LIMIT so it keeps the first rows of the order
+        if let Some((column, dir)) = select.order_by {
+            let mut sorted: Vec<(String, String)> = rows.collect::<DbResult<_>>()?;
+            sorted.sort_by(|a, b| {
+                let ordering = compare_column(&column, a, b);
+                match dir {
+                    OrderDir::Asc => ordering,
+                    OrderDir::Desc => ordering.reverse(),
                 }
-            } else {
-                // No WHERE clause is not practical for large datasets
-                Err(DbError::InvalidOperation(
-                    "SELECT without WHERE clause is not supported (would return all data)".to_string()
-                ))
+            });
+            rows = Box::new(sorted.into_iter().map(Ok));
+        }
+        if let Some(limit) = select.limit {
+            rows = Box::new(rows.take(limit));
+        }
+
+        Ok(Box::new(rows.map(|row| row.map(|(key, value)| {
+            let mut record = HashMap::new();
+            record.insert("key".to_string(), key);
+            record.insert("value".to_string(), value);
+            record
+        }))))
+    }
+
+    // Rows satisfying a WHERE condition (all rows without one), in key order. A scan checks
+    // the deadline at every row and ends with a timeout error once it has passed
+    fn matching_rows(&self, condition: Option<Condition>, deadline: Option<Instant>) -> DbResult<RowStream> {
+        // Read only the keys the access path covers, then check the whole condition on each row
+        let path = condition.as_ref().map_or(AccessPath::FullScan, access_path);
+        let rows: RowStream = match path {
+            AccessPath::PointLookup(key) => Box::new(self.lsm_tree.get(&key)?.map(|value| Ok((key, value))).into_iter()),
+            AccessPath::PrefixScan(_) | AccessPath::RangeScan(..) | AccessPath::FullScan => {
+                let (start, end) = path.key_bounds();
+                Box::new(self.lsm_tree.scan_stream(start.as_ref().map(String::as_str), end.as_ref().map(String::as_str))?)
             }
-        } else {
-            Err(DbError::InvalidOperation(
-                "Multi-column SELECT not supported in this key-value implementation".to_string()
-            ))
+        };
+
+        let mut expired = false;
+        Ok(Box::new(rows
+            .map_while(move |row| {
+                if expired {
+                    return None;
+                }
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    expired = true;
+                    return Some(Err(DbError::InvalidQuery("query timeout exceeded".to_string())));
+                }
+                Some(row)
+            })
+            .filter(move |row| match (row, &condition) {
+                (Ok((key, value)), Some(condition)) => row_matches(condition, key, value),
+                _ => true,
+            })))
+    }
+
+    // A single row named after the aggregate, e.g. {"COUNT(*)": "3"}. MIN/MAX of no rows is
+    // no row. Over the whole table, the key space answers these without filtering
+    fn execute_aggregate(
+        &self,
+        aggregate: &Aggregate,
+        where_clause: Option<&WhereClause>,
+        deadline: Option<Instant>,
+    ) -> DbResult<Vec<Row>> {
+        let result = match (aggregate, where_clause) {
+            (Aggregate::Count(column), None) if column == "*" => Some(self.lsm_tree.key_count()?.to_string()),
+            (Aggregate::Min(column), None) if column == "key" => self.lsm_tree.min_key()?,
+            (Aggregate::Max(column), None) if column == "key" => self.lsm_tree.max_key()?,
+            _ => {
+                let condition = where_clause.map(|w| w.condition.clone());
+                let rows: Vec<(String, String)> = self.matching_rows(condition, deadline)?.collect::<DbResult<_>>()?;
+                match aggregate {
+                    Aggregate::Count(column) => Some(
+                        rows.iter()
+                            .filter(|(key, value)| column == "*" || column_value(column, key, value).is_some())
+                            .count()
+                            .to_string(),
+                    ),
+                    Aggregate::Min(column) => rows.iter()
+                        .filter(|(key, value)| column_value(column, key, value).is_some())
+                        .min_by(|a, b| compare_column(column, a, b))
+                        .and_then(|(key, value)| column_value(column, key, value)),
+                    Aggregate::Max(column) => rows.iter()
+                        .filter(|(key, value)| column_value(column, key, value).is_some())
+                        .max_by(|a, b| compare_column(column, a, b))
+                        .and_then(|(key, value)| column_value(column, key, value)),
+                }
+            }
+        };
+
+        Ok(result.into_iter().map(|value| HashMap::from([(aggregate.to_string(), value)])).collect())
+    }
+
+    // Describe how a statement would read the tree, without running it
+    fn execute_explain(&self, statement: Statement) -> DbResult<QueryResult> {
+        let (path, aggregate) = match statement {
+            Statement::Select(select) => match (&select.aggregate, &select.where_clause) {
+                (Some(Aggregate::Min(column) | Aggregate::Max(column)), None) if column == "key" => {
+                    return Ok(QueryResult::Explain(
+                        "Key bounds of the MemTable and SSTables, no scan\nEstimated rows: 1".to_string(),
+                    ));
+                }
+                (aggregate, where_clause) => (
+                    where_clause.as_ref().map_or(AccessPath::FullScan, |w| access_path(&w.condition)),
+                    aggregate.is_some(),
+                ),
+            },
+            Statement::Delete(delete) => {
+                let key = match &delete.where_clause {
+                    Some(where_clause) => self.extract_key_from_condition(&where_clause.condition)?,
+                    None => None,
+                };
+                let key = key.ok_or_else(|| {
+                    DbError::InvalidOperation("Only DELETE by a single key is supported".to_string())
+                })?;
+                (AccessPath::PointLookup(key), false)
+            }
+            Statement::Insert(_) => {
+                return Ok(QueryResult::Explain("Insert: one write to the WAL and MemTable".to_string()));
+            }
+            Statement::Explain(_) => return Err(DbError::InvalidQuery("EXPLAIN can't be nested".to_string())),
+        };
+
+        let mut lines = vec![path.to_string()];
+        let plan = match &path {
+            AccessPath::PointLookup(key) => self.lsm_tree.plan_point_lookup(key),
+            _ => {
+                let (start, end) = path.key_bounds();
+                self.lsm_tree.plan_scan(start.as_ref().map(String::as_str), end.as_ref().map(String::as_str))
+            }
+        };
+        lines.extend(describe_read_plan(&path, &plan));
+        if aggregate {
+            lines.push("Aggregate: computed over the matching rows".to_string());
         }
+        Ok(QueryResult::Explain(lines.join("\n")))
     }
 
     fn execute_insert(&mut self, insert: InsertStatement) -> DbResult<QueryResult> {
+        let (key, value) = Self::insert_pair(insert)?;
+        self.lsm_tree.insert(key, value)?;
+        Ok(QueryResult::Insert(1))
+    }
+
+    // Insert many rows with one WAL write instead of one per statement. Every statement is
+    // checked before anything is written, so a bad row leaves the tree untouched
+    pub fn execute_bulk(&mut self, inserts: Vec<InsertStatement>) -> DbResult<QueryResult> {
+        let pairs = inserts.into_iter().map(Self::insert_pair).collect::<DbResult<Vec<_>>>()?;
+        Ok(QueryResult::Insert(self.lsm_tree.insert_batch(pairs)?))
+    }
+
+    fn insert_pair(insert: InsertStatement) -> DbResult<(String, String)> {
         // For key-value store, we expect key and value columns
         if insert.columns.len() != 2 {
             return Err(DbError::InvalidOperation(
@@ -73,6 +303,8 @@ impl<'a> QueryExecutor<'a> {
         let key = match &insert.values[0] {
             Value::String(s) => s.clone(),
             Value::Number(n) => n.to_string(),
+            #[cfg(feature = "chrono")]
+            Value::Date(date) => date.format(DATE_FORMAT).to_string(),
             _ => return Err(DbError::InvalidOperation(
                 "Key must be a string or number".to_string(),
             )),
@@ -83,10 +315,14 @@ impl<'a> QueryExecutor<'a> {
             Value::Number(n) => n.to_string(),
             Value::Boolean(b) => b.to_string(),
             Value::Null => "NULL".to_string(),
+            Value::Param(_) => return Err(DbError::InvalidQuery(
+                "Query parameter was not bound".to_string(),
+            )),
+            #[cfg(feature = "chrono")]
+            Value::Date(date) => date.format(DATE_FORMAT).to_string(),
         };
 
-        self.lsm_tree.insert(key, value)?;
-        Ok(QueryResult::Insert(1))
+        Ok((key, value))
     }
 
     fn execute_delete(&mut self, delete: DeleteStatement) -> DbResult<QueryResult> {
@@ -113,6 +349,8 @@ impl<'a> QueryExecutor<'a> {
                     match value {
                         Value::String(s) => Ok(Some(s.clone())),
                         Value::Number(n) => Ok(Some(n.to_string())),
+                        #[cfg(feature = "chrono")]
+                        Value::Date(date) => Ok(Some(date.format(DATE_FORMAT).to_string())),
                         _ => Ok(None),
                     }
                 } else {
@@ -124,15 +362,242 @@ impl<'a> QueryExecutor<'a> {
     }
 }
 
+// Which keys a WHERE condition needs read from the tree
+#[derive(Debug, Clone, PartialEq)]
+enum AccessPath {
+    PointLookup(String),
+    PrefixScan(String),
+    RangeScan(Bound<String>, Bound<String>),
+    FullScan,
+}
+
+impl AccessPath {
+    // The key range a scan reads; everything for a full scan
+    fn key_bounds(&self) -> (Bound<String>, Bound<String>) {
+        match self {
+            AccessPath::PointLookup(key) => (Bound::Included(key.clone()), Bound::Included(key.clone())),
+            AccessPath::PrefixScan(prefix) => (
+                Bound::Included(prefix.clone()),
+                LSMTree::prefix_successor(prefix).map_or(Bound::Unbounded, Bound::Excluded),
+            ),
+            AccessPath::RangeScan(start, end) => (start.clone(), end.clone()),
+            AccessPath::FullScan => (Bound::Unbounded, Bound::Unbounded),
+        }
+    }
+
+    // Both sides of an AND must hold, so whichever reads less serves both
+    fn and(self, other: AccessPath) -> AccessPath {
+        match (self, other) {
+            (AccessPath::PointLookup(key), _) | (_, AccessPath::PointLookup(key)) => AccessPath::PointLookup(key),
+            (AccessPath::FullScan, path) | (path, AccessPath::FullScan) => path,
+            (a, b) => {
+                let ((start_a, end_a), (start_b, end_b)) = (a.key_bounds(), b.key_bounds());
+                AccessPath::RangeScan(tighter_bound(start_a, start_b, Ordering::Greater), tighter_bound(end_a, end_b, Ordering::Less))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for AccessPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessPath::PointLookup(key) => write!(f, "Point lookup: key = {}", Value::String(key.clone())),
+            AccessPath::PrefixScan(prefix) => write!(f, "Prefix scan: keys starting with {}", Value::String(prefix.clone())),
+            AccessPath::RangeScan(start, end) => {
+                let mut bounds = Vec::new();
+                match start {
+                    Bound::Included(key) => bounds.push(format!("key >= {}", Value::String(key.clone()))),
+                    Bound::Excluded(key) => bounds.push(format!("key > {}", Value::String(key.clone()))),
+                    Bound::Unbounded => {}
+                }
+                match end {
+                    Bound::Included(key) => bounds.push(format!("key <= {}", Value::String(key.clone()))),
+                    Bound::Excluded(key) => bounds.push(format!("key < {}", Value::String(key.clone()))),
+                    Bound::Unbounded => {}
+                }
+                write!(f, "Range scan: {}", bounds.join(" AND "))
+            }
+            AccessPath::FullScan => write!(f, "Full scan: every key is read and checked"),
+        }
+    }
+}
+
+// Key comparisons against string (or date) literals follow the tree's key order, so they bound
+// what has to be read. Numbers compare numerically, which the key order doesn't follow
+fn access_path(condition: &Condition) -> AccessPath {
+    let key_text = |column: &str, value: &Value| match value {
+        _ if column != "key" => None,
+        Value::String(s) => Some(s.clone()),
+        #[cfg(feature = "chrono")]
+        Value::Date(date) => Some(date.format(DATE_FORMAT).to_string()),
+        _ => None,
+    };
+    let range = |column: &str, value: &Value, bounds: fn(String) -> (Bound<String>, Bound<String>)| {
+        key_text(column, value).map_or(AccessPath::FullScan, |key| {
+            let (start, end) = bounds(key);
+            AccessPath::RangeScan(start, end)
+        })
+    };
+
+    match condition {
+        Condition::Equals(column, Value::Number(n)) if column == "key" => AccessPath::PointLookup(n.to_string()),
+        Condition::Equals(column, value) => key_text(column, value).map_or(AccessPath::FullScan, AccessPath::PointLookup),
+        Condition::GreaterThan(column, value) => range(column, value, |key| (Bound::Excluded(key), Bound::Unbounded)),
+        Condition::GreaterThanOrEqual(column, value) => range(column, value, |key| (Bound::Included(key), Bound::Unbounded)),
+        Condition::LessThan(column, value) => range(column, value, |key| (Bound::Unbounded, Bound::Excluded(key))),
+        Condition::LessThanOrEqual(column, value) => range(column, value, |key| (Bound::Unbounded, Bound::Included(key))),
+        Condition::Like(column, pattern) if column == "key" => {
+            let prefix: String = pattern.chars().take_while(|&c| c != '%' && c != '_').collect();
+            if prefix.is_empty() {
+                AccessPath::FullScan
+            } else if prefix.len() == pattern.len() {
+                AccessPath::PointLookup(prefix)
+            } else {
+                AccessPath::PrefixScan(prefix)
+            }
+        }
+        Condition::And(left, right) => access_path(left).and(access_path(right)),
+        _ => AccessPath::FullScan,
+    }
+}
+
+// The narrower of two bounds on the same side of a range: `wanted` is Greater for start
+// bounds and Less for end bounds
+fn tighter_bound(a: Bound<String>, b: Bound<String>, wanted: Ordering) -> Bound<String> {
+    let key = |bound: &Bound<String>| match bound {
+        Bound::Included(key) | Bound::Excluded(key) => Some(key.clone()),
+        Bound::Unbounded => None,
+    };
+    match (key(&a), key(&b)) {
+        (None, _) => b,
+        (_, None) => a,
+        (Some(x), Some(y)) if x.cmp(&y) == wanted => a,
+        (Some(x), Some(y)) if x == y && matches!(a, Bound::Excluded(_)) => a,
+        _ => b,
+    }
+}
+
+// One line per level with files, then the row estimate
+fn describe_read_plan(path: &AccessPath, plan: &ReadPlan) -> Vec<String> {
+    let point = matches!(path, AccessPath::PointLookup(_));
+    let mut lines = vec![if point {
+        format!("MemTable: {}", if plan.memtable_entries > 0 { "holds the key" } else { "checked first" })
+    } else {
+        format!("MemTable: {} entries in range", plan.memtable_entries)
+    }];
+
+    for level in plan.levels.iter().filter(|level| level.sstables > 0) {
+        let mut line = format!("Level {}: {} of {} SSTable(s)", level.level, level.probed, level.sstables);
+        if point {
+            line.push_str(&format!(
+                " cover the key, bloom filter rules out {}",
+                level.bloom_excluded
+            ));
+        } else {
+            line.push_str(&format!(" overlap the range, {} entries", level.entries));
+        }
+        lines.push(line);
+    }
+
+    let estimate = if point {
+        let may_exist = plan.memtable_entries > 0 || plan.levels.iter().any(|level| level.probed > level.bloom_excluded);
+        format!("Estimated rows: {}", usize::from(may_exist))
+    } else {
+        format!("Estimated rows: at most {}", plan.max_rows())
+    };
+    lines.push(estimate);
+    lines
+}
+
+// A row's value for a column: `key`, `value`, or a field of a JSON object value
+fn column_value(column: &str, key: &str, value: &str) -> Option<String> {
+    match column {
+        "key" => Some(key.to_string()),
+        "value" => Some(value.to_string()),
+        field => match serde_json::from_str::<serde_json::Value>(value).ok()?.get(field)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Null => None,
+            other => Some(other.to_string()),
+        },
+    }
+}
+
+// Numbers compare numerically, anything else as text; rows missing the column sort first
+fn compare_column(column: &str, a: &(String, String), b: &(String, String)) -> Ordering {
+    let a = column_value(column, &a.0, &a.1);
+    let b = column_value(column, &b.0, &b.1);
+    match (a, b) {
+        (Some(a), Some(b)) => match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => a.cmp(&b),
+        },
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+fn compare_to_literal(actual: &str, literal: &Value) -> Option<Ordering> {
+    match literal {
+        Value::Number(n) => actual.parse::<f64>().ok()?.partial_cmp(n),
+        Value::String(s) => Some(actual.cmp(s.as_str())),
+        Value::Boolean(b) => Some(actual.cmp(if *b { "true" } else { "false" })),
+        Value::Null | Value::Param(_) => None,
+        // Dates compare by calendar order; text that isn't a date compares with the literal as
+        // written, the way it would without the chrono feature
+        #[cfg(feature = "chrono")]
+        Value::Date(date) => match chrono::NaiveDate::parse_from_str(actual, DATE_FORMAT) {
+            Ok(actual) => Some(actual.cmp(date)),
+            Err(_) => Some(actual.cmp(date.format(DATE_FORMAT).to_string().as_str())),
+        },
+    }
+}
+
+fn row_matches(condition: &Condition, key: &str, value: &str) -> bool {
+    let compare = |column: &str, literal: &Value| {
+        column_value(column, key, value).and_then(|actual| compare_to_literal(&actual, literal))
+    };
+    // Comparing with NULL is unknown rather than true or false, so those never match; use IS NULL
+    match condition {
+        Condition::Equals(column, literal) => compare(column, literal) == Some(Ordering::Equal),
+        Condition::NotEquals(column, literal) => matches!(compare(column, literal), Some(o) if o != Ordering::Equal),
+        Condition::GreaterThan(column, literal) => compare(column, literal) == Some(Ordering::Greater),
+        Condition::LessThan(column, literal) => compare(column, literal) == Some(Ordering::Less),
+        Condition::GreaterThanOrEqual(column, literal) => matches!(compare(column, literal), Some(o) if o != Ordering::Less),
+        Condition::LessThanOrEqual(column, literal) => matches!(compare(column, literal), Some(o) if o != Ordering::Greater),
+        Condition::Like(column, pattern) => column_value(column, key, value)
+            .is_some_and(|actual| like_matches(&actual.chars().collect::<Vec<_>>(), &pattern.chars().collect::<Vec<_>>())),
+        Condition::IsNull(column) => column_value(column, key, value).is_none(),
+        Condition::IsNotNull(column) => column_value(column, key, value).is_some(),
+        Condition::And(left, right) => row_matches(left, key, value) && row_matches(right, key, value),
+        Condition::Or(left, right) => row_matches(left, key, value) || row_matches(right, key, value),
+    }
+}
+
+// SQL LIKE: `%` matches any run of characters, `_` exactly one
+fn like_matches(text: &[char], pattern: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('%', rest)) => (0..=text.len()).any(|skip| like_matches(&text[skip..], rest)),
+        Some(('_', rest)) => !text.is_empty() && like_matches(&text[1..], rest),
+        Some((c, rest)) => text.first() == Some(c) && like_matches(&text[1..], rest),
+    }
+}
+
 pub enum QueryResult {
     Select(Vec<HashMap<String, String>>),
+    SelectStream(SelectStream), // From execute_streaming: rows are read as it's iterated
     Insert(usize),
     Delete(usize),
+    Explain(String), // The plan, one step per line
 }
 
 impl QueryResult {
-    pub fn format(&self) -> String {
+    // A streamed SELECT is read to the end to format it
+    pub fn format(self) -> String {
         match self {
+            QueryResult::SelectStream(rows) => match rows.collect::<DbResult<Vec<_>>>() {
+                Ok(records) => QueryResult::Select(records).format(),
+                Err(e) => format!("Error: {}", e),
+            },
             QueryResult::Select(records) => {
                 if records.is_empty() {
                     "No records found".to_string()
@@ -151,14 +616,133 @@ impl QueryResult {
             }
             QueryResult::Insert(count) => format!("Inserted {} record(s)", count),
             QueryResult::Delete(count) => format!("Deleted {} record(s)", count),
+            QueryResult::Explain(plan) => plan,
+        }
+    }
+
+    // Rows of a SELECT as CSV under a header of their columns. Fields holding commas, quotes
+    // or line breaks are quoted, with inner quotes doubled
+    pub fn to_csv(self) -> DbResult<String> {
+        let records = self.into_records(None)?;
+        records_to_csv(&records)
+    }
+
+    // Rows of a SELECT as a JSON array with one object per row
+    pub fn to_json(self) -> DbResult<String> {
+        let records = self.into_records(None)?;
+        records_to_json(&records)
+    }
+
+    // The rows of a SELECT, at most `limit` of them. Of a stream, only those rows are read
+    fn into_records(self, limit: Option<usize>) -> DbResult<Vec<Row>> {
+        let limit = limit.unwrap_or(usize::MAX);
+        match self {
+            QueryResult::Select(mut records) => {
+                records.truncate(limit);
+                Ok(records)
+            }
+            QueryResult::SelectStream(rows) => rows.take(limit).collect(),
+            _ => Err(DbError::InvalidQuery("Only SELECT results have rows to write out".to_string())),
+        }
+    }
+
+    // Render for command-line output as "table", "json" or "csv", keeping at most `limit` rows.
+    // Results other than SELECT render as their format() message
+    pub fn render(self, format: &str, limit: Option<usize>) -> DbResult<String> {
+        if !matches!(self, QueryResult::Select(_) | QueryResult::SelectStream(_)) {
+            return Ok(self.format());
+        }
+        let records = self.into_records(limit)?;
+
+        match format {
+            "json" => records_to_json(&records),
+            "csv" => records_to_csv(&records),
+            "table" => {
+                if records.is_empty() {
+                    return Ok("No records found".to_string());
+                }
+
+                let columns = record_columns(&records);
+                let widths: Vec<usize> = columns.iter()
+                    .map(|c| records.iter()
+                        .filter_map(|r| r.get(*c))
+                        .map(|v| v.len())
+                        .fold(c.len(), usize::max))
+                    .collect();
+                let border = widths.iter().map(|w| "-".repeat(w + 2)).collect::<Vec<_>>().join("+");
+                let row = |cells: Vec<&str>| cells.iter().zip(&widths)
+                    .map(|(cell, w)| format!(" {:<w$} ", cell, w = w))
+                    .collect::<Vec<_>>()
+                    .join("|");
+
+                let mut out = vec![
+                    format!("+{}+", border),
+                    format!("|{}|", row(columns.iter().map(|c| c.as_str()).collect())),
+                    format!("+{}+", border),
+                ];
+                for record in &records {
+                    let cells = columns.iter().map(|c| record.get(*c).map_or("", |v| v.as_str())).collect();
+                    out.push(format!("|{}|", row(cells)));
+                }
+                out.push(format!("+{}+", border));
+                out.push(format!("{} row(s)", records.len()));
+                Ok(out.join("\n"))
+            }
+            other => Err(DbError::InvalidOperation(format!(
+                "Unknown output format '{}', expected table, json or csv", other
+            ))),
         }
     }
 }
 
+// Columns in a stable order: key, value, then anything else alphabetically
+fn record_columns(records: &[Row]) -> Vec<&String> {
+    let mut columns: Vec<&String> = records.iter().flat_map(|r| r.keys()).collect();
+    columns.sort_by_key(|c| (c.as_str() != "key", c.as_str() != "value", c.as_str()));
+    columns.dedup();
+    columns
+}
+
+fn records_to_json(records: &[Row]) -> DbResult<String> {
+    let columns = record_columns(records);
+    let rows: Vec<serde_json::Value> = records.iter()
+        .map(|record| {
+            let object = columns.iter()
+                .filter_map(|c| record.get(*c).map(|v| (c.to_string(), serde_json::Value::String(v.clone()))))
+                .collect();
+            serde_json::Value::Object(object)
+        })
+        .collect();
+    serde_json::to_string_pretty(&rows).map_err(|e| {
+        DbError::InvalidOperation(format!("Failed to render JSON: {}", e))
+    })
+}
+
+fn records_to_csv(records: &[Row]) -> DbResult<String> {
+    let columns = record_columns(records);
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let write_err = |e: csv::Error| DbError::InvalidOperation(format!("Failed to render CSV: {}", e));
+    // No rows means no columns, and an empty header would come out as a quoted empty field
+    if !columns.is_empty() {
+        writer.write_record(&columns).map_err(write_err)?;
+    }
+    for record in records {
+        writer.write_record(columns.iter().map(|c| record.get(*c).map_or("", |v| v.as_str())))
+            .map_err(write_err)?;
+    }
+    let bytes = writer.into_inner().map_err(|e| {
+        DbError::InvalidOperation(format!("Failed to render CSV: {}", e))
+    })?;
+    String::from_utf8(bytes).map_err(|e| {
+        DbError::InvalidOperation(format!("Failed to render CSV: {}", e))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::engine::{LSMTree, LSMConfig};
+    use crate::query::SQLParser;
     use tempfile::tempdir;
 
     #[test]
@@ -203,10 +787,12 @@ mod tests {
 
         let select = SelectStatement {
             columns: vec!["*".to_string()],
+            aggregate: None,
             table: "users".to_string(),
             where_clause: Some(WhereClause {
                 condition: Condition::Equals("key".to_string(), Value::String("user1".to_string())),
             }),
+            order_by: None,
             limit: None,
         };
 
@@ -251,4 +837,483 @@ mod tests {
         // Verify the data was deleted
         assert_eq!(lsm_tree.get("user1").unwrap(), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_select_order_by() {
+        let temp_dir = tempdir().unwrap();
+        let config = LSMConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+        for (key, name, age) in [("u1", "Carol", 35), ("u2", "Alice", 9), ("u3", "Bob", 41), ("x1", "Zed", 1)] {
+            lsm_tree.insert(key.to_string(), format!(r#"{{"name": "{}", "age": {}}}"#, name, age)).unwrap();
+        }
+        let mut executor = QueryExecutor::new(&mut lsm_tree);
+
+        let mut keys = |sql: &str| match executor.execute(SQLParser::new(sql).parse().unwrap()).unwrap() {
+            QueryResult::Select(records) => records.into_iter().map(|r| r["key"].clone()).collect::<Vec<_>>(),
+            _ => panic!("Expected Select result"),
+        };
+
+        assert_eq!(keys("SELECT * FROM t WHERE key LIKE 'u%' ORDER BY key DESC"), vec!["u3", "u2", "u1"]);
+        assert_eq!(keys("SELECT * FROM t WHERE key < 'x' ORDER BY name ASC"), vec!["u2", "u3", "u1"]);
+        // Numeric fields sort as numbers, not text
+        assert_eq!(keys("SELECT * FROM t WHERE key < 'x' ORDER BY age"), vec!["u2", "u1", "u3"]);
+        // LIMIT applies after sorting
+        assert_eq!(keys("SELECT * FROM t WHERE key < 'x' ORDER BY age DESC LIMIT 2"), vec!["u3", "u1"]);
+    }
+
+    #[test]
+    fn test_select_aggregates() {
+        let temp_dir = tempdir().unwrap();
+        let config = LSMConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+        for (key, value) in [("a", "5"), ("b", "40"), ("c", "300"), ("d", "7")] {
+            lsm_tree.insert(key.to_string(), value.to_string()).unwrap();
+        }
+        lsm_tree.flush().unwrap();
+        lsm_tree.delete("a").unwrap();
+        let mut executor = QueryExecutor::new(&mut lsm_tree);
+
+        let mut run = |sql: &str| match executor.execute(SQLParser::new(sql).parse().unwrap()).unwrap() {
+            QueryResult::Select(records) => records.into_iter().flat_map(|r| r.into_values()).collect::<Vec<_>>(),
+            _ => panic!("Expected Select result"),
+        };
+
+        assert_eq!(run("SELECT COUNT(*) FROM t"), vec!["3"]);
+        // The tombstoned boundary key isn't reported
+        assert_eq!(run("SELECT MIN(key) FROM t"), vec!["b"]);
+        assert_eq!(run("SELECT MAX(key) FROM t"), vec!["d"]);
+        assert_eq!(run("SELECT COUNT(*) FROM t WHERE key > 'b'"), vec!["2"]);
+        assert_eq!(run("SELECT MAX(value) FROM t WHERE key > 'a'"), vec!["300"]);
+        assert_eq!(run("SELECT MIN(value) FROM t WHERE key > 'a'"), vec!["7"]);
+        assert!(run("SELECT MIN(key) FROM t WHERE key > 'z'").is_empty());
+    }
+
+    #[test]
+    fn test_query_timeout() {
+        let temp_dir = tempdir().unwrap();
+        let config = LSMConfig {
+            memtable_size_limit: 100_000,
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+        for i in 0..20_000 {
+            lsm_tree.insert(format!("key{:05}", i), format!("value{}", i)).unwrap();
+        }
+        let scan = || SQLParser::new("SELECT * FROM t WHERE value LIKE '%9%9%9%'").parse().unwrap();
+
+        let mut executor = QueryExecutor::new(&mut lsm_tree).with_timeout(Duration::from_nanos(1));
+        match executor.execute(scan()) {
+            Err(DbError::InvalidQuery(message)) => assert_eq!(message, "query timeout exceeded"),
+            _ => panic!("Expected the scan to time out"),
+        }
+        let count = SQLParser::new("SELECT COUNT(key) FROM t").parse().unwrap();
+        assert!(executor.execute(count).is_err());
+
+        let mut executor = QueryExecutor::new(&mut lsm_tree).with_timeout(Duration::from_secs(30));
+        match executor.execute(scan()).unwrap() {
+            QueryResult::Select(records) => assert!(!records.is_empty()),
+            _ => panic!("Expected Select result"),
+        }
+    }
+
+    #[test]
+    fn test_query_cache() {
+        let temp_dir = tempdir().unwrap();
+        let config = LSMConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: false,
+            ..LSMConfig::default()
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+        lsm_tree.insert("user1".to_string(), "Alice".to_string()).unwrap();
+        let cache = Arc::new(QueryCache::new(16));
+        let count_rows = |lsm_tree: &mut LSMTree, sql: &str| {
+            let statement = SQLParser::new(sql).parse().unwrap();
+            match QueryExecutor::new(lsm_tree).with_cache(cache.clone()).execute(statement).unwrap() {
+                QueryResult::Select(records) => records.len(),
+                _ => panic!("Expected Select result"),
+            }
+        };
+
+        assert_eq!(count_rows(&mut lsm_tree, "SELECT * FROM t WHERE key >= 'user'"), 1);
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+        // Same statement with different spacing and keyword case
+        assert_eq!(count_rows(&mut lsm_tree, "select *   from t where key >= 'user'"), 1);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // An INSERT through the executor invalidates the entry...
+        let insert = SQLParser::new("INSERT INTO t (key, value) VALUES ('user2', 'Bob')").parse().unwrap();
+        QueryExecutor::new(&mut lsm_tree).with_cache(cache.clone()).execute(insert).unwrap();
+        assert_eq!(count_rows(&mut lsm_tree, "SELECT * FROM t WHERE key >= 'user'"), 2);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+        // ...and so does a write made directly on the tree
+        lsm_tree.delete("user1").unwrap();
+        assert_eq!(count_rows(&mut lsm_tree, "SELECT * FROM t WHERE key >= 'user'"), 1);
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+        assert_eq!(count_rows(&mut lsm_tree, "SELECT * FROM t WHERE key >= 'user'"), 1);
+        assert_eq!(cache.hits(), 2);
+    }
+
+    #[test]
+    fn test_execute_bulk() {
+        let temp_dir = tempdir().unwrap();
+        let config = LSMConfig {
+            memtable_size_limit: 100_000,
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: true,
+            ..LSMConfig::default()
+        };
+        let insert = |i: usize| InsertStatement {
+            table: "t".to_string(),
+            columns: vec!["key".to_string(), "value".to_string()],
+            values: vec![Value::String(format!("key{:04}", i)), Value::Number(i as f64)],
+        };
+
+        let mut lsm_tree = LSMTree::with_config(config).unwrap();
+        let flushes_before = lsm_tree.wal_flush_count();
+        let result = QueryExecutor::new(&mut lsm_tree).execute_bulk((0..1000).map(insert).collect()).unwrap();
+        assert!(matches!(result, QueryResult::Insert(1000)));
+        let bulk_flushes = lsm_tree.wal_flush_count() - flushes_before;
+        for i in 0..1000 {
+            assert_eq!(lsm_tree.get(&format!("key{:04}", i)).unwrap(), Some(i.to_string()));
+        }
+
+        // One statement at a time pays for a WAL write per row
+        let flushes_before = lsm_tree.wal_flush_count();
+        let mut executor = QueryExecutor::new(&mut lsm_tree);
+        for i in 1000..1100 {
+            executor.execute(Statement::Insert(insert(i))).unwrap();
+        }
+        let single_flushes = lsm_tree.wal_flush_count() - flushes_before;
+        assert!(bulk_flushes < single_flushes, "bulk {} vs single {}", bulk_flushes, single_flushes);
+
+        // A bad row rejects the whole batch
+        let mut bad = insert(5000);
+        bad.values.pop();
+        assert!(QueryExecutor::new(&mut lsm_tree).execute_bulk(vec![insert(4000), bad]).is_err());
+        assert_eq!(lsm_tree.get("key4000").unwrap(), None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_date_range_conditions() {
+        let temp_dir = tempdir().unwrap();
+        let mut lsm_tree = LSMTree::with_config(LSMConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: false,
+            ..LSMConfig::default()
+        })
+        .unwrap();
+        for key in ["2023-12-31", "2024-01-01", "2024-01-15", "2024-02-01", "2024-10-05", "notes"] {
+            lsm_tree.insert(key.to_string(), format!("event on {}", key)).unwrap();
+        }
+        let mut executor = QueryExecutor::new(&mut lsm_tree);
+
+        let mut keys = |sql: &str| match executor.execute(SQLParser::new(sql).parse().unwrap()).unwrap() {
+            QueryResult::Select(records) => records.into_iter().map(|r| r["key"].clone()).collect::<Vec<_>>(),
+            _ => panic!("Expected SELECT result"),
+        };
+
+        assert_eq!(keys("SELECT * FROM t WHERE key < '2024-01-01'"), vec!["2023-12-31"]);
+        assert_eq!(
+            keys("SELECT * FROM t WHERE key >= '2024-01-01' AND key < '2024-02-01'"),
+            vec!["2024-01-01", "2024-01-15"]
+        );
+        assert_eq!(keys("SELECT * FROM t WHERE key > '2024-02-01'"), vec!["2024-10-05", "notes"]);
+        assert_eq!(keys("SELECT * FROM t WHERE key = '2024-01-15'"), vec!["2024-01-15"]);
+        // Keys that aren't dates compare as text, as they would without the feature
+        assert_eq!(
+            keys("SELECT * FROM t WHERE key != '2024-01-15'"),
+            vec!["2023-12-31", "2024-01-01", "2024-02-01", "2024-10-05", "notes"]
+        );
+        assert_eq!(keys("SELECT * FROM t WHERE key > '2024-10-05'"), vec!["notes"]);
+        assert_eq!(keys("SELECT * FROM t WHERE key = 'notes'"), vec!["notes"]);
+    }
+
+    #[test]
+    fn test_null_comparisons() {
+        let temp_dir = tempdir().unwrap();
+        let mut lsm_tree = LSMTree::with_config(LSMConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: false,
+            ..LSMConfig::default()
+        })
+        .unwrap();
+        lsm_tree.insert("user1".to_string(), r#"{"email": "a@example.com"}"#.to_string()).unwrap();
+        lsm_tree.insert("user2".to_string(), r#"{"email": null}"#.to_string()).unwrap();
+        lsm_tree.insert("user3".to_string(), r#"{"name": "c"}"#.to_string()).unwrap();
+        let mut executor = QueryExecutor::new(&mut lsm_tree);
+
+        let mut keys = |sql: &str| match executor.execute(SQLParser::new(sql).parse().unwrap()).unwrap() {
+            QueryResult::Select(records) => records.into_iter().map(|r| r["key"].clone()).collect::<Vec<_>>(),
+            _ => panic!("Expected SELECT result"),
+        };
+
+        // A comparison with NULL is never true, whichever way round
+        assert!(keys("SELECT * FROM t WHERE key = NULL").is_empty());
+        assert!(keys("SELECT * FROM t WHERE key != NULL").is_empty());
+        assert!(keys("SELECT * FROM t WHERE email = NULL").is_empty());
+
+        assert_eq!(keys("SELECT * FROM t WHERE key IS NOT NULL"), vec!["user1", "user2", "user3"]);
+        assert!(keys("SELECT * FROM t WHERE key IS NULL").is_empty());
+        assert_eq!(keys("SELECT * FROM t WHERE email IS NULL"), vec!["user2", "user3"]);
+        assert_eq!(keys("SELECT * FROM t WHERE email IS NOT NULL"), vec!["user1"]);
+    }
+
+    #[test]
+    fn test_explain_reports_access_path() {
+        let temp_dir = tempdir().unwrap();
+        let mut lsm_tree = LSMTree::with_config(LSMConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: false,
+            background_compaction: false,
+            ..LSMConfig::default()
+        })
+        .unwrap();
+        for i in 0..20 {
+            lsm_tree.insert(format!("user{:02}", i), format!("name{}", i)).unwrap();
+        }
+        lsm_tree.flush().unwrap();
+        lsm_tree.insert("x".to_string(), "in memory".to_string()).unwrap();
+        let mut executor = QueryExecutor::new(&mut lsm_tree);
+
+        let mut explain = |sql: &str| match executor.execute(SQLParser::new(sql).parse().unwrap()).unwrap() {
+            QueryResult::Explain(plan) => plan,
+            _ => panic!("Expected EXPLAIN result"),
+        };
+
+        let plan = explain("EXPLAIN SELECT * FROM t WHERE key = 'x'");
+        assert!(plan.starts_with("Point lookup: key = 'x'"), "{}", plan);
+        assert!(plan.contains("MemTable: holds the key"), "{}", plan);
+        assert!(plan.ends_with("Estimated rows: 1"), "{}", plan);
+
+        let plan = explain("EXPLAIN SELECT * FROM t WHERE key = 'user05'");
+        assert!(plan.contains("Level 0: 1 of 1 SSTable(s) cover the key"), "{}", plan);
+
+        assert!(explain("EXPLAIN SELECT * FROM t WHERE key LIKE '%05'").starts_with("Full scan"));
+        assert!(explain("EXPLAIN SELECT * FROM t WHERE value = 'name5'").starts_with("Full scan"));
+        assert!(explain("EXPLAIN SELECT * FROM t WHERE key LIKE 'user1%'").starts_with("Prefix scan: keys starting with 'user1'"));
+
+        let plan = explain("EXPLAIN SELECT COUNT(*) FROM t WHERE key >= 'user05' AND key < 'user10' AND value != 'a'");
+        assert!(plan.starts_with("Range scan: key >= 'user05' AND key < 'user10'"), "{}", plan);
+        assert!(plan.contains("Level 0: 1 of 1 SSTable(s) overlap the range, 20 entries"), "{}", plan);
+
+        // Nothing runs: the key is still there after explaining a DELETE
+        assert!(explain("EXPLAIN DELETE FROM t WHERE key = 'x'").starts_with("Point lookup"));
+        drop(executor);
+        assert_eq!(lsm_tree.get("x").unwrap(), Some("in memory".to_string()));
+    }
+
+    #[test]
+    fn test_key_range_conditions_match_full_scan() {
+        let temp_dir = tempdir().unwrap();
+        let mut lsm_tree = LSMTree::with_config(LSMConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            enable_wal: false,
+            background_compaction: false,
+            memtable_size_limit: 16,
+            ..LSMConfig::default()
+        })
+        .unwrap();
+        for i in 0..100 {
+            lsm_tree.insert(format!("k{:02}", i), format!("{}", i % 7)).unwrap();
+        }
+        for i in (0..100).step_by(9) {
+            lsm_tree.delete(&format!("k{:02}", i)).unwrap();
+        }
+        let rows: Vec<(String, String)> = lsm_tree.iter().unwrap().collect();
+        let mut executor = QueryExecutor::new(&mut lsm_tree);
+
+        for where_clause in [
+            "key > 'k42'",
+            "key >= 'k42' AND key <= 'k58'",
+            "key < 'k50' AND key > 'k50'",
+            "key >= 'k10' AND key >= 'k20' AND key < 'k30' AND key < 'k25'",
+            "key LIKE 'k4%'",
+            "key LIKE 'k4%' AND key > 'k45'",
+            "key LIKE 'k4_'",
+            "key = 'k45' AND value = '3'",
+            "key = 'k46' AND value = '3'",
+            "key > 'k90' OR key < 'k03'",
+            "key > 5 AND key < 'k20'",
+        ] {
+            let condition = match SQLParser::new(&format!("SELECT * FROM t WHERE {}", where_clause)).parse().unwrap() {
+                Statement::Select(select) => select.where_clause.unwrap().condition,
+                _ => unreachable!(),
+            };
+            let expected: Vec<String> = rows.iter()
+                .filter(|(key, value)| row_matches(&condition, key, value))
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            let select = Statement::Select(SelectStatement {
+                columns: vec!["*".to_string()],
+                aggregate: None,
+                table: "t".to_string(),
+                where_clause: Some(WhereClause { condition }),
+                order_by: None,
+                limit: None,
+            });
+            let actual: Vec<String> = match executor.execute(select).unwrap() {
+                QueryResult::Select(records) => records.into_iter().map(|r| r["key"].clone()).collect(),
+                _ => panic!("Expected SELECT result"),
+            };
+            assert_eq!(actual, expected, "WHERE {}", where_clause);
+        }
+    }
+
+    #[test]
+    fn test_execute_streaming_reads_rows_lazily() {
+        let temp_dir = tempdir().unwrap();
+        let mut lsm_tree = LSMTree::with_config(LSMConfig {
+            memtable_size_limit: 100,
+            data_dir: temp_dir.path().to_path_buf(),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        })
+        .unwrap();
+        for i in 0..1_000 {
+            lsm_tree.insert(format!("key{:04}", i), format!("value{}", i)).unwrap();
+        }
+        for i in (0..1_000).step_by(10) {
+            lsm_tree.delete(&format!("key{:04}", i)).unwrap();
+        }
+        let statement = |sql: &str| SQLParser::new(sql).parse().unwrap();
+        let range = "SELECT * FROM t WHERE key >= 'key0100' AND key < 'key0900'";
+        let mut executor = QueryExecutor::new(&mut lsm_tree).with_timeout(Duration::from_millis(500));
+
+        let QueryResult::Select(collected) = executor.execute(statement(range)).unwrap() else {
+            panic!("Expected SELECT result");
+        };
+        assert_eq!(collected.len(), 720);
+
+        // Each row is read, and checked against the timeout, only when it's pulled
+        let QueryResult::SelectStream(mut rows) = executor.execute_streaming(statement(range)).unwrap() else {
+            panic!("Expected a streamed SELECT");
+        };
+        for i in (100..500).filter(|i| i % 10 != 0) {
+            assert_eq!(rows.next().unwrap().unwrap()["key"], format!("key{:04}", i));
+        }
+        assert_eq!(rows.rows_returned(), 360);
+        std::thread::sleep(Duration::from_millis(600));
+        assert!(rows.next().unwrap().is_err());
+        assert!(rows.next().is_none());
+
+        // Rendering a few rows reads only those
+        let csv = executor.execute_streaming(statement(range)).unwrap().render("csv", Some(2)).unwrap();
+        assert_eq!(csv, "key,value\nkey0101,value101\nkey0102,value102\n");
+
+        // A stream totals the same as the collected result
+        let prefix = "SELECT * FROM t WHERE key LIKE 'key06%'";
+        let QueryResult::Select(collected) = executor.execute(statement(prefix)).unwrap() else {
+            panic!("Expected SELECT result");
+        };
+        let QueryResult::SelectStream(streamed) = executor.execute_streaming(statement(prefix)).unwrap() else {
+            panic!("Expected a streamed SELECT");
+        };
+        let streamed: Vec<_> = streamed.collect::<DbResult<_>>().unwrap();
+        assert_eq!(streamed.len(), 90);
+        assert_eq!(streamed, collected);
+        assert_eq!(executor.execute_streaming(statement(prefix)).unwrap().format().lines().count(), 90);
+
+        assert!(executor.execute_streaming(statement("DELETE FROM t WHERE key = 'key0001'")).is_err());
+    }
+
+    fn select_result() -> QueryResult {
+        let mut first = HashMap::new();
+        first.insert("key".to_string(), "user1".to_string());
+        first.insert("value".to_string(), "Alice".to_string());
+        let mut second = HashMap::new();
+        second.insert("key".to_string(), "user2".to_string());
+        second.insert("value".to_string(), "Bob, Jr.".to_string());
+        QueryResult::Select(vec![first, second])
+    }
+
+    #[test]
+    fn test_to_csv_and_to_json_escape_values() {
+        let awkward = [
+            ("plain", "Alice"),
+            ("comma", "Bob, Jr."),
+            ("quotes", "say \"hi\", then \"\"bye\"\""),
+            ("newline", "line one\nline two\r\nline three"),
+            ("colon", "key: value"),
+            ("empty", ""),
+        ];
+        let result = || QueryResult::Select(awkward.iter()
+            .map(|(key, value)| HashMap::from([
+                ("key".to_string(), key.to_string()),
+                ("value".to_string(), value.to_string()),
+            ]))
+            .collect());
+
+        // Reading the CSV back gives every value unchanged
+        let csv = result().to_csv().unwrap();
+        assert!(csv.contains("\"say \"\"hi\"\", then \"\"\"\"bye\"\"\"\"\""));
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers().unwrap(), vec!["key", "value"]);
+        let rows: Vec<(String, String)> = reader.records()
+            .map(|record| {
+                let record = record.unwrap();
+                (record[0].to_string(), record[1].to_string())
+            })
+            .collect();
+        assert_eq!(rows, awkward.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<_>>());
+
+        // As does parsing the JSON
+        let parsed: serde_json::Value = serde_json::from_str(&result().to_json().unwrap()).unwrap();
+        let expected: Vec<serde_json::Value> = awkward.iter()
+            .map(|(key, value)| serde_json::json!({"key": key, "value": value}))
+            .collect();
+        assert_eq!(parsed, serde_json::Value::Array(expected));
+
+        // Empty results are still valid output, and other results have no rows to write
+        assert_eq!(QueryResult::Select(Vec::new()).to_json().unwrap(), "[]");
+        assert_eq!(QueryResult::Select(Vec::new()).to_csv().unwrap(), "");
+        assert!(QueryResult::Insert(1).to_csv().is_err());
+        assert!(QueryResult::Delete(1).to_json().is_err());
+    }
+
+    #[test]
+    fn test_render_json() {
+        let rendered = select_result().render("json", None).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(parsed, serde_json::json!([
+            {"key": "user1", "value": "Alice"},
+            {"key": "user2", "value": "Bob, Jr."},
+        ]));
+
+        let limited: serde_json::Value = serde_json::from_str(&select_result().render("json", Some(1)).unwrap()).unwrap();
+        assert_eq!(limited.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_render_csv_and_table() {
+        let csv = select_result().render("csv", None).unwrap();
+        assert_eq!(csv, "key,value\nuser1,Alice\nuser2,\"Bob, Jr.\"\n");
+
+        let table = select_result().render("table", None).unwrap();
+        assert!(table.contains("| key   | value    |"));
+        assert!(table.contains("| user2 | Bob, Jr. |"));
+        assert!(table.ends_with("2 row(s)"));
+
+        assert!(select_result().render("xml", None).is_err());
+        assert_eq!(QueryResult::Insert(1).render("json", None).unwrap(), "Inserted 1 record(s)");
+    }
+}
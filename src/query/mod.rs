@@ -1,8 +1,16 @@
 pub mod ast;
 pub mod parser;
 pub mod executor;
+pub mod cursor;
+pub mod cache;
+pub mod params;
+pub mod prepared;
 
 pub use ast::*;
 pub use parser::*;
 pub use executor::*;
+pub use cursor::{ResultCursor, SelectStream};
+pub use cache::QueryCache;
+pub use params::SqlLiteral;
+pub use prepared::PreparedStatement;
 
@@ -0,0 +1,59 @@
+// Query parameters - render Rust values as SQL literals so `query!` can splice them into a
+// statement. Strings are quoted with embedded quotes doubled, so a value can never end the
+// literal early and inject SQL of its own
+
+pub trait SqlLiteral {
+    fn to_sql_literal(&self) -> String;
+}
+
+impl SqlLiteral for str {
+    fn to_sql_literal(&self) -> String {
+        format!("'{}'", self.replace('\'', "''"))
+    }
+}
+
+impl SqlLiteral for String {
+    fn to_sql_literal(&self) -> String {
+        self.as_str().to_sql_literal()
+    }
+}
+
+impl SqlLiteral for bool {
+    fn to_sql_literal(&self) -> String {
+        if *self { "TRUE" } else { "FALSE" }.to_string()
+    }
+}
+
+macro_rules! numeric_literal {
+    ($($ty:ty),*) => {
+        $(impl SqlLiteral for $ty {
+            fn to_sql_literal(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+numeric_literal!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+#[cfg(feature = "chrono")]
+impl SqlLiteral for chrono::NaiveDate {
+    fn to_sql_literal(&self) -> String {
+        format!("'{}'", self.format(crate::query::ast::DATE_FORMAT))
+    }
+}
+
+impl<T: SqlLiteral + ?Sized> SqlLiteral for &T {
+    fn to_sql_literal(&self) -> String {
+        (**self).to_sql_literal()
+    }
+}
+
+impl<T: SqlLiteral> SqlLiteral for Option<T> {
+    fn to_sql_literal(&self) -> String {
+        match self {
+            Some(value) => value.to_sql_literal(),
+            None => "NULL".to_string(),
+        }
+    }
+}
@@ -4,12 +4,13 @@ use crate::{DbResult, DbError};
 pub struct SQLParser {
     tokens: Vec<String>,
     position: usize,
+    parameters: Vec<Option<String>>, // One slot per placeholder: `?` is unnamed, `:name` named
 }
 
 impl SQLParser {
     pub fn new(sql: &str) -> Self {
         let tokens = tokenize(sql);
-        Self { tokens, position: 0 }
+        Self { tokens, position: 0, parameters: Vec::new() }
     }
 
     pub fn parse(&mut self) -> DbResult<Statement> {
@@ -17,18 +18,36 @@ impl SQLParser {
             return Err(DbError::InvalidQuery("Empty SQL statement".to_string()));
         }
 
-        match self.tokens[0].to_uppercase().as_str() {
+        if self.tokens[0].eq_ignore_ascii_case("EXPLAIN") {
+            self.advance();
+            return match self.peek() {
+                Some(_) => Ok(Statement::Explain(Box::new(self.parse_statement()?))),
+                None => Err(DbError::InvalidQuery("EXPLAIN needs a statement".to_string())),
+            };
+        }
+        self.parse_statement()
+    }
+
+    fn parse_statement(&mut self) -> DbResult<Statement> {
+        let keyword = self.peek().cloned().unwrap_or_default();
+        match keyword.to_uppercase().as_str() {
             "SELECT" => self.parse_select(),
             "INSERT" => self.parse_insert(),
             "DELETE" => self.parse_delete(),
-            _ => Err(DbError::InvalidQuery(format!("Unsupported statement: {}", self.tokens[0]))),
+            _ => Err(DbError::InvalidQuery(format!("Unsupported statement: {}", keyword))),
         }
     }
 
+    // Placeholder slots found by `parse`, in order. A `:name` used twice shares one slot
+    pub fn parameters(&self) -> &[Option<String>] {
+        &self.parameters
+    }
+
     fn parse_select(&mut self) -> DbResult<Statement> {
         self.consume("SELECT")?;
 
-        let columns = self.parse_columns()?;
+        let aggregate = self.parse_aggregate()?;
+        let columns = if aggregate.is_some() { Vec::new() } else { self.parse_columns()? };
 
         self.consume("FROM")?;
         let table = self.consume_identifier()?;
@@ -42,6 +61,26 @@ impl SQLParser {
             None
         };
 
+        let order_by = if self.peek().map(|s| s.to_uppercase()) == Some("ORDER".to_string()) {
+            self.consume("ORDER")?;
+            self.consume("BY")?;
+            let column = self.consume_identifier()?;
+            let dir = match self.peek().map(|s| s.to_uppercase()).as_deref() {
+                Some("ASC") => {
+                    self.advance();
+                    OrderDir::Asc
+                }
+                Some("DESC") => {
+                    self.advance();
+                    OrderDir::Desc
+                }
+                _ => OrderDir::Asc,
+            };
+            Some((column, dir))
+        } else {
+            None
+        };
+
         let limit = if self.peek().map(|s| s.to_uppercase()) == Some("LIMIT".to_string()) {
             self.consume("LIMIT")?;
             Some(self.consume_number()? as usize)
@@ -51,12 +90,38 @@ impl SQLParser {
 
         Ok(Statement::Select(SelectStatement {
             columns,
+            aggregate,
             table,
             where_clause,
+            order_by,
             limit,
         }))
     }
 
+    // COUNT(col), MIN(col) or MAX(col) in place of a column list
+    fn parse_aggregate(&mut self) -> DbResult<Option<Aggregate>> {
+        let function = match self.peek() {
+            Some(token) => token.to_uppercase(),
+            None => return Ok(None),
+        };
+        if !matches!(function.as_str(), "COUNT" | "MIN" | "MAX")
+            || self.tokens.get(self.position + 1).map(String::as_str) != Some("(")
+        {
+            return Ok(None);
+        }
+
+        self.advance();
+        self.consume("(")?;
+        let column = self.consume_identifier()?;
+        self.consume(")")?;
+
+        Ok(Some(match function.as_str() {
+            "COUNT" => Aggregate::Count(column),
+            "MIN" => Aggregate::Min(column),
+            _ => Aggregate::Max(column),
+        }))
+    }
+
     fn parse_insert(&mut self) -> DbResult<Statement> {
         self.consume("INSERT")?;
         self.consume("INTO")?;
@@ -148,6 +213,17 @@ impl SQLParser {
     fn parse_comparison(&mut self) -> DbResult<Condition> {
         let column = self.consume_identifier()?;
         let operator = self.consume_identifier()?;
+
+        // `IS [NOT] NULL` takes no value
+        if operator.eq_ignore_ascii_case("IS") {
+            let negated = self.peek().is_some_and(|t| t.eq_ignore_ascii_case("NOT"));
+            if negated {
+                self.advance();
+            }
+            self.consume("NULL")?;
+            return Ok(if negated { Condition::IsNotNull(column) } else { Condition::IsNull(column) });
+        }
+
         let value = self.parse_value()?;
 
         match operator.to_uppercase().as_str() {
@@ -173,9 +249,28 @@ impl SQLParser {
             DbError::InvalidOperation("Expected value".to_string())
         })?;
 
-        if token.starts_with('\'') && token.ends_with('\'') {
-            let value = token[1..token.len()-1].to_string();
+        if token == "?" {
+            self.advance();
+            self.parameters.push(None);
+            Ok(Value::Param(self.parameters.len() - 1))
+        } else if let Some(name) = token.strip_prefix(':').filter(|name| !name.is_empty()) {
+            let name = name.to_string();
             self.advance();
+            let slot = match self.parameters.iter().position(|p| p.as_deref() == Some(name.as_str())) {
+                Some(slot) => slot,
+                None => {
+                    self.parameters.push(Some(name));
+                    self.parameters.len() - 1
+                }
+            };
+            Ok(Value::Param(slot))
+        } else if token.len() >= 2 && token.starts_with('\'') && token.ends_with('\'') {
+            let value = token[1..token.len()-1].replace("''", "'");
+            self.advance();
+            #[cfg(feature = "chrono")]
+            if let Some(date) = parse_date(&value) {
+                return Ok(Value::Date(date));
+            }
             Ok(Value::String(value))
         } else if token.parse::<f64>().is_ok() {
             let value = token.parse::<f64>().unwrap();
@@ -242,6 +337,15 @@ impl SQLParser {
     }
 }
 
+// Only the full 'YYYY-MM-DD' form is a date, so the literal prints back exactly as written
+#[cfg(feature = "chrono")]
+fn parse_date(text: &str) -> Option<chrono::NaiveDate> {
+    if text.len() != 10 {
+        return None;
+    }
+    chrono::NaiveDate::parse_from_str(text, DATE_FORMAT).ok()
+}
+
 fn tokenize(sql: &str) -> Vec<String> {
     let mut tokens = Vec::new();
     let mut current_token = String::new();
@@ -251,7 +355,11 @@ fn tokenize(sql: &str) -> Vec<String> {
     while let Some(ch) = chars.next() {
         match ch {
             '\'' => {
-                if in_string {
+                if in_string && chars.peek() == Some(&'\'') {
+                    // A doubled quote inside a string is an escaped quote
+                    current_token.push(ch);
+                    current_token.push(chars.next().unwrap());
+                } else if in_string {
                     current_token.push(ch);
                     tokens.push(current_token.clone());
                     current_token.clear();
@@ -275,7 +383,7 @@ fn tokenize(sql: &str) -> Vec<String> {
                     }
                 }
             }
-            ',' | '(' | ')' | ';' => {
+            ',' | '(' | ')' | ';' | '?' => {
                 if in_string {
                     current_token.push(ch);
                 } else {
@@ -336,6 +444,80 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_quoted_string_escapes() {
+        let sql = "SELECT * FROM t WHERE key = 'it''s; x = ''y'''";
+        let mut parser = SQLParser::new(sql);
+        match parser.parse().unwrap() {
+            Statement::Select(select) => assert_eq!(
+                select.where_clause.unwrap().condition,
+                Condition::Equals("key".to_string(), Value::String("it's; x = 'y'".to_string()))
+            ),
+            _ => panic!("Expected SELECT statement"),
+        }
+    }
+
+    #[test]
+    fn test_parse_aggregates() {
+        let parse_aggregate = |sql: &str| match SQLParser::new(sql).parse().unwrap() {
+            Statement::Select(select) => select.aggregate,
+            _ => panic!("Expected SELECT statement"),
+        };
+
+        assert_eq!(parse_aggregate("SELECT COUNT(*) FROM t"), Some(Aggregate::Count("*".to_string())));
+        assert_eq!(parse_aggregate("SELECT min(key) FROM t"), Some(Aggregate::Min("key".to_string())));
+        assert_eq!(parse_aggregate("SELECT MAX(key) FROM t WHERE key < 'm'"), Some(Aggregate::Max("key".to_string())));
+        assert_eq!(parse_aggregate("SELECT * FROM t WHERE key = 'count'"), None);
+        assert_eq!(SQLParser::new("SELECT MAX(key) FROM t").parse().unwrap().to_string(), "SELECT MAX(key) FROM t");
+    }
+
+    #[test]
+    fn test_parse_order_by() {
+        let parse_order = |sql: &str| match SQLParser::new(sql).parse().unwrap() {
+            Statement::Select(select) => (select.order_by, select.limit),
+            _ => panic!("Expected SELECT statement"),
+        };
+
+        assert_eq!(
+            parse_order("SELECT * FROM t WHERE key >= 'a' ORDER BY value DESC LIMIT 2"),
+            (Some(("value".to_string(), OrderDir::Desc)), Some(2))
+        );
+        assert_eq!(
+            parse_order("SELECT * FROM t WHERE key >= 'a' order by key asc"),
+            (Some(("key".to_string(), OrderDir::Asc)), None)
+        );
+        assert_eq!(parse_order("SELECT * FROM t WHERE key >= 'a' ORDER BY key").0, Some(("key".to_string(), OrderDir::Asc)));
+        assert!(SQLParser::new("SELECT * FROM t WHERE key >= 'a' ORDER key").parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_is_null() {
+        let condition = |sql: &str| match SQLParser::new(sql).parse().unwrap() {
+            Statement::Select(select) => select.where_clause.unwrap().condition,
+            _ => panic!("Expected SELECT statement"),
+        };
+
+        assert_eq!(condition("SELECT * FROM t WHERE email IS NULL"), Condition::IsNull("email".to_string()));
+        assert_eq!(condition("SELECT * FROM t WHERE key is not null"), Condition::IsNotNull("key".to_string()));
+        assert_eq!(
+            condition("SELECT * FROM t WHERE email IS NOT NULL AND key > 'a'").to_string(),
+            "(email IS NOT NULL AND key > 'a')"
+        );
+        assert!(SQLParser::new("SELECT * FROM t WHERE key IS 'a'").parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_explain() {
+        let statement = SQLParser::new("explain SELECT * FROM t WHERE key = 'x'").parse().unwrap();
+        match &statement {
+            Statement::Explain(inner) => assert!(matches!(**inner, Statement::Select(_))),
+            _ => panic!("Expected EXPLAIN statement"),
+        }
+        assert_eq!(statement.to_string(), "EXPLAIN SELECT * FROM t WHERE key = 'x'");
+        assert!(SQLParser::new("EXPLAIN").parse().is_err());
+        assert!(SQLParser::new("EXPLAIN EXPLAIN SELECT * FROM t").parse().is_err());
+    }
+
     #[test]
     fn test_parse_select() {
         let mut parser = SQLParser::new("SELECT name, age FROM users WHERE id = 1 LIMIT 10");
@@ -377,4 +559,28 @@ mod tests {
             panic!("Expected DELETE statement");
         }
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_parse_date_literals() {
+        let condition = |sql: &str| match SQLParser::new(sql).parse().unwrap() {
+            Statement::Select(select) => select.where_clause.unwrap().condition,
+            _ => panic!("Expected SELECT statement"),
+        };
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        assert_eq!(
+            condition("SELECT * FROM t WHERE key < '2024-01-01'"),
+            Condition::LessThan("key".to_string(), Value::Date(date))
+        );
+        assert_eq!(condition("SELECT * FROM t WHERE key < '2024-01-01'").to_string(), "key < '2024-01-01'");
+
+        // Anything that isn't exactly a valid YYYY-MM-DD date stays a string
+        for text in ["2024-1-1", "2024-02-30", "2024-01-01x", "not a date"] {
+            assert_eq!(
+                condition(&format!("SELECT * FROM t WHERE key = '{}'", text)),
+                Condition::Equals("key".to_string(), Value::String(text.to_string()))
+            );
+        }
+    }
+}
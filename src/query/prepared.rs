@@ -0,0 +1,200 @@
+// Prepared statements - parse SQL once, then execute it repeatedly with new parameter values
+//
+// Placeholders are `?` (filled by position) or `:name` (filled by position or by name).
+// Each execution substitutes the bound values into a copy of the parsed statement, so the
+// tokenizer and parser only ever run once per statement.
+
+use crate::query::ast::*;
+use crate::query::executor::{QueryExecutor, QueryResult};
+use crate::query::parser::SQLParser;
+use crate::{DbError, DbResult};
+
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    statement: Statement,
+    parameters: Vec<Option<String>>, // Placeholder names by slot, None for `?`
+    bound: Vec<Option<Value>>,
+}
+
+impl PreparedStatement {
+    pub fn new(sql: &str) -> DbResult<Self> {
+        let mut parser = SQLParser::new(sql);
+        let statement = parser.parse()?;
+        let parameters = parser.parameters().to_vec();
+        Ok(Self {
+            statement,
+            bound: vec![None; parameters.len()],
+            parameters,
+        })
+    }
+
+    pub fn parameter_count(&self) -> usize {
+        self.parameters.len()
+    }
+
+    // Bind every placeholder at once, in the order they appear in the statement
+    pub fn bind(&mut self, params: Vec<Value>) -> DbResult<&mut Self> {
+        if params.len() != self.parameters.len() {
+            return Err(DbError::InvalidQuery(format!(
+                "Statement takes {} parameters, got {}",
+                self.parameters.len(),
+                params.len()
+            )));
+        }
+        for (slot, value) in params.into_iter().enumerate() {
+            self.bound[slot] = Some(Self::check_value(value)?);
+        }
+        Ok(self)
+    }
+
+    // Bind the placeholder written as `:name`
+    pub fn bind_named(&mut self, name: &str, value: Value) -> DbResult<&mut Self> {
+        let slot = self
+            .parameters
+            .iter()
+            .position(|p| p.as_deref() == Some(name))
+            .ok_or_else(|| DbError::InvalidQuery(format!("Unknown query parameter :{}", name)))?;
+        self.bound[slot] = Some(Self::check_value(value)?);
+        Ok(self)
+    }
+
+    // Run the statement with the currently bound values. Bindings are kept, so a later
+    // call only needs to rebind what changed
+    pub fn execute(&self, executor: &mut QueryExecutor) -> DbResult<QueryResult> {
+        executor.execute(self.bind_statement(&self.statement)?)
+    }
+
+    fn check_value(value: Value) -> DbResult<Value> {
+        match value {
+            Value::Param(_) => Err(DbError::InvalidQuery(
+                "A query parameter can't be bound to another placeholder".to_string(),
+            )),
+            value => Ok(value),
+        }
+    }
+
+    fn bind_statement(&self, statement: &Statement) -> DbResult<Statement> {
+        Ok(match statement {
+            Statement::Select(select) => Statement::Select(SelectStatement {
+                where_clause: self.bind_where(&select.where_clause)?,
+                ..select.clone()
+            }),
+            Statement::Insert(insert) => Statement::Insert(InsertStatement {
+                values: insert.values.iter().map(|v| self.bind_value(v)).collect::<DbResult<_>>()?,
+                ..insert.clone()
+            }),
+            Statement::Delete(delete) => Statement::Delete(DeleteStatement {
+                where_clause: self.bind_where(&delete.where_clause)?,
+                ..delete.clone()
+            }),
+            Statement::Explain(statement) => Statement::Explain(Box::new(self.bind_statement(statement)?)),
+        })
+    }
+
+    fn bind_where(&self, where_clause: &Option<WhereClause>) -> DbResult<Option<WhereClause>> {
+        where_clause
+            .as_ref()
+            .map(|w| Ok(WhereClause { condition: self.bind_condition(&w.condition)? }))
+            .transpose()
+    }
+
+    fn bind_condition(&self, condition: &Condition) -> DbResult<Condition> {
+        Ok(match condition {
+            Condition::Equals(col, val) => Condition::Equals(col.clone(), self.bind_value(val)?),
+            Condition::NotEquals(col, val) => Condition::NotEquals(col.clone(), self.bind_value(val)?),
+            Condition::GreaterThan(col, val) => Condition::GreaterThan(col.clone(), self.bind_value(val)?),
+            Condition::LessThan(col, val) => Condition::LessThan(col.clone(), self.bind_value(val)?),
+            Condition::GreaterThanOrEqual(col, val) => {
+                Condition::GreaterThanOrEqual(col.clone(), self.bind_value(val)?)
+            }
+            Condition::LessThanOrEqual(col, val) => {
+                Condition::LessThanOrEqual(col.clone(), self.bind_value(val)?)
+            }
+            Condition::Like(col, pattern) => Condition::Like(col.clone(), pattern.clone()),
+            Condition::IsNull(col) => Condition::IsNull(col.clone()),
+            Condition::IsNotNull(col) => Condition::IsNotNull(col.clone()),
+            Condition::And(left, right) => {
+                Condition::And(Box::new(self.bind_condition(left)?), Box::new(self.bind_condition(right)?))
+            }
+            Condition::Or(left, right) => {
+                Condition::Or(Box::new(self.bind_condition(left)?), Box::new(self.bind_condition(right)?))
+            }
+        })
+    }
+
+    fn bind_value(&self, value: &Value) -> DbResult<Value> {
+        match value {
+            Value::Param(slot) => self.bound[*slot].clone().ok_or_else(|| {
+                let name = match &self.parameters[*slot] {
+                    Some(name) => format!(":{}", name),
+                    None => format!("#{}", slot + 1),
+                };
+                DbError::InvalidQuery(format!("Query parameter {} was not bound", name))
+            }),
+            value => Ok(value.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{LSMConfig, LSMTree};
+    use tempfile::tempdir;
+
+    fn test_tree(dir: &std::path::Path) -> LSMTree {
+        LSMTree::with_config(LSMConfig {
+            data_dir: dir.join("db"),
+            background_compaction: false,
+            enable_wal: false,
+            ..LSMConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn selected_value(result: QueryResult) -> Option<String> {
+        match result {
+            QueryResult::Select(mut records) => records.pop().map(|mut r| r.remove("value").unwrap()),
+            _ => panic!("Expected SELECT result"),
+        }
+    }
+
+    #[test]
+    fn test_prepared_select_runs_with_many_bindings() {
+        let temp_dir = tempdir().unwrap();
+        let mut tree = test_tree(temp_dir.path());
+        for i in 0..50 {
+            tree.insert(format!("key{}", i), format!("value{}", i)).unwrap();
+        }
+        let mut executor = QueryExecutor::new(&mut tree);
+
+        let mut select = QueryExecutor::prepare("SELECT * FROM t WHERE key = ?").unwrap();
+        assert_eq!(select.parameter_count(), 1);
+        for i in 0..50 {
+            select.bind(vec![Value::String(format!("key{}", i))]).unwrap();
+            assert_eq!(selected_value(select.execute(&mut executor).unwrap()), Some(format!("value{}", i)));
+        }
+        select.bind(vec![Value::String("missing".to_string())]).unwrap();
+        assert_eq!(selected_value(select.execute(&mut executor).unwrap()), None);
+    }
+
+    #[test]
+    fn test_named_parameters() {
+        let temp_dir = tempdir().unwrap();
+        let mut tree = test_tree(temp_dir.path());
+        let mut executor = QueryExecutor::new(&mut tree);
+
+        let mut insert = QueryExecutor::prepare("INSERT INTO t (key, value) VALUES (:key, :value)").unwrap();
+        assert!(insert.execute(&mut executor).is_err()); // Nothing bound yet
+        insert
+            .bind_named("key", Value::String("user1".to_string())).unwrap()
+            .bind_named("value", Value::Number(7.0)).unwrap();
+        insert.execute(&mut executor).unwrap();
+        assert!(insert.bind_named("missing", Value::Null).is_err());
+        assert!(insert.bind(vec![Value::Null]).is_err());
+
+        let mut select = QueryExecutor::prepare("SELECT * FROM t WHERE key = :key").unwrap();
+        select.bind_named("key", Value::String("user1".to_string())).unwrap();
+        assert_eq!(selected_value(select.execute(&mut executor).unwrap()), Some("7".to_string()));
+    }
+}
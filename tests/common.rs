@@ -18,6 +18,7 @@ pub fn create_test_lsm() -> (LSMTree, TempDir) {
         background_compaction: false,
         background_compaction_interval: std::time::Duration::from_secs(10),
         enable_wal: true,
+        ..LSMConfig::default()
     };
     
     let lsm_tree = LSMTree::with_config(config).expect("Failed to create LSM tree");
@@ -94,8 +95,6 @@ pub fn generate_test_data(count: usize, prefix: &str) -> Vec<(String, String)> {
 }
 
 // Memory usage monitoring helper
-pub fn get_memory_usage() -> usize {
-    // Simple approximation - in a real implementation we'd use a proper memory profiler
-    // For now, we'll use a placeholder that could be extended with actual memory monitoring
-    std::mem::size_of::<LSMTree>()
+pub fn get_memory_usage(lsm_tree: &LSMTree) -> usize {
+    lsm_tree.approx_memory_bytes()
 }
\ No newline at end of file
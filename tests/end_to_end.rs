@@ -129,6 +129,7 @@ fn test_wal_recovery_query_verify() {
         background_compaction: false,
         background_compaction_interval: std::time::Duration::from_secs(10),
         enable_wal: true,
+        ..LSMConfig::default()
     };
     
     // Insert data and close database
@@ -254,4 +255,70 @@ fn test_error_recovery_csv_loading() {
         }
         _ => panic!("Expected Select result"),
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_load_command_uses_requested_columns() {
+    use rust_solo_all_db::config::DatabaseConfig;
+    use std::process::Command;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let csv_path = temp_dir.path().join("users.csv");
+    std::fs::write(&csv_path, "id,name,email\n1,Alice,alice@example.com\n2,Bob,bob@example.com\n").unwrap();
+
+    let mut config = DatabaseConfig::default();
+    config.storage.data_dir = temp_dir.path().join("db");
+    let config_path = temp_dir.path().join("db.yaml");
+    config.save_to_file(&config_path).unwrap();
+
+    // Key and value by index, with the loader settings overridden
+    let output = Command::new(env!("CARGO_BIN_EXE_rustdb"))
+        .arg("--config").arg(&config_path)
+        .arg("load").arg(&csv_path)
+        .args(["--key-column", "1", "--value-column", "0", "--threads", "2", "--batch-size", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "load failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Then by header name
+    let output = Command::new(env!("CARGO_BIN_EXE_rustdb"))
+        .arg("--config").arg(&config_path)
+        .arg("load").arg(&csv_path)
+        .args(["--key-column", "id", "--value-column", "email"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "load failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Or mixed: the default key column 0 by index, the value by name
+    let output = Command::new(env!("CARGO_BIN_EXE_rustdb"))
+        .arg("--config").arg(&config_path)
+        .arg("load").arg(&csv_path)
+        .args(["--value-column", "name"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "load failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // And the value by index with the key by name, overwriting the mixed load's values
+    let output = Command::new(env!("CARGO_BIN_EXE_rustdb"))
+        .arg("--config").arg(&config_path)
+        .arg("load").arg(&csv_path)
+        .args(["--key-column", "id", "--value-column", "2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "load failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let lsm_tree = LSMTree::open_existing(config.to_lsm_config()).unwrap();
+    assert_eq!(lsm_tree.get("1").unwrap(), Some("alice@example.com".to_string()));
+    assert_eq!(lsm_tree.get("2").unwrap(), Some("bob@example.com".to_string()));
+    assert_eq!(lsm_tree.get("Alice").unwrap(), Some("1".to_string()));
+    drop(lsm_tree);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustdb"))
+        .arg("--config").arg(&config_path)
+        .arg("load").arg(&csv_path)
+        .args(["--key-column", "id", "--value-column", "phone"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("phone"));
+}
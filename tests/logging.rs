@@ -0,0 +1,87 @@
+// Engine output goes through the `log` facade: a flush is reported as an info event
+// and nothing is printed to stdout
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use rust_solo_all_db::engine::{LSMConfig, LSMTree};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+use tempfile::tempdir;
+
+const CHILD_ENV: &str = "RUSTDB_LOGGING_TEST_CHILD";
+
+struct CapturingLogger {
+    events: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.events.lock().unwrap().push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger {
+    events: Mutex::new(Vec::new()),
+};
+
+fn flush_and_check_events() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+
+    let temp_dir = tempdir().unwrap();
+    let config = LSMConfig {
+        memtable_size_limit: 100,
+        data_dir: temp_dir.path().to_path_buf(),
+        background_compaction: false,
+        background_compaction_interval: Duration::from_secs(1),
+        enable_wal: true,
+        ..LSMConfig::default()
+    };
+    let mut lsm = LSMTree::with_config(config).unwrap();
+    for i in 0..10 {
+        lsm.insert(format!("key{}", i), format!("value{}", i)).unwrap();
+    }
+    lsm.flush().unwrap();
+
+    let events = LOGGER.events.lock().unwrap();
+    assert!(
+        events.iter().any(|(level, msg)| *level == Level::Info && msg.contains("Flushed MemTable")),
+        "no info-level flush event in {:?}",
+        events
+    );
+}
+
+#[test]
+fn test_flush_logs_info_event_without_stdout() {
+    // The child does the work; the parent checks what the child wrote to stdout.
+    // The test harness captures println! in-process, so only a real process shows it
+    if std::env::var_os(CHILD_ENV).is_some() {
+        flush_and_check_events();
+        return;
+    }
+
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["test_flush_logs_info_event_without_stdout", "--exact", "--nocapture", "--test-threads=1"])
+        .env(CHILD_ENV, "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "child failed:\n{}\n{}", stdout, String::from_utf8_lossy(&output.stderr));
+
+    // Only the harness's own lines. Anything the engine printed would land between
+    // "test <name> ... " and "ok", breaking up the result line
+    let result_line = "test test_flush_logs_info_event_without_stdout ... ok";
+    for line in stdout.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        assert!(
+            line.starts_with("running ") || line.starts_with("test result: ") || line == result_line,
+            "unexpected stdout line: {}",
+            line
+        );
+    }
+}
@@ -0,0 +1,48 @@
+// Tests for code using the procedural macros
+
+use rust_solo_all_db::engine::{LSMConfig, LSMTree};
+use rust_solo_all_db::query::QueryResult;
+use rust_solo_all_db_macros::query;
+use tempfile::tempdir;
+
+#[test]
+fn test_macro_usage_example_compiles() {
+    let t = trybuild::TestCases::new();
+    t.pass("examples/macro_usage.rs");
+    t.compile_fail("tests/ui/table_name_not_a_string.rs");
+    t.compile_fail("tests/ui/indexed_id_field.rs");
+}
+
+fn selected_values(result: QueryResult) -> Vec<String> {
+    match result {
+        QueryResult::Select(records) => records.into_iter().map(|mut r| r.remove("value").unwrap()).collect(),
+        _ => panic!("Expected SELECT result"),
+    }
+}
+
+#[test]
+fn test_query_macro_binds_local_variables() {
+    let temp_dir = tempdir().unwrap();
+    let config = LSMConfig {
+        data_dir: temp_dir.path().join("db"),
+        background_compaction: false,
+        enable_wal: false,
+        ..LSMConfig::default()
+    };
+    let mut db = LSMTree::with_config(config).unwrap();
+    db.insert("user1".to_string(), "Alice".to_string()).unwrap();
+
+    let user_id = "user1";
+    let result = query!(db, "SELECT * FROM t WHERE key = {user_id}").unwrap();
+    assert_eq!(selected_values(result), vec!["Alice"]);
+
+    // Values are quoted, so quotes in them stay part of the literal
+    let key = String::from("o'brien");
+    let count = 42;
+    query!(db, "INSERT INTO t (key, value) VALUES ({key}, {count})").unwrap();
+    assert_eq!(db.get("o'brien").unwrap(), Some("42".to_string()));
+
+    let user_id = "nobody' OR key = 'user1";
+    let result = query!(db, "SELECT * FROM t WHERE key = {user_id}").unwrap();
+    assert!(selected_values(result).is_empty());
+}
@@ -120,7 +120,7 @@ fn test_compaction_performance() {
 fn test_memory_usage_under_load() {
     let (mut lsm_tree, _temp_dir) = create_test_lsm();
     
-    let initial_memory = get_memory_usage();
+    let initial_memory = get_memory_usage(&lsm_tree);
     
     // Insert data in batches and monitor memory
     let batch_size = 1000;
@@ -134,7 +134,7 @@ fn test_memory_usage_under_load() {
             lsm_tree.insert(key, value).expect("Failed to insert");
         }
         
-        let current_memory = get_memory_usage();
+        let current_memory = get_memory_usage(&lsm_tree);
         println!("Batch {}: Memory usage: {} bytes", batch, current_memory);
         
         // Memory should grow reasonably (not exponentially)
@@ -144,7 +144,7 @@ fn test_memory_usage_under_load() {
     
     // Force compaction and check memory
     lsm_tree.compact().expect("Failed to compact");
-    let post_compaction_memory = get_memory_usage();
+    let post_compaction_memory = get_memory_usage(&lsm_tree);
     println!("Post-compaction memory: {} bytes", post_compaction_memory);
     
     // Verify data integrity
@@ -201,6 +201,78 @@ fn test_csv_loading_performance() {
     }
 }
 
+#[test]
+fn test_batched_csv_loading_beats_per_key_locked_inserts() {
+    use parking_lot::Mutex;
+    use rayon::prelude::*;
+
+    let record_count = 20000;
+    let test_data: Vec<(String, String)> = (0..record_count)
+        .map(|i| (format!("csv_key_{:06}", i), format!("csv_value_{:06}", i)))
+        .collect();
+
+    // A MemTable big enough to never flush, so only the insert path itself is timed
+    let create_tree = || {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = LSMConfig {
+            memtable_size_limit: record_count * 2,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            enable_wal: true,
+            ..LSMConfig::default()
+        };
+        (LSMTree::with_config(config).unwrap(), temp_dir)
+    };
+
+    // The loader's old approach: parallel chunks that all queue on one lock, one insert
+    // (and one WAL write) per key
+    let (mut per_key_tree, _per_key_dir) = create_tree();
+    let (_, per_key_duration) = measure_time(|| {
+        let tree = Mutex::new(&mut per_key_tree);
+        test_data.par_chunks(1000).for_each(|chunk| {
+            let mut tree = tree.lock();
+            for (key, value) in chunk {
+                tree.insert(key.clone(), value.clone()).expect("Failed to insert");
+            }
+        });
+    });
+
+    let (mut batched_tree, temp_dir) = create_tree();
+    let test_data_refs: Vec<(&str, &str)> = test_data.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    let csv_path = create_test_csv(&temp_dir, "batched.csv", &test_data_refs);
+    let loader = ETLLoader::with_config(1000, 4);
+
+    // Parsing is shared work the old approach didn't pay for above, so it's timed separately
+    let (_, parse_duration) = measure_time(|| {
+        rust_solo_all_db::etl::CSVParser::new(0, 1).parse_records(std::fs::File::open(&csv_path).unwrap()).unwrap()
+    });
+    let (count, load_duration) = measure_time(|| {
+        loader.load_csv(&csv_path, &mut batched_tree, 0, 1).expect("Failed to load CSV")
+    });
+    assert_eq!(count, record_count);
+    let batched_duration = load_duration.saturating_sub(parse_duration);
+
+    let per_key_throughput = record_count as f64 / per_key_duration.as_secs_f64();
+    let batched_throughput = record_count as f64 / batched_duration.as_secs_f64().max(f64::EPSILON);
+    println!(
+        "Per-key locked inserts: {:.2} records/second, batched load: {:.2} records/second",
+        per_key_throughput, batched_throughput
+    );
+
+    // The saving comes from WAL writes: one per batch instead of one per key. Wall-clock time
+    // shares the machine with the other tests, so it only has to stay in the same league
+    assert_eq!(per_key_tree.wal_flush_count(), record_count as u64);
+    assert!(batched_tree.wal_flush_count() <= (record_count / 1000) as u64);
+    assert!(
+        batched_duration < per_key_duration * 2,
+        "Batched load ({:?}) should not trail per-key locked inserts ({:?})", batched_duration, per_key_duration
+    );
+
+    for i in (0..record_count).step_by(997) {
+        assert_eq!(batched_tree.get(&format!("csv_key_{:06}", i)).unwrap(), Some(format!("csv_value_{:06}", i)));
+    }
+}
+
 #[test] 
 fn test_concurrent_read_write_performance() {
     use std::sync::{Arc, Mutex};
@@ -261,4 +333,76 @@ fn test_concurrent_read_write_performance() {
     // Assert reasonable concurrent performance
     assert!(throughput > 100.0, 
         "Concurrent throughput too low: {:.2} ops/second", throughput);
-}
\ No newline at end of file
+}
+// Read syscalls made by this process so far, where the platform exposes it
+fn read_syscall_count() -> Option<u64> {
+    let io = std::fs::read_to_string("/proc/self/io").ok()?;
+    io.lines()
+        .find_map(|line| line.strip_prefix("syscr:"))
+        .and_then(|count| count.trim().parse().ok())
+}
+
+#[test]
+fn test_mmap_read_performance() {
+    let mut results = Vec::new();
+
+    for use_mmap in [false, true] {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp directory");
+        let config = LSMConfig {
+            memtable_size_limit: 500,
+            data_dir: temp_dir.path().join("db"),
+            background_compaction: false,
+            background_compaction_interval: Duration::from_secs(10),
+            enable_wal: false,
+            use_mmap,
+            block_cache_bytes: 0, // Measure the raw read paths
+            ..LSMConfig::default()
+        };
+        let mut lsm_tree = LSMTree::with_config(config).expect("Failed to create LSM tree");
+
+        for i in 0..2000 {
+            lsm_tree.insert(format!("mmap_key_{:05}", i), format!("mmap_value_{:05}", i)).expect("Failed to insert");
+        }
+        lsm_tree.flush().expect("Failed to flush");
+
+        let syscalls_before = read_syscall_count();
+        let (values, duration) = measure_time(|| {
+            (0..2000)
+                .step_by(7)
+                .map(|i| lsm_tree.get(&format!("mmap_key_{:05}", i)).expect("Failed to get"))
+                .collect::<Vec<_>>()
+        });
+        let syscalls = syscalls_before.zip(read_syscall_count()).map(|(before, after)| after - before);
+
+        println!(
+            "{} reads: {} gets in {:?} (read syscalls: {})",
+            if use_mmap { "mmap" } else { "buffered" },
+            values.len(),
+            duration,
+            syscalls.map(|n| n.to_string()).unwrap_or_else(|| "n/a".to_string())
+        );
+        results.push(values);
+
+        // The mapped path never goes back to the file: with the SSTables moved out of the data
+        // directory, mapped gets still succeed, while buffered ones reopen the file and fail
+        let moved_to = temp_dir.path().join("moved");
+        std::fs::create_dir(&moved_to).expect("Failed to create directory");
+        for entry in std::fs::read_dir(temp_dir.path().join("db")).expect("Failed to read data directory") {
+            let path = entry.expect("Failed to read entry").path();
+            if path.extension().is_some_and(|ext| ext == "sst") {
+                std::fs::rename(&path, moved_to.join(path.file_name().unwrap())).expect("Failed to move SSTable");
+            }
+        }
+        for i in (0..2000).step_by(7) {
+            let read = lsm_tree.get(&format!("mmap_key_{:05}", i));
+            if use_mmap {
+                assert_eq!(read.expect("A mapped read went back to the file"), Some(format!("mmap_value_{:05}", i)));
+            } else {
+                assert!(read.is_err(), "buffered read of mmap_key_{:05} didn't reopen the file", i);
+            }
+        }
+    }
+
+    assert_eq!(results[0], results[1]);
+    assert!(results[1].iter().all(|value| value.is_some()));
+}
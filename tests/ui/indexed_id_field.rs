@@ -0,0 +1,12 @@
+use rust_solo_all_db_macros::QueryBuilder;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, QueryBuilder)]
+struct Session {
+    #[primary_key]
+    token: String,
+    #[indexed]
+    id: String,
+}
+
+fn main() {}
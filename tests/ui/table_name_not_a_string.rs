@@ -0,0 +1,11 @@
+use rust_solo_all_db_macros::QueryBuilder;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, QueryBuilder)]
+#[table_name(users)]
+struct User {
+    #[primary_key]
+    id: String,
+}
+
+fn main() {}